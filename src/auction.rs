@@ -0,0 +1,152 @@
+use crate::error::Error;
+use crate::utils::{execute, is_below_min_volume, rested_since};
+use crate::{OrderExecutionResult, Orderbook};
+use orderbook_primitives::types::{OrderSide, OrderStatus, TradingPair};
+use rust_decimal::Decimal;
+
+impl Orderbook {
+    /// Returns the price the crossed books of the pair clear at in a call auction: the price
+    /// executing the most volume, then leaving the smallest imbalance, then the lowest one.
+    /// Returns `None` if the books do not cross.
+    pub fn auction_clearing_price(&self, pair: &TradingPair) -> Option<Decimal> {
+        let bids = self.book_levels(pair, OrderSide::Bid);
+        let asks = self.book_levels(pair, OrderSide::Ask);
+        let mut best: Option<(Decimal, Decimal, Decimal)> = None;
+        for (price, _) in bids.iter().chain(asks.iter()) {
+            let demand: Decimal = bids
+                .iter()
+                .filter(|(bid, _)| bid >= price)
+                .map(|(_, qty)| *qty)
+                .sum();
+            let supply: Decimal = asks
+                .iter()
+                .filter(|(ask, _)| ask <= price)
+                .map(|(_, qty)| *qty)
+                .sum();
+            let volume = demand.min(supply);
+            let imbalance = demand.saturating_sub(supply).abs();
+            if volume.is_zero() {
+                continue;
+            }
+            let better = best.is_none_or(|(best_price, best_volume, best_imbalance)| {
+                (volume, best_imbalance, best_price) > (best_volume, imbalance, *price)
+            });
+            if better {
+                best = Some((*price, volume, imbalance));
+            }
+        }
+        best.map(|(price, _, _)| price)
+    }
+
+    /// Uncrosses the books of the pair at their clearing price. Orders trade in priority order
+    /// of their side, the later order of every trade takes the liquidity of the earlier one.
+    /// Orders left with less than the min volume are closed. Like in continuous matching, if
+    /// the pair cancels self trades, the earlier of two crossing orders of the same account is
+    /// cancelled instead of traded.
+    pub fn run_auction(
+        &mut self,
+        pair: TradingPair,
+        stid: u64,
+        now: u64,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        let config = self
+            .get_pair_config(&pair)
+            .cloned()
+            .ok_or(Error::TradingPairConfigNotFound)?;
+        // State of prepared executions can not change until they are committed or rolled back
+        self.ensure_pair_not_frozen(&pair)?;
+        let mut changes = OrderExecutionResult::new(stid);
+        let Some(price) = self.auction_clearing_price(&pair) else {
+            return Ok(changes);
+        };
+        let settings = self.pair_settings(&pair);
+        let mut bids = self.bid_books.remove(&pair).unwrap_or_default();
+        let mut asks = self.ask_books.remove(&pair).unwrap_or_default();
+        let mut self_trades = Vec::new();
+        while bids.peek().is_some_and(|bid| bid.price >= price)
+            && asks.peek().is_some_and(|ask| ask.price <= price)
+        {
+            let (Some(mut bid), Some(mut ask)) = (bids.pop(), asks.pop()) else {
+                break;
+            };
+            // The order resting last takes
+            let bid_takes =
+                rested_since(&bid, &self.rested_at) >= rested_since(&ask, &self.rested_at);
+            if settings.cancel_on_self_trade && bid.main_account == ask.main_account {
+                let (maker, taker) = if bid_takes { (ask, bid) } else { (bid, ask) };
+                self.count_open_order(&maker.main_account, false);
+                self.rested_at.remove(&maker.id);
+                self_trades.push(maker);
+                match taker.side {
+                    OrderSide::Bid => bids.push(taker),
+                    OrderSide::Ask => asks.push(taker),
+                }
+                continue;
+            }
+            let trade = if bid_takes {
+                execute(&mut bid, &mut ask, price, config.qty_step_size)
+            } else {
+                execute(&mut ask, &mut bid, price, config.qty_step_size)
+            };
+            let Some(mut trade) = trade else {
+                bids.push(bid);
+                asks.push(ask);
+                break;
+            };
+            for order in [&mut trade.maker, &mut trade.taker] {
+                if is_below_min_volume(order.available_volume(None), &config) {
+                    order.status = OrderStatus::CLOSED;
+                }
+            }
+            for mut order in [bid, ask] {
+                self.reduce_from_pricelevel(
+                    &config,
+                    pair,
+                    order.price,
+                    trade.amount,
+                    order.side,
+                    &mut changes.pricelevels,
+                );
+                if is_below_min_volume(order.available_volume(None), &config) {
+                    order.status = OrderStatus::CLOSED;
+                }
+                order.stid = stid;
+                changes.modified_orders.insert(order.id, order.clone());
+                if order.status == OrderStatus::CLOSED {
+                    self.count_open_order(&order.main_account, false);
+                    self.rested_at.remove(&order.id);
+                } else if order.side == OrderSide::Bid {
+                    bids.push(order);
+                } else {
+                    asks.push(order);
+                }
+            }
+            changes.trades.push(trade);
+        }
+        self.bid_books.insert(pair, bids);
+        self.ask_books.insert(pair, asks);
+        log::info!(target:"engine","Auction of {:?} cleared {:?} trades at {:?}",pair,changes.trades.len(),price);
+        self.settle_trades(config, now, &mut changes);
+        // Both sides of an auction trade rested, filled rungs taking in it post their next rung
+        // like the makers do
+        for trade in &changes.trades {
+            let taker = &trade.taker;
+            if taker.status == OrderStatus::CLOSED
+                && self.ladders.contains_key(&taker.id)
+                && !self.triggered_rungs.contains(&taker.id)
+            {
+                self.triggered_rungs.push(taker.id);
+            }
+        }
+        for maker in self_trades {
+            self.cancel_resting_order(maker, &mut changes);
+        }
+        self.release_closed_order_residuals(&mut changes);
+        // Post the next rungs of ladders filled in the auction
+        self.advance_ladders(stid, &mut changes);
+        self.aggregate_display_levels(&mut changes);
+        self.cap_published_levels(&mut changes);
+        self.round_published_avg_prices(&mut changes);
+        Ok(changes)
+    }
+}
@@ -0,0 +1,139 @@
+use crate::utils::{trade_price, will_orders_match};
+use crate::Orderbook;
+use codec::{Decode, Encode};
+use orderbook_primitives::types::{Order, OrderSide, OrderType, Trade, TradingPair};
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::Decimal;
+use sp_core::hashing::blake2_256;
+use sp_core::H256;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+/// Full state of a single pair for offline analysis of matching anomalies.
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+pub struct PairStateDump {
+    pub pair: TradingPair,
+    // Resting bids, best first
+    pub bids: Vec<Order>,
+    // Resting asks, best first
+    pub asks: Vec<Order>,
+    // (side, price) => qty of the pair's price levels
+    pub price_levels: BTreeMap<(OrderSide, Decimal), Decimal>,
+    // Base and quote balances of the accounts with resting orders on the pair
+    pub balances: BTreeMap<(AccountId, AssetId), (Decimal, Decimal)>,
+}
+
+impl Orderbook {
+    /// Verifies that a trade is consistent with the orders it was generated from.
+    /// The maker and taker of a trade are the order states right after the trade.
+    pub fn verify_trade(&self, trade: &Trade) -> Result<(), String> {
+        let Trade {
+            maker,
+            taker,
+            price,
+            amount,
+            ..
+        } = trade;
+        let Some(config) = self.trading_pairs.get(&maker.pair) else {
+            return Err("trading pair is not registered".to_string());
+        };
+        if maker.pair != taker.pair || maker.side == taker.side {
+            return Err("maker and taker are not on opposite sides of a pair".to_string());
+        }
+        if maker.order_type != OrderType::LIMIT {
+            return Err("maker is not a limit order".to_string());
+        }
+        let price_mode = self.pair_settings(&maker.pair).price_mode;
+        let expected_price = trade_price(taker, maker, price_mode, config.price_tick_size);
+        if *price != expected_price {
+            return Err(format!(
+                "trade price {price:?} is not the expected price {expected_price:?}"
+            ));
+        }
+        if amount.is_sign_negative() || amount.is_zero() {
+            return Err("trade amount is not positive".to_string());
+        }
+        // Filled quantities include this trade, so neither order can be over filled.
+        if maker.filled_quantity > maker.qty || *amount > maker.filled_quantity {
+            return Err("trade amount exceeds maker quantity".to_string());
+        }
+        let quote_budget = taker.side == OrderSide::Bid
+            && taker.order_type == OrderType::MARKET
+            && taker.qty.is_zero();
+        if quote_budget {
+            if price.saturating_mul(*amount) > taker.quote_order_qty {
+                return Err("trade volume exceeds taker quote budget".to_string());
+            }
+        } else if taker.filled_quantity > taker.qty || *amount > taker.filled_quantity {
+            return Err("trade amount exceeds taker quantity".to_string());
+        }
+        if !will_orders_match(taker, maker) {
+            return Err("taker and maker prices do not cross".to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns a commitment to the full engine state. It hashes the canonical encoding of the
+    /// trading pairs, the resting orders in priority order, balances, price levels and fee
+    /// structures, so two engines in the same logical state have the same root.
+    pub fn state_root(&self) -> H256 {
+        let mut state = Vec::new();
+        self.trading_pairs.encode_to(&mut state);
+        for books in [&self.bid_books, &self.ask_books] {
+            for (pair, book) in books {
+                // Sorted vec is in ascending priority, so it is reversed to start with the best
+                let orders: Vec<Order> = book.clone().into_sorted_vec().into_iter().rev().collect();
+                (pair, orders).encode_to(&mut state);
+            }
+        }
+        self.balances.encode_to(&mut state);
+        self.pricelevels.encode_to(&mut state);
+        for (main, fee) in &self.fees_collector.fee_structure {
+            (
+                main,
+                fee.maker_fraction,
+                fee.taker_fraction,
+                fee.priority_boost,
+                fee.flat_fee,
+            )
+                .encode_to(&mut state);
+        }
+        H256::from(blake2_256(&state))
+    }
+
+    /// Dumps the resting orders in priority order, the price levels and the balances of the
+    /// involved accounts of the pair.
+    pub fn dump_state(&self, pair: &TradingPair) -> PairStateDump {
+        let best_first = |book: Option<&BinaryHeap<Order>>| -> Vec<Order> {
+            book.map(|book| book.clone().into_sorted_vec().into_iter().rev().collect())
+                .unwrap_or_default()
+        };
+        let bids = best_first(self.bid_books.get(pair));
+        let asks = best_first(self.ask_books.get(pair));
+        let price_levels = self
+            .pricelevels
+            .iter()
+            .filter(|((level_pair, _, _), _)| level_pair == pair)
+            .map(|((_, side, price), qty)| ((*side, *price), *qty))
+            .collect();
+        let accounts: BTreeSet<&AccountId> = bids
+            .iter()
+            .chain(asks.iter())
+            .map(|order| &order.main_account)
+            .collect();
+        let mut balances = BTreeMap::new();
+        for main in accounts {
+            for asset in [pair.base, pair.quote] {
+                if let Some(balance) = self.balances.get(&(main.clone(), asset)) {
+                    balances.insert((main.clone(), asset), *balance);
+                }
+            }
+        }
+        PairStateDump {
+            pair: *pair,
+            bids,
+            asks,
+            price_levels,
+            balances,
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{OrderExecutionResult, Orderbook};
+use orderbook_primitives::types::Order;
+
+/// How a batch of orders continues when one of them fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Stop at the first failing order, the orders after it are not processed
+    #[default]
+    FailFast,
+    /// Skip failing orders and process the rest of the batch
+    BestEffort,
+}
+
+impl Orderbook {
+    /// Processes the orders in sequence at the same stid and returns their merged result
+    /// together with the index and error of every order that failed. A failing order is rolled
+    /// back, the orders processed before it stay applied. In `FailFast` mode the batch stops at
+    /// the first failure.
+    pub fn process_orders(
+        &mut self,
+        orders: Vec<Order>,
+        stid: u64,
+        mode: BatchMode,
+    ) -> (OrderExecutionResult, Vec<(usize, anyhow::Error)>) {
+        let mut result = OrderExecutionResult::new(stid);
+        let mut errors = Vec::new();
+        for (index, order) in orders.into_iter().enumerate() {
+            match self.prepare_order(order, stid) {
+                Ok((prepared, changes)) => {
+                    self.commit(prepared);
+                    result.merge(changes);
+                }
+                Err(err) => {
+                    log::info!(target:"engine","Order {:?} of the batch failed: {:?}",index,err);
+                    errors.push((index, err));
+                    if mode == BatchMode::FailFast {
+                        break;
+                    }
+                }
+            }
+        }
+        (result, errors)
+    }
+}
@@ -0,0 +1,261 @@
+use crate::error::Error;
+use crate::utils::{is_expired, rested_since, resting_reservation};
+use crate::{OrderExecutionResult, Orderbook, ReservationReason};
+use anyhow::anyhow;
+use orderbook_primitives::types::{Order, OrderId, OrderSide, OrderStatus, TradingPair};
+use polkadex_primitives::AccountId;
+use rust_decimal::prelude::Zero;
+use rust_decimal::Decimal;
+use std::collections::BinaryHeap;
+
+impl Orderbook {
+    /// Removes a resting order from its book, returns it if it was found.
+    pub fn take_resting_order(
+        &mut self,
+        pair: &TradingPair,
+        side: OrderSide,
+        order_id: &OrderId,
+    ) -> Option<Order> {
+        let book = match side {
+            OrderSide::Ask => self.ask_books.get_mut(pair)?,
+            OrderSide::Bid => self.bid_books.get_mut(pair)?,
+        };
+        let mut orders = std::mem::take(book).into_vec();
+        let position = orders.iter().position(|order| order.id == *order_id);
+        let taken = position.map(|position| orders.swap_remove(position));
+        *book = BinaryHeap::from(orders);
+        if let Some(order) = &taken {
+            self.count_open_order(&order.main_account, false);
+            self.rested_at.remove(&order.id);
+        }
+        taken
+    }
+
+    /// Cancels a resting order at `now`, in milliseconds. Orders rest since the time of the
+    /// engine clock they were inserted at and are rejected if they did not rest for the min
+    /// resting time of the pair yet. The cancel fee of the pair is charged unless the order
+    /// rested for at least the exempt age.
+    pub fn cancel_order(
+        &mut self,
+        pair: TradingPair,
+        side: OrderSide,
+        order_id: &OrderId,
+        now: u64,
+        stid: u64,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        let book = match side {
+            OrderSide::Ask => self.ask_books.get(&pair),
+            OrderSide::Bid => self.bid_books.get(&pair),
+        };
+        let (rested, main) = book
+            .and_then(|book| book.iter().find(|order| order.id == *order_id))
+            .map(|order| {
+                let rested = rested_since(order, &self.rested_at);
+                (rested, order.main_account.clone())
+            })
+            .ok_or_else(|| anyhow!("order {order_id:?} is not resting in the book"))?;
+        self.ensure_not_frozen(&pair, &main)?;
+        let age = now.saturating_sub(rested);
+        let settings = self.pair_settings(&pair);
+        if settings
+            .min_resting_time
+            .is_some_and(|min_resting_time| age < min_resting_time)
+        {
+            return Err(Error::MinRestingTimeNotMet.into());
+        }
+
+        let order = self
+            .take_resting_order(&pair, side, order_id)
+            .ok_or_else(|| anyhow!("order {order_id:?} is not resting in the book"))?;
+        let mut changes = OrderExecutionResult::new(stid);
+        self.cancel_resting_order(order, &mut changes);
+
+        let exempt = settings
+            .cancel_fee_exempt_age
+            .is_some_and(|exempt_age| age >= exempt_age);
+        if !settings.cancel_fee.is_zero() && !exempt {
+            let charged = self.charge_to_pot(&main, pair.quote, settings.cancel_fee, &mut changes);
+            log::info!(target:"engine","Charged cancel fee: {:?} to {:?}",charged,main);
+        }
+        self.aggregate_display_levels(&mut changes);
+        Ok(changes)
+    }
+
+    /// Cancels all resting orders of the account at the given price level of the pair. Cancel
+    /// fees and the min resting time do not apply, so market makers can refresh whole levels.
+    pub fn cancel_at_price(
+        &mut self,
+        pair: TradingPair,
+        side: OrderSide,
+        price: Decimal,
+        account: &AccountId,
+        stid: u64,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        self.ensure_not_frozen(&pair, account)?;
+        let book = match side {
+            OrderSide::Ask => self.ask_books.get(&pair),
+            OrderSide::Bid => self.bid_books.get(&pair),
+        }
+        .ok_or(Error::TradingPairConfigNotFound)?;
+        let order_ids: Vec<OrderId> = book
+            .iter()
+            .filter(|order| order.price == price && order.main_account == *account)
+            .map(|order| order.id)
+            .collect();
+        let mut changes = OrderExecutionResult::new(stid);
+        for order_id in order_ids {
+            if let Some(order) = self.take_resting_order(&pair, side, &order_id) {
+                self.cancel_resting_order(order, &mut changes);
+            }
+        }
+        self.aggregate_display_levels(&mut changes);
+        Ok(changes)
+    }
+
+    /// Cancels all resting orders of the pair and clears its price levels. Unlike removing the
+    /// pair, its config and settings are kept so it accepts new orders right away. Ladders of
+    /// the pair stop posting rungs. Rejected while an execution of the pair or of an account
+    /// with resting orders is prepared.
+    pub fn reset_pair(
+        &mut self,
+        pair: TradingPair,
+        stid: u64,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        self.ensure_pair_not_frozen(&pair)?;
+        let mut changes = OrderExecutionResult::new(stid);
+        let books = [self.bid_books.get_mut(&pair), self.ask_books.get_mut(&pair)];
+        let orders: Vec<Order> = books
+            .into_iter()
+            .flatten()
+            .flat_map(|book| std::mem::take(book).into_vec())
+            .collect();
+        for order in orders {
+            self.count_open_order(&order.main_account, false);
+            self.rested_at.remove(&order.id);
+            self.cancel_resting_order(order, &mut changes);
+        }
+        // Levels left over by rounding are cleared too
+        let leftover: Vec<_> = self
+            .pricelevels
+            .keys()
+            .filter(|(level_pair, _, _)| *level_pair == pair)
+            .copied()
+            .collect();
+        for level in leftover {
+            self.pricelevels.remove(&level);
+            changes.pricelevels.insert(level, Decimal::zero());
+        }
+        self.aggregate_display_levels(&mut changes);
+        log::info!(target:"engine","Reset pair {:?}",pair);
+        Ok(changes)
+    }
+
+    /// Cancels an order taken out of the book. The reservation of its unfilled part is
+    /// released and its volume is removed from the price level. A cancelled ladder rung ends
+    /// its ladder.
+    pub fn cancel_resting_order(&mut self, order: Order, changes: &mut OrderExecutionResult) {
+        self.ladders.remove(&order.id);
+        self.release_resting_order(order, OrderStatus::CANCELLED, changes);
+    }
+
+    // Releases the unfilled part of an order taken out of the book and records it with `status`.
+    // Only the reservation of the unfilled quantity is released, fees paid on the filled part
+    // stay with the pot whatever `order.fee` holds.
+    fn release_resting_order(
+        &mut self,
+        mut order: Order,
+        status: OrderStatus,
+        changes: &mut OrderExecutionResult,
+    ) {
+        let unfilled = order.qty.saturating_sub(order.filled_quantity);
+        // Orders put into the book without being reserved release what their unfilled part needs
+        self.release_reserved_fees(&order.id, &order.main_account, changes);
+        let mut reservation = self.take_order_reservation(&order.id);
+        if reservation.is_empty() {
+            reservation.extend([resting_reservation(&order)]);
+        }
+        for (asset, amount) in reservation {
+            self.unreserve_balance(
+                amount,
+                asset,
+                order.main_account.clone(),
+                ReservationReason::Cancel,
+                changes,
+            );
+        }
+        if let Some(config) = self.trading_pairs.get(&order.pair).cloned() {
+            self.reduce_from_pricelevel(
+                &config,
+                order.pair,
+                order.price,
+                unfilled,
+                order.side,
+                &mut changes.pricelevels,
+            );
+        }
+        let deferred = self.take_deferred_maker_fee(&order.id);
+        order.fee = Order::rounding_off(order.fee.saturating_add(deferred));
+        order.status = status;
+        order.stid = changes.stid;
+        self.round_published_avg_price(&mut order);
+        log::info!(target:"engine","Released order {:?} with unfilled qty: {:?}",order.id,unfilled);
+        changes.modified_orders.insert(order.id, order);
+    }
+
+    // Cancels the makers taken out of the book because they crossed a taker of their account
+    pub(crate) fn cancel_self_trade_makers(&mut self, changes: &mut OrderExecutionResult) {
+        for maker in std::mem::take(&mut self.self_trade_cancels) {
+            self.cancel_resting_order(maker, changes);
+        }
+    }
+
+    // Cancels the makers of the book opposite to the taker that outlived the order ttl at `now`
+    pub(crate) fn cancel_expired_makers(
+        &mut self,
+        taker: &Order,
+        now: u64,
+        changes: &mut OrderExecutionResult,
+    ) {
+        let Some(ttl) = self.pair_settings(&taker.pair).order_ttl else {
+            return;
+        };
+        let side = match taker.side {
+            OrderSide::Ask => OrderSide::Bid,
+            OrderSide::Bid => OrderSide::Ask,
+        };
+        let book = match side {
+            OrderSide::Ask => self.ask_books.get(&taker.pair),
+            OrderSide::Bid => self.bid_books.get(&taker.pair),
+        };
+        let expired: Vec<OrderId> = book
+            .into_iter()
+            .flatten()
+            .filter(|maker| is_expired(rested_since(maker, &self.rested_at), ttl, now))
+            .map(|maker| maker.id)
+            .collect();
+        for order_id in expired {
+            if let Some(maker) = self.take_resting_order(&taker.pair, side, &order_id) {
+                log::info!(target:"engine","Cancelling expired maker {:?}",order_id);
+                self.cancel_resting_order(maker, changes);
+            }
+        }
+    }
+
+    // Closes the makers taken out of the book because their residual is below one qty step
+    pub(crate) fn close_sub_step_residuals(&mut self, changes: &mut OrderExecutionResult) {
+        for maker in std::mem::take(&mut self.sub_step_residuals) {
+            self.release_resting_order(maker, OrderStatus::CLOSED, changes);
+        }
+    }
+
+    /// Returns the resting orders of the account in all books.
+    pub fn resting_orders_of(&self, main: &AccountId) -> Vec<Order> {
+        self.bid_books
+            .values()
+            .chain(self.ask_books.values())
+            .flat_map(|book| book.iter())
+            .filter(|order| order.main_account == *main)
+            .cloned()
+            .collect()
+    }
+}
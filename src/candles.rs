@@ -0,0 +1,112 @@
+use crate::Orderbook;
+use orderbook_primitives::types::{Trade, TradingPair};
+use rust_decimal::Decimal;
+
+/// Default width of the stored candle buckets, in milliseconds
+pub const DEFAULT_CANDLE_INTERVAL: u64 = 60_000;
+
+/// Default number of candle buckets kept per pair
+pub const DEFAULT_CANDLE_RETENTION: usize = 10_000;
+
+/// Open, high, low, close prices and base volume of the trades of a pair in a time bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Candle {
+    // Start of the bucket
+    pub start: u64,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    // Traded base quantity
+    pub volume: Decimal,
+}
+
+impl Candle {
+    fn new(start: u64, price: Decimal, volume: Decimal) -> Self {
+        Self {
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume,
+        }
+    }
+
+    // Folds a later candle into this one
+    fn merge(&mut self, later: &Candle) {
+        self.high = self.high.max(later.high);
+        self.low = self.low.min(later.low);
+        self.close = later.close;
+        self.volume = self.volume.saturating_add(later.volume);
+    }
+}
+
+impl Orderbook {
+    /// Sets the width of the stored candle buckets. Already recorded candles keep their buckets,
+    /// so this should be configured before trading starts.
+    pub fn set_candle_interval(&mut self, interval: u64) {
+        self.candle_interval = interval.max(1);
+    }
+
+    /// Sets the number of candle buckets kept per pair. Pairs holding more drop their oldest
+    /// buckets.
+    pub fn set_candle_retention(&mut self, retention: usize) {
+        self.candle_retention = retention.max(1);
+        let mut pairs: Vec<TradingPair> = self.candles.keys().map(|(pair, _)| *pair).collect();
+        pairs.dedup();
+        for pair in pairs {
+            self.prune_candles(pair);
+        }
+    }
+
+    // Records a trade settled at `now` in the candle of its pair
+    pub(crate) fn record_candle(&mut self, now: u64, trade: &Trade) {
+        let start = now - now % self.candle_interval;
+        let pair = trade.maker.pair;
+        let candle = Candle::new(start, trade.price, trade.amount);
+        match self.candles.get_mut(&(pair, start)) {
+            Some(stored) => stored.merge(&candle),
+            None => {
+                self.candles.insert((pair, start), candle);
+                // Buckets only open here, so the pair is pruned once per bucket
+                self.prune_candles(pair);
+            }
+        }
+    }
+
+    // Drops the oldest buckets of the pair beyond the retention
+    fn prune_candles(&mut self, pair: TradingPair) {
+        let stored = self.candles.range((pair, 0)..=(pair, u64::MAX)).count();
+        let excess = stored.saturating_sub(self.candle_retention);
+        let oldest: Vec<(TradingPair, u64)> = self
+            .candles
+            .range((pair, 0)..=(pair, u64::MAX))
+            .take(excess)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in oldest {
+            self.candles.remove(&key);
+        }
+    }
+
+    /// Returns the last `count` candles of the pair, oldest first, aggregated to the given
+    /// interval. Intervals are rounded up to a multiple of the stored candle interval.
+    pub fn candles(&self, pair: TradingPair, interval: u64, count: usize) -> Vec<Candle> {
+        let interval = interval
+            .div_ceil(self.candle_interval)
+            .max(1)
+            .saturating_mul(self.candle_interval);
+        let mut candles: Vec<Candle> = Vec::new();
+        let stored = self.candles.range((pair, 0)..=(pair, u64::MAX));
+        for (_, candle) in stored {
+            let start = candle.start - candle.start % interval;
+            match candles.last_mut() {
+                Some(last) if last.start == start => last.merge(candle),
+                _ => candles.push(Candle { start, ..*candle }),
+            }
+        }
+        let skip = candles.len().saturating_sub(count);
+        candles.split_off(skip)
+    }
+}
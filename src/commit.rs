@@ -0,0 +1,406 @@
+use crate::candles::Candle;
+use crate::error::Error;
+use crate::ladder::Ladder;
+use crate::{OrderExecutionResult, Orderbook, PriceLevels, ReservationEvent};
+use orderbook_primitives::types::{Order, OrderId, Trade, TradingPair};
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+
+type Balance = (Decimal, Decimal);
+
+/// An order execution that is applied to the engine but can still be rolled back. The pair
+/// and the accounts it touched are frozen until it is committed or rolled back. Latency
+/// samples are not transactional, they keep the processing time of rolled back executions.
+/// Mid samples are recorded by `record_bbo` only, never by an execution.
+#[derive(Debug)]
+pub struct PreparedExecution {
+    id: u64,
+    pair: TradingPair,
+    // Accounts frozen until the execution is committed or rolled back
+    accounts: BTreeSet<AccountId>,
+    // Id of the prepared order
+    order_id: OrderId,
+    // Books of the pair before the execution
+    bid_book: BinaryHeap<Order>,
+    ask_book: BinaryHeap<Order>,
+    // Price levels of the pair before the execution
+    pricelevels: PriceLevels,
+    // (main, asset) => (balance before, balance after) of every changed balance
+    balances: BTreeMap<(AccountId, AssetId), (Option<Balance>, Balance)>,
+    // Candles of the pair before the execution
+    candles: Vec<((TradingPair, u64), Candle)>,
+    // Ladders of the pair before the execution
+    ladders: Vec<(OrderId, (Ladder, usize))>,
+    // Fee accruals recorded by the execution
+    revenue: Vec<(u64, AssetId, Decimal)>,
+    // Fees paid by accounts in the execution
+    fees_paid: Vec<(AccountId, AssetId, Decimal)>,
+    // Reserved balance changes logged by the execution
+    reservation_events: Vec<ReservationEvent>,
+    // Trade tape of the pair before the execution
+    trade_tape: Option<VecDeque<Trade>>,
+    // Reference price of the pair before the execution
+    reference_price: Option<(Decimal, u64)>,
+    // Deferred maker fees of the orders of the pair before the execution
+    deferred_maker_fees: Vec<(OrderId, Decimal)>,
+    // Tracked reservations of the prepared order and the orders of the pair before the execution
+    order_reservations: Vec<(OrderId, BTreeMap<AssetId, Decimal>)>,
+    // Reserved fees of the prepared order and the orders of the pair before the execution
+    fee_reservations: Vec<(OrderId, BTreeMap<AssetId, Decimal>)>,
+    // Times the orders of the pair started resting
+    rested_at: Vec<(OrderId, u64)>,
+}
+
+impl PreparedExecution {
+    /// Returns the id of the prepared execution
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Orderbook {
+    /// Processes the order like `process_order` and returns the result together with the
+    /// execution needed to commit or roll it back. Until then, orders and cancellations on
+    /// the pair or of the accounts whose balances changed are rejected. An order that fails
+    /// is reverted before its error is returned.
+    pub fn prepare_order(
+        &mut self,
+        order: Order,
+        stid: u64,
+    ) -> anyhow::Result<(PreparedExecution, OrderExecutionResult)> {
+        let (prepared, result) = self.execute_revertible(order, stid)?;
+        self.prepared
+            .insert(prepared.id, (prepared.pair, prepared.accounts.clone()));
+        Ok((prepared, result))
+    }
+
+    // Processes the order and records what is needed to roll it back, without freezing its
+    // pair and accounts. Executions spanning several orders freeze nothing in between.
+    pub(crate) fn execute_revertible(
+        &mut self,
+        order: Order,
+        stid: u64,
+    ) -> anyhow::Result<(PreparedExecution, OrderExecutionResult)> {
+        let pair = order.pair;
+        let taker = order.main_account.clone();
+        // Fees are paid by the taker and the makers of the pair
+        let mut payers: BTreeSet<AccountId> =
+            [self.bid_books.get(&pair), self.ask_books.get(&pair)]
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|order| order.main_account.clone())
+                .collect();
+        payers.insert(taker.clone());
+        let fees_paid_before: BTreeMap<(AccountId, AssetId), Decimal> = self
+            .fees_collector
+            .fees_paid
+            .iter()
+            .filter(|((main, _), _)| payers.contains(main))
+            .map(|(key, total)| (key.clone(), *total))
+            .collect();
+        let revenue_before = self.fees_collector.revenue_recorded;
+        let logged_before = self.reservation_log().len();
+        let deferred_maker_fees = self
+            .pair_orders(&pair)
+            .filter_map(|id| Some((id, *self.deferred_maker_fees.get(&id)?)))
+            .collect();
+        let order_reservations = self
+            .pair_orders(&pair)
+            .chain([order.id])
+            .filter_map(|id| Some((id, self.order_reservations.get(&id)?.clone())))
+            .collect();
+        let fee_reservations = self
+            .pair_orders(&pair)
+            .chain([order.id])
+            .filter_map(|id| Some((id, self.fee_reservations.get(&id)?.clone())))
+            .collect();
+        let rested_at = self
+            .pair_orders(&pair)
+            .filter_map(|id| Some((id, *self.rested_at.get(&id)?)))
+            .collect();
+        let mut prepared = PreparedExecution {
+            id: self.next_prepared_id,
+            pair,
+            accounts: BTreeSet::from([taker]),
+            order_id: order.id,
+            bid_book: self.bid_books.get(&pair).cloned().unwrap_or_default(),
+            ask_book: self.ask_books.get(&pair).cloned().unwrap_or_default(),
+            pricelevels: self
+                .pricelevels
+                .iter()
+                .filter(|((level_pair, _, _), _)| *level_pair == pair)
+                .map(|(key, qty)| (*key, *qty))
+                .collect(),
+            balances: BTreeMap::new(),
+            candles: self
+                .candles
+                .iter()
+                .filter(|((candle_pair, _), _)| *candle_pair == pair)
+                .map(|(key, candle)| (*key, *candle))
+                .collect(),
+            ladders: self
+                .ladders
+                .iter()
+                .filter(|(_, (ladder, _))| ladder.template.pair == pair)
+                .map(|(id, ladder)| (*id, ladder.clone()))
+                .collect(),
+            revenue: Vec::new(),
+            fees_paid: Vec::new(),
+            reservation_events: Vec::new(),
+            trade_tape: self.trade_tape.get(&pair).cloned(),
+            reference_price: self.reference_price(&pair),
+            deferred_maker_fees,
+            order_reservations,
+            fee_reservations,
+            rested_at,
+        };
+        // Only the balances changed by the execution are recorded
+        self.balance_journal = Some(BTreeMap::new());
+        let result = self.process_order(order, stid);
+        let journal = self.balance_journal.take().unwrap_or_default();
+        // Accruals of the execution are the ones recorded last
+        let recorded = self
+            .fees_collector
+            .revenue_recorded
+            .saturating_sub(revenue_before);
+        prepared.revenue = self
+            .fees_collector
+            .revenue_log
+            .iter()
+            .rev()
+            .take(usize::try_from(recorded).unwrap_or(usize::MAX))
+            .copied()
+            .collect();
+        prepared.reservation_events = self
+            .reservation_log()
+            .get(logged_before..)
+            .unwrap_or_default()
+            .to_vec();
+        prepared.fees_paid = self
+            .fees_collector
+            .fees_paid
+            .iter()
+            .filter(|((main, _), _)| payers.contains(main))
+            .filter_map(|((main, asset), total)| {
+                let before = fees_paid_before
+                    .get(&(main.clone(), *asset))
+                    .copied()
+                    .unwrap_or_default();
+                let paid = total.saturating_sub(before);
+                (!paid.is_zero()).then(|| (main.clone(), *asset, paid))
+            })
+            .collect();
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                // A failed execution is reverted with every balance it touched
+                for (key, before) in journal {
+                    let after = self.balances.get(&key).copied().unwrap_or_default();
+                    prepared.balances.insert(key, (before, after));
+                }
+                self.rollback(prepared);
+                return Err(err);
+            }
+        };
+        for key in result.balances.keys() {
+            let after = self.balances.get(key).copied().unwrap_or_default();
+            let before = journal.get(key).copied().unwrap_or(Some(after));
+            prepared.balances.insert(key.clone(), (before, after));
+            // The pot accrues fees of every pair, its changes are reverted as deltas
+            if key.0 != self.fees_collector.pot {
+                prepared.accounts.insert(key.0.clone());
+            }
+        }
+        self.next_prepared_id = self.next_prepared_id.wrapping_add(1);
+        Ok((prepared, result))
+    }
+
+    /// Makes the prepared execution final and unfreezes its pair and accounts.
+    pub fn commit(&mut self, prepared: PreparedExecution) {
+        self.prepared.remove(&prepared.id);
+    }
+
+    /// Reverts the state changed by the prepared execution and unfreezes its pair and
+    /// accounts. Balances are reverted by their change, so fees accrued by the pot on other
+    /// pairs in the meantime are kept.
+    pub fn rollback(&mut self, prepared: PreparedExecution) {
+        let PreparedExecution {
+            id,
+            pair,
+            order_id,
+            accounts: _,
+            bid_book,
+            ask_book,
+            pricelevels,
+            balances,
+            candles,
+            ladders,
+            revenue,
+            fees_paid,
+            reservation_events,
+            trade_tape,
+            reference_price,
+            deferred_maker_fees,
+            order_reservations,
+            fee_reservations,
+            rested_at,
+        } = prepared;
+        let current: Vec<OrderId> = self.pair_orders(&pair).collect();
+        for id in current {
+            self.deferred_maker_fees.remove(&id);
+            self.order_reservations.remove(&id);
+            self.fee_reservations.remove(&id);
+            self.rested_at.remove(&id);
+        }
+        self.order_reservations.remove(&order_id);
+        self.order_reservations.extend(order_reservations);
+        self.fee_reservations.remove(&order_id);
+        self.fee_reservations.extend(fee_reservations);
+        self.rested_at.remove(&order_id);
+        self.rested_at.extend(rested_at);
+        // Recount the open orders of the accounts resting on the pair
+        let replaced: Vec<AccountId> = [self.bid_books.get(&pair), self.ask_books.get(&pair)]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|order| order.main_account.clone())
+            .collect();
+        for main in &replaced {
+            self.count_open_order(main, false);
+        }
+        for order in bid_book.iter().chain(ask_book.iter()) {
+            self.count_open_order(&order.main_account, true);
+        }
+        self.deferred_maker_fees.extend(deferred_maker_fees);
+        self.bid_books.insert(pair, bid_book);
+        self.ask_books.insert(pair, ask_book);
+        self.pricelevels
+            .retain(|(level_pair, _, _), _| *level_pair != pair);
+        self.pricelevels.extend(pricelevels);
+        self.candles
+            .retain(|(candle_pair, _), _| *candle_pair != pair);
+        self.candles.extend(candles);
+        self.ladders
+            .retain(|_, (ladder, _)| ladder.template.pair != pair);
+        self.ladders.extend(ladders);
+        match trade_tape {
+            Some(tape) => self.trade_tape.insert(pair, tape),
+            None => self.trade_tape.remove(&pair),
+        };
+        match reference_price {
+            Some(reference) => self.reference_prices.insert(pair, reference),
+            None => self.reference_prices.remove(&pair),
+        };
+        for (key, (before, after)) in balances {
+            let current = self.balances.get(&key).copied().unwrap_or(after);
+            let (free_before, reserved_before) = before.unwrap_or_default();
+            let reverted = (
+                current
+                    .0
+                    .saturating_add(free_before)
+                    .saturating_sub(after.0),
+                current
+                    .1
+                    .saturating_add(reserved_before)
+                    .saturating_sub(after.1),
+            );
+            if before.is_none() && reverted == Balance::default() {
+                self.balances.remove(&key);
+            } else {
+                self.balances.insert(key, reverted);
+            }
+        }
+        for entry in revenue {
+            let log = &mut self.fees_collector.revenue_log;
+            if let Some(position) = log.iter().rposition(|recorded| *recorded == entry) {
+                log.remove(position);
+            }
+        }
+        if let Some(log) = self.reservation_log.as_mut() {
+            for event in reservation_events.iter().rev() {
+                if let Some(position) = log.iter().rposition(|logged| logged == event) {
+                    log.remove(position);
+                }
+            }
+        }
+        for (main, asset, amount) in fees_paid {
+            if let Some(total) = self
+                .fees_collector
+                .fees_paid
+                .get_mut(&(main.clone(), asset))
+            {
+                *total = total.saturating_sub(amount);
+                if total.is_zero() {
+                    self.fees_collector.fees_paid.remove(&(main, asset));
+                }
+            }
+        }
+        self.prepared.remove(&id);
+    }
+
+    // Ids of the orders resting on both sides of the pair
+    fn pair_orders(&self, pair: &TradingPair) -> impl Iterator<Item = OrderId> + '_ {
+        [self.bid_books.get(pair), self.ask_books.get(pair)]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|order| order.id)
+    }
+
+    // Rejects changes to a pair or an account frozen by a prepared execution
+    pub(crate) fn ensure_not_frozen(
+        &self,
+        pair: &TradingPair,
+        main: &AccountId,
+    ) -> Result<(), Error> {
+        let frozen = self
+            .prepared
+            .values()
+            .any(|(frozen_pair, accounts)| frozen_pair == pair || accounts.contains(main));
+        if frozen {
+            return Err(Error::ExecutionPending);
+        }
+        Ok(())
+    }
+
+    // Rejects changes to the balances and resting orders of an account while an execution of
+    // the account or of a pair it has orders resting in is prepared
+    pub(crate) fn ensure_account_not_frozen(&self, main: &AccountId) -> Result<(), Error> {
+        let pairs: BTreeSet<TradingPair> = [&self.bid_books, &self.ask_books]
+            .into_iter()
+            .flat_map(|books| books.values())
+            .flat_map(|book| book.iter())
+            .filter(|order| order.main_account == *main)
+            .map(|order| order.pair)
+            .collect();
+        let frozen = self
+            .prepared
+            .values()
+            .any(|(frozen_pair, accounts)| pairs.contains(frozen_pair) || accounts.contains(main));
+        if frozen {
+            return Err(Error::ExecutionPending);
+        }
+        Ok(())
+    }
+
+    // Rejects changes to the whole pair while an execution of the pair or of an account with
+    // orders resting in it is prepared
+    pub(crate) fn ensure_pair_not_frozen(&self, pair: &TradingPair) -> Result<(), Error> {
+        let books = [self.bid_books.get(pair), self.ask_books.get(pair)];
+        let owners: BTreeSet<&AccountId> = books
+            .into_iter()
+            .flatten()
+            .flat_map(|book| book.iter())
+            .map(|order| &order.main_account)
+            .collect();
+        let frozen = self.prepared.values().any(|(frozen_pair, accounts)| {
+            frozen_pair == pair || accounts.iter().any(|main| owners.contains(main))
+        });
+        if frozen {
+            return Err(Error::ExecutionPending);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use crate::Orderbook;
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+
+impl Orderbook {
+    /// Sets the rate one unit of `from` converts to `to` at. The inverse conversion uses the
+    /// reciprocal unless a rate is set for it too.
+    pub fn set_conversion_rate(&mut self, from: AssetId, to: AssetId, rate: Decimal) {
+        self.conversion_rates.insert((from, to), rate);
+    }
+
+    /// Converts an amount of `from` into `to`. Returns `None` if no rate is known either way.
+    pub fn convert(&self, amount: Decimal, from: AssetId, to: AssetId) -> Option<Decimal> {
+        if from == to {
+            return Some(amount);
+        }
+        if let Some(rate) = self.conversion_rates.get(&(from, to)) {
+            return Some(amount.saturating_mul(*rate));
+        }
+        let inverse = self.conversion_rates.get(&(to, from))?;
+        amount.checked_div(*inverse)
+    }
+
+    /// Returns the fees accrued since `since_stid`, see `fee_revenue`, converted into a single
+    /// asset. Returns `None` if any accrued asset has no rate into it.
+    pub fn fee_revenue_in(&self, since_stid: u64, asset: AssetId) -> Option<Decimal> {
+        self.fee_revenue(since_stid)
+            .into_iter()
+            .try_fold(Decimal::ZERO, |total, (from, amount)| {
+                Some(total.saturating_add(self.convert(amount, from, asset)?))
+            })
+    }
+}
@@ -0,0 +1,172 @@
+use crate::settings::{MinVolumePolicy, PairSettings};
+use crate::utils::{
+    is_below_min_volume, is_expired, leaves_sub_min_residual, rested_since, trade_price,
+    will_orders_match,
+};
+use crate::Orderbook;
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::{Order, OrderId, OrderType};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BinaryHeap};
+
+/// Whether a taker trades with a resting maker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchDecision {
+    /// The prices cross and the orders trade
+    Yes,
+    /// The maker is beyond the limit or protection price of the taker, matching stops
+    No,
+    /// The maker is cancelled and matching continues with the next one
+    SkipMaker(SkipReason),
+}
+
+/// Why a maker is cancelled instead of matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The maker outlived the order ttl of the pair
+    Expired,
+    /// The maker crosses a taker of its own account on a pair cancelling self trades
+    SelfTrade,
+}
+
+/// Decides whether the taker trades with the maker resting since `rested_since` at `now`, in
+/// milliseconds. Expired makers are skipped first, then the limit or protection price of the
+/// taker must cross the maker and crossing makers of the taker's account are skipped if the
+/// pair cancels self trades.
+pub fn match_decision(
+    taker: &Order,
+    maker: &Order,
+    rested_since: u64,
+    settings: &PairSettings,
+    now: u64,
+) -> MatchDecision {
+    if settings
+        .order_ttl
+        .is_some_and(|ttl| is_expired(rested_since, ttl, now))
+    {
+        return MatchDecision::SkipMaker(SkipReason::Expired);
+    }
+    if !will_orders_match(taker, maker) {
+        return MatchDecision::No;
+    }
+    if settings.cancel_on_self_trade && taker.main_account == maker.main_account {
+        return MatchDecision::SkipMaker(SkipReason::SelfTrade);
+    }
+    MatchDecision::Yes
+}
+
+impl Orderbook {
+    /// Decides whether the taker trades with the maker at `now` under the settings of the
+    /// taker's pair, see `match_decision`.
+    pub fn can_match(&self, taker: &Order, maker: &Order, now: u64) -> MatchDecision {
+        let rested = rested_since(maker, &self.rested_at);
+        match_decision(taker, maker, rested, &self.pair_settings(&taker.pair), now)
+    }
+}
+
+/// Selects the makers a taker matches against, shared by matching and its preview. Makers
+/// skipped by `match_decision` and makers left with less than one qty step are taken out of
+/// the book and kept aside. Under `MinVolumePolicy::KeepMakerWhole`, crossing makers a fill
+/// would leave below the min volume are kept aside too and must be put back into the book.
+/// Limit takers stop after sweeping the max number of price levels, kept makers do not count.
+pub(crate) struct MakerSelector<'a> {
+    settings: &'a PairSettings,
+    config: &'a TradingPairConfig,
+    rested_at: &'a BTreeMap<OrderId, u64>,
+    now: u64,
+    max_levels: Option<usize>,
+    swept_levels: usize,
+    last_price: Option<Decimal>,
+    // Matching stopped at the max number of levels before a crossing maker
+    pub(crate) level_capped: bool,
+    // Makers cancelled instead of matched
+    pub(crate) skipped: Vec<Order>,
+    // Makers whose residual can never be filled by quantized fills
+    pub(crate) residuals: Vec<Order>,
+    // Crossing makers kept whole instead of left below the min volume
+    pub(crate) kept_whole: Vec<Order>,
+}
+
+impl<'a> MakerSelector<'a> {
+    pub(crate) fn new(
+        taker: &Order,
+        settings: &'a PairSettings,
+        config: &'a TradingPairConfig,
+        rested_at: &'a BTreeMap<OrderId, u64>,
+        now: u64,
+    ) -> Self {
+        Self {
+            settings,
+            config,
+            rested_at,
+            now,
+            max_levels: settings
+                .max_sweep_levels
+                .filter(|_| taker.order_type == OrderType::LIMIT),
+            swept_levels: 0,
+            last_price: None,
+            level_capped: false,
+            skipped: Vec::new(),
+            residuals: Vec::new(),
+            kept_whole: Vec::new(),
+        }
+    }
+
+    /// Returns the next maker the taker matches, taken out of the book with `pop_best`.
+    pub(crate) fn next<P>(
+        &mut self,
+        book: &mut BinaryHeap<Order>,
+        taker: &Order,
+        mut pop_best: P,
+    ) -> Option<Order>
+    where
+        P: FnMut(&mut BinaryHeap<Order>) -> Option<Order>,
+    {
+        loop {
+            let maker = pop_best(book)?;
+            let rested = rested_since(&maker, self.rested_at);
+            if let MatchDecision::SkipMaker(_) =
+                match_decision(taker, &maker, rested, self.settings, self.now)
+            {
+                self.skipped.push(maker);
+                continue;
+            }
+            if maker.qty.saturating_sub(maker.filled_quantity) < self.config.qty_step_size {
+                self.residuals.push(maker);
+                continue;
+            }
+            if self.keeps_whole(taker, &maker) {
+                self.kept_whole.push(maker);
+                continue;
+            }
+            if self.last_price != Some(maker.price) {
+                if self.max_levels.is_some_and(|max| self.swept_levels >= max) {
+                    self.level_capped = will_orders_match(taker, &maker);
+                    book.push(maker);
+                    return None;
+                }
+                self.swept_levels += 1;
+                self.last_price = Some(maker.price);
+            }
+            return Some(maker);
+        }
+    }
+
+    // Returns true if the maker crosses the taker but a fill would leave it below the min volume
+    // of the market on a pair keeping such makers whole
+    fn keeps_whole(&self, taker: &Order, maker: &Order) -> bool {
+        if self.settings.min_volume_policy != MinVolumePolicy::KeepMakerWhole
+            || !will_orders_match(taker, maker)
+            || is_below_min_volume(taker.available_volume(Some(maker.price)), self.config)
+        {
+            return false;
+        }
+        let price = trade_price(
+            taker,
+            maker,
+            self.settings.price_mode,
+            self.config.price_tick_size,
+        );
+        leaves_sub_min_residual(taker, maker, price, self.config)
+    }
+}
@@ -2,4 +2,32 @@
 pub enum Error {
     #[error("Trading Pair config is not registered")]
     TradingPairConfigNotFound,
+    #[error("Insufficient free balance to pay the settlement fee")]
+    InsufficientSettlementFeeBalance,
+    #[error("Invalid trading pair config: {0}")]
+    InvalidPairConfig(String),
+    #[error("Market order has neither qty nor quote order qty set")]
+    InvalidMarketOrder,
+    #[error("Insufficient free balance to reserve for the order")]
+    InsufficientBalance,
+    #[error("Balance state is corrupted")]
+    BalanceCorruption,
+    #[error("Order has not rested for the minimum resting time")]
+    MinRestingTimeNotMet,
+    #[error("Pair or account is frozen by a prepared execution")]
+    ExecutionPending,
+    #[error("Account has reached the maximum number of open orders")]
+    TooManyOpenOrders,
+    #[error("Reference price of the pair is missing or stale")]
+    StaleReferencePrice,
+    #[error("Pair has reached the maximum number of price levels")]
+    TooManyPriceLevels,
+    #[error("Market orders are not accepted while the pair is auction only")]
+    AuctionOnly,
+    #[error("Order price is outside the price bounds of the pair")]
+    PriceOutOfBounds,
+    #[error("Order qty is outside the qty bounds of the pair")]
+    QtyOutOfBounds,
+    #[error("Order quote budget is outside the volume bounds of the pair")]
+    QuoteOutOfBounds,
 }
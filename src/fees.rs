@@ -18,11 +18,12 @@
 
 use frame_support::sp_runtime::traits::AccountIdConversion;
 use orderbook_primitives::constants::FEE_POT_PALLET_ID;
+use orderbook_primitives::types::{Order, OrderId, OrderSide, TradingPair};
 use polkadex_primitives::fees::FeeConfig;
 use polkadex_primitives::{AccountId, AssetId};
 use rust_decimal::{Decimal, RoundingStrategy};
 use sp_core::H256;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 
 /// A structure that contains the maker and taker fee
 /// percentages for the given
@@ -30,6 +31,10 @@ use std::collections::BTreeMap;
 pub struct AccountFee {
     pub maker_fraction: Decimal,
     pub taker_fraction: Decimal,
+    // Queue priority of the account's makers among makers at the same price
+    pub priority_boost: u32,
+    // Flat fee in the fee asset charged once per order, on its first fill
+    pub flat_fee: Decimal,
 }
 
 impl Default for AccountFee {
@@ -38,6 +43,40 @@ impl Default for AccountFee {
         Self {
             maker_fraction: config.maker_fraction,
             taker_fraction: config.taker_fraction,
+            priority_boost: 0,
+            flat_fee: Decimal::ZERO,
+        }
+    }
+}
+
+// Fee settings of an account, the ones left unset fall back to the default fee structure
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct FeeOverride {
+    pub(crate) maker_fraction: Option<Decimal>,
+    pub(crate) taker_fraction: Option<Decimal>,
+    pub(crate) priority_boost: Option<u32>,
+    pub(crate) flat_fee: Option<Decimal>,
+}
+
+impl FeeOverride {
+    // Resolves the fee structure of the account against the default one
+    pub(crate) fn resolve(&self, default: &AccountFee) -> AccountFee {
+        AccountFee {
+            maker_fraction: self.maker_fraction.unwrap_or(default.maker_fraction),
+            taker_fraction: self.taker_fraction.unwrap_or(default.taker_fraction),
+            priority_boost: self.priority_boost.unwrap_or(default.priority_boost),
+            flat_fee: self.flat_fee.unwrap_or(default.flat_fee),
+        }
+    }
+}
+
+impl From<AccountFee> for FeeOverride {
+    fn from(fee: AccountFee) -> Self {
+        Self {
+            maker_fraction: Some(fee.maker_fraction),
+            taker_fraction: Some(fee.taker_fraction),
+            priority_boost: Some(fee.priority_boost),
+            flat_fee: Some(fee.flat_fee),
         }
     }
 }
@@ -46,19 +85,62 @@ impl Default for AccountFee {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct FeeReceipt {
     pub user: AccountId, // main account
+    pub order_id: OrderId,
     pub trade_id: H256,
     pub asset: AssetId,
     pub amt: Decimal,
     pub is_maker: bool,
 }
 
+/// Asset of a trade in which a side pays its fees
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeeAsset {
+    /// Deducted from the asset received in the trade
+    #[default]
+    Received,
+    /// Paid on top of the asset given away in the trade
+    GivenAway,
+    /// Charged on the quote notional of the trade, `price * amount`, in the quote asset for
+    /// both sides. Deducted from the received quote of sells, paid on top of the given away
+    /// quote of buys.
+    QuoteNotional,
+}
+
+/// Assets received and given away by one side of a trade
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TradeFlows {
+    pub recv_asset: AssetId,
+    pub recv_amt: Decimal,
+    pub give_away_asset: AssetId,
+    pub lost_amt: Decimal,
+}
+
+/// Maximum number of fee accruals kept for revenue reporting
+pub const MAX_REVENUE_ENTRIES: usize = 100_000;
+
 /// Fee collector settles fees for each trade given to it.
 /// It will also have a mechanism to withdraw fees too.
 pub struct FeeCollector {
     // Main account of fees pot
     pub(crate) pot: AccountId,
-    // Accounts to fee structure map
-    pub(crate) fee_structure: BTreeMap<AccountId, AccountFee>,
+    // Accounts to the fee settings overriding the default fee structure
+    pub(crate) fee_structure: BTreeMap<AccountId, FeeOverride>,
+    // Fee structure of the accounts without an entry in the map
+    pub(crate) default_fee: AccountFee,
+    // Asset makers pay their fees in
+    pub(crate) maker_fee_asset: FeeAsset,
+    // Asset takers pay their fees in
+    pub(crate) taker_fee_asset: FeeAsset,
+    // (min trade amount, fee multiplier) sorted by amount, discounts fees of large trades
+    pub(crate) size_discounts: Vec<(Decimal, Decimal)>,
+    // (stid, asset, amount) of the latest fee accruals, oldest first
+    pub(crate) revenue_log: VecDeque<(u64, AssetId, Decimal)>,
+    // Number of fee accruals recorded so far, including evicted ones
+    pub(crate) revenue_recorded: u64,
+    // Accounts to the stid until which their trades are free of fees
+    pub(crate) fee_free_until: BTreeMap<AccountId, u64>,
+    // (main, asset) => total fees paid by the account in the asset
+    pub(crate) fees_paid: BTreeMap<(AccountId, AssetId), Decimal>,
 }
 
 impl FeeCollector {
@@ -66,46 +148,147 @@ impl FeeCollector {
         Self {
             pot: FEE_POT_PALLET_ID.into_account_truncating(),
             fee_structure: Default::default(),
+            default_fee: AccountFee::default(),
+            maker_fee_asset: FeeAsset::Received,
+            taker_fee_asset: FeeAsset::Received,
+            size_discounts: Vec::new(),
+            revenue_log: VecDeque::new(),
+            revenue_recorded: 0,
+            fee_free_until: BTreeMap::new(),
+            fees_paid: BTreeMap::new(),
         }
     }
 
-    /// Calculates and returns the fees that must be added/deducted from maker and taker.
-    /// NOTE: This method assumes that trade is already settled with NO FEE assumption and the result
-    /// of this method is updated on top of that NO FEE SETTLEMENT state, to add fees.
-    pub fn settle_trade_fees(
-        &mut self,
+    /// Creates a fee collector accruing fees to the given pot account.
+    pub fn with_pot(pot: AccountId) -> Self {
+        let mut collector = Self::initialize();
+        collector.pot = pot;
+        collector
+    }
+
+    /// Sets the pot account and returns the previous one. The accrued balances are not moved,
+    /// see `Orderbook::set_pot`.
+    pub fn set_pot(&mut self, pot: AccountId) -> AccountId {
+        std::mem::replace(&mut self.pot, pot)
+    }
+
+    /// Applies the fee of a trade of the account to its asset flows, see `settle_trade_fees`.
+    /// Returns the asset and amount of the fee.
+    pub fn apply_trade_fee(
+        &self,
         main: &AccountId,
-        trade_id: H256,
+        pair: TradingPair,
         is_maker: bool,
-        recv_amt: &mut Decimal,
-        recv_asset: AssetId,
-    ) -> FeeReceipt {
-        let fee_structure = self.fee_structure.get(main).cloned().unwrap_or_default();
+        first_fill: bool,
+        flows: &mut TradeFlows,
+    ) -> (AssetId, Decimal) {
+        let fee_structure = self.fee_structure_of(main);
 
         let fee_fraction = if is_maker {
             fee_structure.maker_fraction
         } else {
             fee_structure.taker_fraction
         };
-        // Calculate the fees
-        let fees = recv_amt
+        let fee_asset = self.resolve_fee_asset(pair, is_maker, flows);
+        // The traded base amount is the one that flows in the base asset
+        let trade_amount = if flows.recv_asset == pair.base {
+            flows.recv_amt
+        } else {
+            flows.lost_amt
+        };
+        let fee_fraction = fee_fraction.saturating_mul(self.size_discount(trade_amount));
+        let flat_fee = if first_fill {
+            fee_structure.flat_fee
+        } else {
+            Decimal::ZERO
+        };
+        let (amount, asset) = match fee_asset {
+            FeeAsset::GivenAway => (flows.lost_amt, flows.give_away_asset),
+            FeeAsset::Received | FeeAsset::QuoteNotional => (flows.recv_amt, flows.recv_asset),
+        };
+        // Calculate the fees, they can never exceed the traded amount of the fee asset
+        let fees = amount
             .saturating_mul(fee_fraction)
+            .saturating_add(flat_fee)
+            .min(amount)
             .round_dp_with_strategy(9, RoundingStrategy::ToZero);
-        // Calculate the recv_amt
-        *recv_amt = recv_amt
-            .saturating_sub(fees)
-            .round_dp_with_strategy(9, RoundingStrategy::ToZero);
+        // Apply the fees to the asset flows
+        match fee_asset {
+            FeeAsset::GivenAway => flows.lost_amt = flows.lost_amt.saturating_add(fees),
+            FeeAsset::Received | FeeAsset::QuoteNotional => {
+                flows.recv_amt = flows
+                    .recv_amt
+                    .saturating_sub(fees)
+                    .round_dp_with_strategy(9, RoundingStrategy::ToZero);
+            }
+        }
+        (asset, fees)
+    }
+
+    // Returns whether the side pays its fee in the received or the given away asset of the trade
+    fn resolve_fee_asset(&self, pair: TradingPair, is_maker: bool, flows: &TradeFlows) -> FeeAsset {
+        let fee_asset = if is_maker {
+            self.maker_fee_asset
+        } else {
+            self.taker_fee_asset
+        };
+        match fee_asset {
+            FeeAsset::QuoteNotional if flows.recv_asset == pair.quote => FeeAsset::Received,
+            FeeAsset::QuoteNotional => FeeAsset::GivenAway,
+            fee_asset => fee_asset,
+        }
+    }
+
+    /// Calculates and returns the fees that must be added/deducted from maker and taker.
+    /// Fees in the received asset reduce `recv_amt`, fees in the given away asset are added
+    /// to `lost_amt`. The flat fee of the account is only charged on the first fill of an
+    /// order, so an order filled across several trades pays it once. The fee fraction is scaled
+    /// by the size discount of the traded base amount. No fee is charged if the account is fee
+    /// free at `stid`.
+    /// NOTE: This method assumes that trade is already settled with NO FEE assumption and the result
+    /// of this method is updated on top of that NO FEE SETTLEMENT state, to add fees.
+    pub fn settle_trade_fees(
+        &mut self,
+        order: &Order,
+        trade_id: H256,
+        is_maker: bool,
+        first_fill: bool,
+        stid: u64,
+        flows: &mut TradeFlows,
+    ) -> FeeReceipt {
+        let main = &order.main_account;
+        let (asset, fees) = if self.is_fee_free(main, stid) {
+            match self.resolve_fee_asset(order.pair, is_maker, flows) {
+                FeeAsset::GivenAway => (flows.give_away_asset, Decimal::ZERO),
+                FeeAsset::Received | FeeAsset::QuoteNotional => (flows.recv_asset, Decimal::ZERO),
+            }
+        } else {
+            self.apply_trade_fee(main, order.pair, is_maker, first_fill, flows)
+        };
 
         // Return receipt
         FeeReceipt {
             user: main.clone(),
+            order_id: order.id,
             is_maker,
             trade_id,
-            asset: recv_asset,
+            asset,
             amt: fees,
         }
     }
 
+    /// Makes the trades of the account free of fees until the given stid, exclusive.
+    pub fn set_fee_free_until(&mut self, main: &AccountId, stid: u64) {
+        self.fee_free_until.insert(main.clone(), stid);
+    }
+
+    /// Returns true if the trades of the account are free of fees at the given stid
+    pub fn is_fee_free(&self, main: &AccountId, stid: u64) -> bool {
+        self.fee_free_until
+            .get(main)
+            .is_some_and(|deadline| stid < *deadline)
+    }
+
     /// Update the fees structure of given account
     pub fn update_fee_structure(
         &mut self,
@@ -113,17 +296,146 @@ impl FeeCollector {
         maker_fraction: Decimal,
         taker_fraction: Decimal,
     ) -> AccountFee {
-        let fee = self
-            .fee_structure
+        let fee = self.fee_structure.entry(main.clone()).or_default();
+        fee.maker_fraction = Some(maker_fraction);
+        fee.taker_fraction = Some(taker_fraction);
+        fee.resolve(&self.default_fee)
+    }
+
+    /// Update the flat fee of the given account
+    pub fn update_flat_fee(&mut self, main: &AccountId, flat_fee: Decimal) {
+        self.fee_structure.entry(main.clone()).or_default().flat_fee = Some(flat_fee);
+    }
+
+    /// Update the queue priority boost of the given account's fee tier
+    pub fn update_priority_boost(&mut self, main: &AccountId, priority_boost: u32) {
+        self.fee_structure
             .entry(main.clone())
-            .and_modify(|fee_structure| {
-                fee_structure.maker_fraction = maker_fraction;
-                fee_structure.taker_fraction = taker_fraction;
-            })
-            .or_insert(AccountFee {
-                maker_fraction,
-                taker_fraction,
-            });
-        *fee
+            .or_default()
+            .priority_boost = Some(priority_boost);
+    }
+
+    /// Sets the per trade fee discounts as (min trade amount, fee multiplier) pairs. A trade
+    /// pays its fee fraction times the multiplier of the largest threshold it reaches.
+    pub fn set_size_discounts(&mut self, mut schedule: Vec<(Decimal, Decimal)>) {
+        schedule.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.size_discounts = schedule;
+    }
+
+    /// Returns the fee multiplier of a trade of the given base amount
+    pub fn size_discount(&self, trade_amount: Decimal) -> Decimal {
+        self.size_discounts
+            .iter()
+            .rev()
+            .find(|(threshold, _)| trade_amount >= *threshold)
+            .map(|(_, multiplier)| *multiplier)
+            .unwrap_or(Decimal::ONE)
+    }
+
+    /// Returns the fee structure of the given account, the default tier if none is set
+    pub fn fee_structure_of(&self, main: &AccountId) -> AccountFee {
+        self.fee_structure
+            .get(main)
+            .map(|fee| fee.resolve(&self.default_fee))
+            .unwrap_or(self.default_fee)
+    }
+
+    /// Sets the fee structure of the accounts without one of their own, and the settings the
+    /// accounts with one did not override
+    pub fn set_default_fee(&mut self, fee: AccountFee) {
+        self.default_fee = fee;
+    }
+
+    /// Sets the assets makers and takers pay their fees in
+    pub fn set_fee_assets(&mut self, maker_fee_asset: FeeAsset, taker_fee_asset: FeeAsset) {
+        self.maker_fee_asset = maker_fee_asset;
+        self.taker_fee_asset = taker_fee_asset;
+    }
+
+    /// Records a fee accrued to the pot at `stid`. Only the latest `MAX_REVENUE_ENTRIES`
+    /// accruals are kept.
+    pub fn record_revenue(&mut self, stid: u64, asset: AssetId, amount: Decimal) {
+        if amount.is_zero() {
+            return;
+        }
+        self.revenue_log.push_back((stid, asset, amount));
+        self.revenue_recorded = self.revenue_recorded.saturating_add(1);
+        if self.revenue_log.len() > MAX_REVENUE_ENTRIES {
+            self.revenue_log.pop_front();
+        }
+    }
+
+    /// Adds a fee paid by the account to its running total in the asset.
+    pub fn record_fee_paid(&mut self, main: &AccountId, asset: AssetId, amount: Decimal) {
+        if amount.is_zero() {
+            return;
+        }
+        let total = self.fees_paid.entry((main.clone(), asset)).or_default();
+        *total = total.saturating_add(amount);
+    }
+
+    /// Returns the total fees paid by the account per asset.
+    pub fn fees_paid_by(&self, main: &AccountId) -> BTreeMap<AssetId, Decimal> {
+        self.fees_paid
+            .iter()
+            .filter(|((account, _), _)| account == main)
+            .map(|((_, asset), amount)| (*asset, *amount))
+            .collect()
+    }
+
+    /// Returns the fees accrued per asset at or after `since_stid`. Accruals older than the
+    /// kept log are not included.
+    pub fn fee_revenue(&self, since_stid: u64) -> BTreeMap<AssetId, Decimal> {
+        let mut revenue: BTreeMap<AssetId, Decimal> = BTreeMap::new();
+        for (_, asset, amount) in self
+            .revenue_log
+            .iter()
+            .filter(|(stid, _, _)| *stid >= since_stid)
+        {
+            let total = revenue.entry(*asset).or_default();
+            *total = total.saturating_add(*amount);
+        }
+        revenue
+    }
+
+    /// Returns the largest fee the account can pay in the given away asset when an order of the
+    /// given side gives away `amount`, either as maker or as taker. Zero if neither role pays in
+    /// the given away asset.
+    pub fn max_give_away_fee(&self, main: &AccountId, side: OrderSide, amount: Decimal) -> Decimal {
+        let fee_structure = self.fee_structure_of(main);
+        // Buys give away the quote asset, which notional fees are charged in
+        let pays_on_top = |asset: FeeAsset| match asset {
+            FeeAsset::Received => false,
+            FeeAsset::GivenAway => true,
+            FeeAsset::QuoteNotional => side == OrderSide::Bid,
+        };
+        [
+            (fee_structure.maker_fraction, self.maker_fee_asset),
+            (fee_structure.taker_fraction, self.taker_fee_asset),
+        ]
+        .into_iter()
+        .filter(|(_, asset)| pays_on_top(*asset))
+        .map(|(fraction, _)| {
+            amount
+                .saturating_mul(fraction)
+                .saturating_add(fee_structure.flat_fee)
+                .min(amount)
+        })
+        .max()
+        .unwrap_or_default()
+    }
+
+    /// Returns the queue priority boost of the given account
+    pub fn priority_boost(&self, main: &AccountId) -> u32 {
+        self.fee_structure_of(main).priority_boost
+    }
+
+    /// Returns true if any account has a priority boost configured
+    pub fn has_priority_boosts(&self) -> bool {
+        self.default_fee.priority_boost > 0
+            || self
+                .fee_structure
+                .values()
+                .any(|fee| fee.priority_boost.is_some_and(|boost| boost > 0))
     }
 }
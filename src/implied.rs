@@ -0,0 +1,375 @@
+use crate::utils::is_below_min_volume;
+use crate::{OrderExecutionResult, Orderbook, PreparedExecution};
+use anyhow::anyhow;
+use codec::Encode;
+use orderbook_primitives::types::{Order, OrderId, OrderSide, OrderStatus, OrderType, TradingPair};
+use rust_decimal::prelude::Zero;
+use rust_decimal::{Decimal, RoundingStrategy};
+use sp_core::hashing::blake2_256;
+use sp_core::H256;
+
+/// A single level of an implied book synthesized from the top of both legs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ImpliedLevel {
+    // Price on the first leg (base/intermediate)
+    pub first_leg_price: Decimal,
+    // Price on the second leg (intermediate/quote)
+    pub second_leg_price: Decimal,
+    // Quantity of the implied base asset that can be filled
+    pub qty: Decimal,
+    // Quantity of the intermediate asset exchanged on the second leg
+    pub intermediate_qty: Decimal,
+}
+
+impl ImpliedLevel {
+    /// Price of the level in the implied pair
+    pub fn price(&self) -> Decimal {
+        Order::rounding_off(self.first_leg_price.saturating_mul(self.second_leg_price))
+    }
+}
+
+impl Orderbook {
+    /// Registers `implied` (A/C) as a pair that is matched against the combined liquidity of
+    /// `first_leg` (A/B) and `second_leg` (B/C).
+    pub fn register_implied_pair(
+        &mut self,
+        implied: TradingPair,
+        first_leg: TradingPair,
+        second_leg: TradingPair,
+    ) -> anyhow::Result<()> {
+        if first_leg.base != implied.base
+            || first_leg.quote != second_leg.base
+            || second_leg.quote != implied.quote
+        {
+            return Err(anyhow!("legs do not form the implied pair"));
+        }
+        if !self.trading_pairs.contains_key(&first_leg)
+            || !self.trading_pairs.contains_key(&second_leg)
+        {
+            return Err(anyhow!("implied pair legs are not registered"));
+        }
+        self.implied_pairs.insert(implied, (first_leg, second_leg));
+        Ok(())
+    }
+
+    /// Synthesizes the best level of the implied book for a taker of the given side and account
+    /// fee from the top of both leg books. Returns `None` if either leg lacks liquidity.
+    pub fn best_implied_level(
+        &self,
+        implied: &TradingPair,
+        taker_side: OrderSide,
+        taker_fee_fraction: Decimal,
+    ) -> Option<ImpliedLevel> {
+        let (first_leg, second_leg) = self.implied_pairs.get(implied)?;
+        let first_config = self.trading_pairs.get(first_leg)?;
+        let books = match taker_side {
+            OrderSide::Ask => &self.bid_books,
+            OrderSide::Bid => &self.ask_books,
+        };
+        let first = books.get(first_leg)?.peek()?;
+        let second = books.get(second_leg)?.peek()?;
+        let first_available = first.qty.saturating_sub(first.filled_quantity);
+        let second_available = second.qty.saturating_sub(second.filled_quantity);
+        let net = Decimal::ONE.saturating_sub(taker_fee_fraction);
+        // Maximum base quantity the second leg can support for the intermediate asset
+        let second_leg_limit = match taker_side {
+            // Buying the intermediate asset on the second leg, fees are paid in it.
+            OrderSide::Bid => second_available
+                .saturating_mul(net)
+                .checked_div(first.price)?,
+            // Selling the intermediate asset received net of fees on the first leg.
+            OrderSide::Ask => second_available.checked_div(first.price.saturating_mul(net))?,
+        };
+        let qty = first_available
+            .min(second_leg_limit)
+            .checked_div(first_config.qty_step_size)?
+            .floor()
+            .saturating_mul(first_config.qty_step_size);
+        if qty.is_zero() {
+            return None;
+        }
+        let level = ImpliedLevel {
+            first_leg_price: first.price,
+            second_leg_price: second.price,
+            qty,
+            intermediate_qty: Decimal::zero(),
+        };
+        let level = Self::resize_implied_level(level, qty, taker_side, taker_fee_fraction)?;
+        // Guard against rounding leaving the second leg short of liquidity
+        if level.intermediate_qty > second_available {
+            return None;
+        }
+        Some(level)
+    }
+
+    /// Matches a limit order on an implied pair against the legs' books. Every filled implied
+    /// level generates two linked trades, one on each leg, executed on behalf of the taker.
+    /// The closing leg is sized from what the opening leg received, and both legs are rolled
+    /// back if either does not fill. Implied orders never rest, the unfilled part is closed.
+    pub fn process_implied_order(
+        &mut self,
+        mut order: Order,
+        stid: u64,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        if order.order_type != OrderType::LIMIT {
+            return Err(anyhow!("only limit orders can be matched on implied pairs"));
+        }
+        let (first_leg, second_leg) = *self
+            .implied_pairs
+            .get(&order.pair)
+            .ok_or_else(|| anyhow!("pair is not registered as implied"))?;
+        let fee_fraction = self
+            .fees_collector
+            .fee_structure_of(&order.main_account)
+            .taker_fraction;
+        let mut result = OrderExecutionResult::new(stid);
+        // Index of the implied level, so that the legs of every level get their own ids
+        let mut level_index: u32 = 0;
+        while let Some(level) = self.best_implied_level(&order.pair, order.side, fee_fraction) {
+            let matches = match order.side {
+                OrderSide::Ask => level.price() >= order.price,
+                OrderSide::Bid => level.price() <= order.price,
+            };
+            let remaining = order.qty.saturating_sub(order.filled_quantity);
+            if !matches || remaining.is_zero() {
+                break;
+            }
+            let level = if remaining < level.qty {
+                match Self::resize_implied_level(level, remaining, order.side, fee_fraction) {
+                    Some(level) => level,
+                    None => break,
+                }
+            } else {
+                level
+            };
+            // Both leg trades must satisfy the minimum volume of their markets
+            let first_volume = level.qty.saturating_mul(level.first_leg_price);
+            let second_volume = level
+                .intermediate_qty
+                .saturating_mul(level.second_leg_price);
+            if is_below_min_volume(first_volume, &self.trading_pairs[&first_leg])
+                || is_below_min_volume(second_volume, &self.trading_pairs[&second_leg])
+            {
+                break;
+            }
+            // Guard the account balance up front so that no leg is executed on its own.
+            let (asset, required) = match order.side {
+                OrderSide::Bid => (
+                    second_leg.quote,
+                    level
+                        .intermediate_qty
+                        .saturating_mul(level.second_leg_price),
+                ),
+                OrderSide::Ask => (first_leg.base, level.qty),
+            };
+            let free = self
+                .balances
+                .get(&(order.main_account.clone(), asset))
+                .map(|(free, _)| *free)
+                .unwrap_or_default();
+            if free < Order::rounding_off(required) {
+                break;
+            }
+            // Sells on the first leg first, buys on the second leg first
+            let opening_leg = match order.side {
+                OrderSide::Bid => 2,
+                OrderSide::Ask => 1,
+            };
+            let opening = match order.side {
+                OrderSide::Bid => Self::leg_order(
+                    &order,
+                    second_leg,
+                    level_index,
+                    2,
+                    level.second_leg_price,
+                    level.intermediate_qty,
+                ),
+                OrderSide::Ask => Self::leg_order(
+                    &order,
+                    first_leg,
+                    level_index,
+                    1,
+                    level.first_leg_price,
+                    level.qty,
+                ),
+            };
+            // Legs are executed revertibly, so that no leg is kept on its own
+            let Some((opening, opening_result)) = self.execute_leg(opening, stid) else {
+                break;
+            };
+            let received = Self::received_by_leg(
+                &opening_result,
+                Self::leg_id(&order, level_index, opening_leg),
+            );
+            // The closing leg spends what the opening leg actually received
+            let closing = match order.side {
+                OrderSide::Bid => {
+                    let step = self.trading_pairs[&first_leg].qty_step_size;
+                    let qty = received
+                        .checked_div(level.first_leg_price)
+                        .and_then(|qty| qty.checked_div(step))
+                        .map(|steps| steps.floor().saturating_mul(step))
+                        .unwrap_or_default();
+                    Self::leg_order(
+                        &order,
+                        first_leg,
+                        level_index,
+                        1,
+                        level.first_leg_price,
+                        qty,
+                    )
+                }
+                OrderSide::Ask => {
+                    let step = self.trading_pairs[&second_leg].qty_step_size;
+                    let qty = received
+                        .checked_div(step)
+                        .map(|steps| steps.floor().saturating_mul(step))
+                        .unwrap_or_default();
+                    Self::leg_order(
+                        &order,
+                        second_leg,
+                        level_index,
+                        2,
+                        level.second_leg_price,
+                        qty,
+                    )
+                }
+            };
+            let closing_result = if closing.qty.is_zero() {
+                None
+            } else {
+                self.execute_leg(closing, stid)
+            };
+            let Some((closing, closing_result)) = closing_result else {
+                log::error!(target:"engine","Rolling back the opening leg of implied order {:?}",order.id);
+                self.rollback(opening);
+                break;
+            };
+            self.commit(opening);
+            self.commit(closing);
+            // Base quantity of the implied pair traded on the first leg
+            let first_result = match order.side {
+                OrderSide::Bid => &closing_result,
+                OrderSide::Ask => &opening_result,
+            };
+            let filled = first_result
+                .modified_orders
+                .get(&Self::leg_id(&order, level_index, 1))
+                .map(|leg| leg.filled_quantity)
+                .unwrap_or(level.qty);
+            result.merge(opening_result);
+            result.merge(closing_result);
+            order.update_avg_price_and_filled_qty(level.price(), filled);
+            level_index += 1;
+        }
+        order.status = OrderStatus::CLOSED;
+        result.taker = Some(order.id);
+        result.modified_orders.insert(order.id, order);
+        Ok(result)
+    }
+
+    // Recomputes the intermediate quantity of an implied level for the given base quantity.
+    fn resize_implied_level(
+        level: ImpliedLevel,
+        qty: Decimal,
+        side: OrderSide,
+        fee_fraction: Decimal,
+    ) -> Option<ImpliedLevel> {
+        let net = Decimal::ONE.saturating_sub(fee_fraction);
+        let intermediate_qty = match side {
+            OrderSide::Bid => qty
+                .saturating_mul(level.first_leg_price)
+                .checked_div(net)?
+                .round_dp_with_strategy(9, RoundingStrategy::AwayFromZero),
+            OrderSide::Ask => Order::rounding_off(
+                qty.saturating_mul(level.first_leg_price)
+                    .saturating_mul(net),
+            ),
+        };
+        Some(ImpliedLevel {
+            qty,
+            intermediate_qty,
+            ..level
+        })
+    }
+
+    // Executes a leg revertibly. Legs that do not trade or are not filled completely are rolled
+    // back, a leg never rests.
+    fn execute_leg(
+        &mut self,
+        leg: Order,
+        stid: u64,
+    ) -> Option<(PreparedExecution, OrderExecutionResult)> {
+        let (leg_id, leg_pair) = (leg.id, leg.pair);
+        let (prepared, leg_result) = match self.execute_revertible(leg, stid) {
+            Ok(executed) => executed,
+            Err(err) => {
+                log::error!(target:"engine","Implied leg on {:?} failed: {:?}",leg_pair,err);
+                return None;
+            }
+        };
+        let filled = leg_result
+            .modified_orders
+            .get(&leg_id)
+            .is_some_and(|leg| leg.status != OrderStatus::OPEN);
+        if leg_result.trades.is_empty() || !filled {
+            log::error!(target:"engine","Implied leg on {:?} did not execute completely",leg_pair);
+            self.rollback(prepared);
+            return None;
+        }
+        Some((prepared, leg_result))
+    }
+
+    // Returns what the leg order received net of the fees it paid
+    fn received_by_leg(leg_result: &OrderExecutionResult, leg_id: OrderId) -> Decimal {
+        let Some(leg) = leg_result.modified_orders.get(&leg_id) else {
+            return Decimal::zero();
+        };
+        let gross = match leg.side {
+            OrderSide::Bid => leg.filled_quantity,
+            OrderSide::Ask => leg_result
+                .trades
+                .iter()
+                .filter(|trade| trade.taker.id == leg_id || trade.maker.id == leg_id)
+                .map(|trade| trade.price.saturating_mul(trade.amount))
+                .sum(),
+        };
+        let received_asset = match leg.side {
+            OrderSide::Bid => leg.pair.base,
+            OrderSide::Ask => leg.pair.quote,
+        };
+        let fees: Decimal = leg_result
+            .fee_receipts()
+            .iter()
+            .filter(|receipt| receipt.order_id == leg_id && receipt.asset == received_asset)
+            .map(|receipt| receipt.amt)
+            .sum();
+        Order::rounding_off(gross.saturating_sub(fees))
+    }
+
+    // Id of a leg order of the implied taker at the given implied level
+    fn leg_id(order: &Order, level_index: u32, leg: u8) -> OrderId {
+        H256::from(blake2_256(&(order.id, level_index, leg).encode()))
+    }
+
+    // Derives a leg order of the implied taker, with an id linked to the implied order.
+    fn leg_order(
+        order: &Order,
+        pair: TradingPair,
+        level_index: u32,
+        leg: u8,
+        price: Decimal,
+        qty: Decimal,
+    ) -> Order {
+        let mut leg_order = order.clone();
+        leg_order.id = Self::leg_id(order, level_index, leg);
+        leg_order.pair = pair;
+        leg_order.price = price;
+        leg_order.qty = qty;
+        leg_order.filled_quantity = Decimal::zero();
+        leg_order.avg_filled_price = Decimal::zero();
+        leg_order.fee = Decimal::zero();
+        leg_order.status = OrderStatus::OPEN;
+        leg_order
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{OrderExecutionResult, Orderbook};
+use anyhow::anyhow;
+use codec::Encode;
+use orderbook_primitives::types::{Order, OrderSide, OrderStatus, OrderType};
+use rust_decimal::Decimal;
+use sp_core::hashing::blake2_256;
+use sp_core::H256;
+
+/// A parent order that is posted as a ladder of child orders, one rung at a time.
+#[derive(Clone, Debug)]
+pub struct Ladder {
+    // Order of the first rung, the following rungs are derived from it
+    pub template: Order,
+    // Total number of rungs
+    pub levels: usize,
+    // Price offset between two rungs, away from the opposite side of the book
+    pub step: Decimal,
+}
+
+impl Ladder {
+    /// Returns the child order of the given rung
+    pub fn rung(&self, level: usize) -> Order {
+        let mut order = self.template.clone();
+        let offset = self.step.saturating_mul(Decimal::from(level));
+        order.price = match order.side {
+            OrderSide::Ask => order.price.saturating_add(offset),
+            OrderSide::Bid => order.price.saturating_sub(offset),
+        };
+        if level > 0 {
+            order.id = H256::from(blake2_256(&(self.template.id, level as u64).encode()));
+        }
+        order
+    }
+}
+
+impl Orderbook {
+    /// Posts the first rung of a ladder of `levels` limit orders spaced by `step`. Every time a
+    /// rung is filled, the next one is posted automatically.
+    pub fn register_ladder(
+        &mut self,
+        base_order: Order,
+        levels: usize,
+        step: Decimal,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        if base_order.order_type != OrderType::LIMIT {
+            return Err(anyhow!("only limit orders can be laddered"));
+        }
+        if levels == 0 || step <= Decimal::ZERO {
+            return Err(anyhow!(
+                "ladder needs at least one level and a positive step"
+            ));
+        }
+        let ladder = Ladder {
+            template: base_order,
+            levels,
+            step,
+        };
+        if ladder.rung(levels - 1).price <= Decimal::ZERO {
+            return Err(anyhow!("ladder rungs must have a positive price"));
+        }
+        let stid = ladder.template.stid;
+        self.post_rung(ladder, 0, stid)
+    }
+
+    // Posts the rung at `level`, chaining the next rung if it is consumed right away.
+    fn post_rung(
+        &mut self,
+        ladder: Ladder,
+        level: usize,
+        stid: u64,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        let rung = ladder.rung(level);
+        let id = rung.id;
+        let mut result = self.process_order(rung, stid)?;
+        if level + 1 < ladder.levels {
+            let consumed = result
+                .modified_orders
+                .get(&id)
+                .is_some_and(|order| order.status != OrderStatus::OPEN);
+            if consumed {
+                self.post_next_rung(ladder, level, stid, &mut result);
+            } else {
+                self.ladders.insert(id, (ladder, level));
+            }
+        }
+        Ok(result)
+    }
+
+    // Posts the rung after `level` and merges its result. The execution that filled the rung is
+    // already applied, so a rung that can not be posted is reported in the result and ends the
+    // ladder instead of failing the execution.
+    fn post_next_rung(
+        &mut self,
+        ladder: Ladder,
+        level: usize,
+        stid: u64,
+        result: &mut OrderExecutionResult,
+    ) {
+        let id = ladder.rung(level + 1).id;
+        match self.post_rung(ladder, level + 1, stid) {
+            Ok(next) => result.merge(next),
+            Err(err) => {
+                log::error!(target:"engine","Unable to post ladder rung {:?}: {:?}",id,err);
+                result.failed_rungs.push((id, err.to_string()));
+            }
+        }
+    }
+
+    // Posts the next rung of every ladder whose rung was filled as a maker in `settle_trades`.
+    pub(crate) fn advance_ladders(&mut self, stid: u64, result: &mut OrderExecutionResult) {
+        for id in std::mem::take(&mut self.triggered_rungs) {
+            if let Some((ladder, level)) = self.ladders.remove(&id) {
+                self.post_next_rung(ladder, level, stid, result);
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::Orderbook;
+use std::time::Duration;
+
+/// Maximum number of processing latencies kept
+pub const MAX_LATENCY_SAMPLES: usize = 10_000;
+
+impl Orderbook {
+    // Records the time it took to process an order, dropping the oldest sample beyond the cap
+    pub(crate) fn record_latency(&mut self, latency: Duration) {
+        self.latencies.push_back(latency);
+        if self.latencies.len() > MAX_LATENCY_SAMPLES {
+            self.latencies.pop_front();
+        }
+    }
+
+    /// Returns the p50, p95 and p99 of the latest order processing latencies, using the
+    /// nearest rank. Zero if no order was processed yet.
+    pub fn latency_percentiles(&self) -> (Duration, Duration, Duration) {
+        let mut samples: Vec<Duration> = self.latencies.iter().copied().collect();
+        samples.sort_unstable();
+        let percentile = |p: usize| {
+            let rank = (samples.len() * p).div_ceil(100);
+            samples
+                .get(rank.saturating_sub(1))
+                .copied()
+                .unwrap_or_default()
+        };
+        (percentile(50), percentile(95), percentile(99))
+    }
+}
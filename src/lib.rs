@@ -1,17 +1,58 @@
+mod auction;
+mod audit;
+mod batch;
+mod cancel;
+mod candles;
+mod commit;
+mod conversion;
+mod decision;
 mod error;
 mod fees;
+mod implied;
+mod ladder;
+mod latency;
+mod maintenance;
+mod queries;
+mod reference;
+mod replay;
+mod reservations;
+mod settings;
+mod settlement;
+mod tape;
+mod twap;
 mod utils;
 
 #[cfg(test)]
 mod tests;
 
+pub use crate::audit::PairStateDump;
+pub use crate::batch::BatchMode;
+pub use crate::candles::Candle;
+use crate::candles::{DEFAULT_CANDLE_INTERVAL, DEFAULT_CANDLE_RETENTION};
+pub use crate::commit::PreparedExecution;
+use crate::decision::{match_decision, MakerSelector};
+pub use crate::decision::{MatchDecision, SkipReason};
 use crate::error::Error;
-use crate::fees::{AccountFee, FeeCollector};
+pub use crate::fees::{AccountFee, FeeAsset, FeeReceipt};
+use crate::fees::{FeeCollector, TradeFlows};
+use crate::ladder::Ladder;
+pub use crate::queries::{
+    maker_taker_volume, trades_at_price, trades_for_account, ArbOpportunity, MakerTakerHint,
+    MemoryStats,
+};
+pub use crate::reservations::{ReservationEvent, ReservationReason};
+pub use crate::settings::{
+    MinVolumePolicy, NoReferencePolicy, PairSettings, PriceMode, ResidualPolicy,
+};
+pub use crate::settlement::SettlementInstruction;
+use crate::tape::DEFAULT_TRADE_TAPE_SIZE;
 use crate::utils::{
     calculate_assets_flows_from_trade, check_unreserved_balance_for_close_limit_orders_in_trades,
-    execute, will_orders_match,
+    is_below_min_volume, is_expired, match_against_book, pop_best_maker, protection_price,
+    rested_since, resting_reservation, round_to_tick, validate_pair_config,
 };
 use anyhow::anyhow;
+use codec::{Compact, DecodeAll, Encode};
 use log::info;
 use orderbook_primitives::ocex::TradingPairConfig;
 use orderbook_primitives::types::{
@@ -20,21 +61,61 @@ use orderbook_primitives::types::{
 use polkadex_primitives::{AccountId, AssetId};
 use rust_decimal::prelude::Zero;
 use rust_decimal::Decimal;
-use std::collections::{BTreeMap, BinaryHeap};
+use sp_core::H256;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+use std::time::Duration;
+
+/// Acknowledgement of an order that passed validation and had its balance reserved, but is
+/// not matched yet.
+#[derive(Debug, Clone)]
+pub struct AcceptedOrder {
+    order: Order,
+    config: TradingPairConfig,
+    // Time the order is executed at, in milliseconds
+    now: u64,
+    // Amount reserved for the order
+    reserved: Decimal,
+    // Reservation changes of the order
+    changes: OrderExecutionResult,
+}
+
+impl AcceptedOrder {
+    /// Returns the accepted order
+    pub fn order(&self) -> &Order {
+        &self.order
+    }
+
+    /// Returns the balance changes of reserving the order
+    pub fn reservation(&self) -> &OrderExecutionResult {
+        &self.changes
+    }
+}
 
 /// (TradingPair, OrderSide, Price) => Amount
 pub type PriceLevels = BTreeMap<(TradingPair, OrderSide, Decimal), Decimal>;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct OrderExecutionResult {
     // Final state of balances (main, assetid ) => (free, reserved)
     balances: BTreeMap<(AccountId, AssetId), (Decimal, Decimal)>,
+    // Net change of the total balance (main, assetid) => free + reserved delta
+    balance_deltas: BTreeMap<(AccountId, AssetId), Decimal>,
     // Final Price level state
     pricelevels: PriceLevels,
     // Final Order state
     modified_orders: BTreeMap<OrderId, Order>,
     // Trades generated
     trades: Vec<Trade>,
+    // Fees collected for the trades
+    fees: Vec<FeeReceipt>,
+    // Set when price level changes were truncated and subscribers must pull a full snapshot
+    resync_required: bool,
+    // Id of the processed taker order
+    taker: Option<OrderId>,
+    // Sides of the books that held orders before the execution and were emptied by it
+    emptied_sides: BTreeSet<(TradingPair, OrderSide)>,
+    // Ladder rungs that could not be posted and why, ending their ladder
+    failed_rungs: Vec<(OrderId, String)>,
     // State change id
     stid: u64,
 }
@@ -43,12 +124,162 @@ impl OrderExecutionResult {
     pub fn new(stid: u64) -> Self {
         Self {
             balances: Default::default(),
+            balance_deltas: Default::default(),
             pricelevels: Default::default(),
             modified_orders: Default::default(),
             trades: vec![],
+            fees: vec![],
+            resync_required: false,
+            taker: None,
+            emptied_sides: Default::default(),
+            failed_rungs: vec![],
             stid,
         }
     }
+
+    /// Returns true if the price level changes were truncated and a full snapshot must be pulled
+    pub fn resync_required(&self) -> bool {
+        self.resync_required
+    }
+
+    /// Returns a receipt for every fee charged by the trades of the execution, with the trade,
+    /// the paying account and order, the asset, the amount and whether it was paid as maker.
+    pub fn fee_receipts(&self) -> &[FeeReceipt] {
+        &self.fees
+    }
+
+    /// Returns the sides of the books the execution took the last resting order from
+    pub fn emptied_sides(&self) -> &BTreeSet<(TradingPair, OrderSide)> {
+        &self.emptied_sides
+    }
+
+    // Adds the change of the total balance of an account in an asset
+    pub(crate) fn record_balance_delta(&mut self, key: (AccountId, AssetId), delta: Decimal) {
+        let total = self.balance_deltas.entry(key).or_default();
+        *total = total.saturating_add(delta);
+    }
+
+    /// Returns the ladder rungs the execution could not post with the reason, their ladders
+    /// post no further rungs. The execution itself is not affected.
+    pub fn failed_rungs(&self) -> &[(OrderId, String)] {
+        &self.failed_rungs
+    }
+
+    /// Returns the filled fraction of the processed taker order, between 0 and 1. Market buys
+    /// defined by a quote budget are measured against the budget.
+    pub fn fill_ratio(&self) -> Decimal {
+        let Some(taker) = self
+            .taker
+            .and_then(|taker| self.modified_orders.get(&taker))
+        else {
+            return Decimal::zero();
+        };
+        let quote_budget = taker.side == OrderSide::Bid
+            && taker.order_type == OrderType::MARKET
+            && taker.qty.is_zero();
+        let (filled, requested) = if quote_budget {
+            (
+                taker.filled_quantity.saturating_mul(taker.avg_filled_price),
+                taker.quote_order_qty,
+            )
+        } else {
+            (taker.filled_quantity, taker.qty)
+        };
+        filled
+            .checked_div(requested)
+            .unwrap_or_default()
+            .min(Decimal::ONE)
+    }
+
+    /// Traces every trade back to its maker and taker orders and their filled quantities
+    /// around the trade. The orders of a trade are captured right after `execute` filled them
+    /// by the trade amount, so the quantities before the trade are recovered from it.
+    pub fn fill_traces(&self) -> Vec<FillTrace> {
+        self.trades
+            .iter()
+            .map(|trade| FillTrace {
+                trade_id: trade.trade_id(),
+                maker_id: trade.maker.id,
+                taker_id: trade.taker.id,
+                maker_filled_before: trade.maker.filled_quantity.saturating_sub(trade.amount),
+                maker_filled_after: trade.maker.filled_quantity,
+                taker_filled_before: trade.taker.filled_quantity.saturating_sub(trade.amount),
+                taker_filled_after: trade.taker.filled_quantity,
+            })
+            .collect()
+    }
+
+    /// Encodes the balances, price levels, trades, resync flag and stid of the result using SCALE.
+    /// Modified orders and fee receipts are not part of the encoding as they can be derived
+    /// from the trades, neither are the taker, the emptied sides and the failed ladder rungs.
+    pub fn encode_compact(&self) -> Vec<u8> {
+        (
+            &self.balances,
+            &self.pricelevels,
+            &self.trades,
+            self.resync_required,
+            Compact(self.stid),
+        )
+            .encode()
+    }
+
+    /// Decodes a result previously encoded with `encode_compact`. Bytes left over after the
+    /// result are rejected.
+    pub fn decode_compact(bytes: &[u8]) -> anyhow::Result<Self> {
+        let (balances, pricelevels, trades, resync_required, stid) =
+            <(
+                BTreeMap<(AccountId, AssetId), (Decimal, Decimal)>,
+                PriceLevels,
+                Vec<Trade>,
+                bool,
+                Compact<u64>,
+            )>::decode_all(&mut &bytes[..])
+            .map_err(|err| anyhow!("unable to decode execution result: {err:?}"))?;
+        Ok(Self {
+            balances,
+            balance_deltas: Default::default(),
+            pricelevels,
+            modified_orders: Default::default(),
+            trades,
+            fees: Default::default(),
+            resync_required,
+            taker: None,
+            emptied_sides: Default::default(),
+            failed_rungs: vec![],
+            stid: stid.0,
+        })
+    }
+
+    /// Folds the changes of a later result into this one. Balances, price levels and modified
+    /// orders of the later result win per key, balance deltas are added up, trades and fees
+    /// are appended and the highest stid is kept.
+    pub fn merge(&mut self, other: OrderExecutionResult) {
+        self.balances.extend(other.balances);
+        for (key, delta) in other.balance_deltas {
+            self.record_balance_delta(key, delta);
+        }
+        self.pricelevels.extend(other.pricelevels);
+        self.modified_orders.extend(other.modified_orders);
+        self.trades.extend(other.trades);
+        self.fees.extend(other.fees);
+        self.resync_required |= other.resync_required;
+        self.taker = self.taker.or(other.taker);
+        self.emptied_sides.extend(other.emptied_sides);
+        self.failed_rungs.extend(other.failed_rungs);
+        self.stid = self.stid.max(other.stid);
+    }
+}
+
+/// Filled quantities of the maker and taker of a trade before and after the trade.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FillTrace {
+    pub trade_id: H256,
+    pub maker_id: OrderId,
+    pub taker_id: OrderId,
+    pub maker_filled_before: Decimal,
+    pub maker_filled_after: Decimal,
+    pub taker_filled_before: Decimal,
+    pub taker_filled_after: Decimal,
 }
 
 pub struct Orderbook {
@@ -64,6 +295,68 @@ pub struct Orderbook {
     balances: BTreeMap<(AccountId, AssetId), (Decimal, Decimal)>,
     // Fee Collector
     fees_collector: FeeCollector,
+    // Implied pair => (first leg, second leg)
+    implied_pairs: BTreeMap<TradingPair, (TradingPair, TradingPair)>,
+    // Engine side settings of trading pairs
+    pair_settings: BTreeMap<TradingPair, PairSettings>,
+    // Resting ladder rung => (ladder, rung level)
+    ladders: BTreeMap<OrderId, (Ladder, usize)>,
+    // Ladder rungs filled during settlement whose next rung must be posted
+    triggered_rungs: Vec<OrderId>,
+    // Makers taken out of the book during matching because they would self trade
+    self_trade_cancels: Vec<Order>,
+    // Makers taken out of the book during matching because their residual is below one qty step
+    sub_step_residuals: Vec<Order>,
+    // Account receiving swept dust balances, the fee pot if not set
+    dust_account: Option<AccountId>,
+    // Maximum number of changed price levels published per result
+    max_published_levels: Option<usize>,
+    // (Pair, bucket start) => candle of the trades in the bucket
+    candles: BTreeMap<(TradingPair, u64), Candle>,
+    // Width of the candle buckets
+    candle_interval: u64,
+    // Maximum number of candle buckets kept per pair
+    candle_retention: usize,
+    // Trail of reserved balance changes, only kept when enabled
+    reservation_log: Option<Vec<ReservationEvent>>,
+    // (main, asset) => part of the free balance held for withdrawals
+    holds: BTreeMap<(AccountId, AssetId), Decimal>,
+    // Prepared execution id => (pair, accounts) frozen until it is committed or rolled back
+    prepared: BTreeMap<u64, (TradingPair, BTreeSet<AccountId>)>,
+    // Id of the next prepared execution
+    next_prepared_id: u64,
+    // Account => number of its orders resting in the books
+    open_orders: BTreeMap<AccountId, usize>,
+    // Maximum number of resting orders per account, unlimited if not set
+    max_open_orders: Option<usize>,
+    // Pair => (time, mid price) samples, oldest first
+    mid_samples: BTreeMap<TradingPair, VecDeque<(u64, Decimal)>>,
+    // Pair => (reference price, time it was set at)
+    reference_prices: BTreeMap<TradingPair, (Decimal, u64)>,
+    // Settle the trades of an execution on a working copy of the balances they touch
+    net_balance_updates: bool,
+    // Pair => latest trades, oldest first
+    trade_tape: BTreeMap<TradingPair, VecDeque<Trade>>,
+    // Maximum number of trades kept per pair
+    trade_tape_size: usize,
+    // Time it took to process the latest orders, oldest first
+    latencies: VecDeque<Duration>,
+    // (from, to) => amount of `to` one unit of `from` converts to
+    conversion_rates: BTreeMap<(AssetId, AssetId), Decimal>,
+    // Maker fees of resting orders not yet applied to them, see `PairSettings::defer_maker_fees`
+    deferred_maker_fees: BTreeMap<OrderId, Decimal>,
+    // Order => reserved balance per asset the order still holds
+    order_reservations: BTreeMap<OrderId, BTreeMap<AssetId, Decimal>>,
+    // (main, asset) => (free, reserved) changes accumulated while settling with balance netting
+    pending_balance_deltas: Option<BTreeMap<(AccountId, AssetId), (Decimal, Decimal)>>,
+    // Order => fees reserved on top of the order per asset, not paid yet
+    fee_reservations: BTreeMap<OrderId, BTreeMap<AssetId, Decimal>>,
+    // Latest time supplied by the caller, in milliseconds
+    clock: u64,
+    // Order => time of the clock when the order started resting, in milliseconds
+    rested_at: BTreeMap<OrderId, u64>,
+    // (main, asset) => balance before its first change while an execution is prepared
+    balance_journal: Option<BTreeMap<(AccountId, AssetId), Option<(Decimal, Decimal)>>>,
 }
 
 impl Default for Orderbook {
@@ -81,6 +374,37 @@ impl Orderbook {
             ask_books: Default::default(),
             balances: Default::default(),
             fees_collector: FeeCollector::initialize(),
+            implied_pairs: Default::default(),
+            pair_settings: Default::default(),
+            ladders: Default::default(),
+            triggered_rungs: Default::default(),
+            self_trade_cancels: Default::default(),
+            sub_step_residuals: Default::default(),
+            dust_account: None,
+            max_published_levels: None,
+            candles: Default::default(),
+            candle_interval: DEFAULT_CANDLE_INTERVAL,
+            candle_retention: DEFAULT_CANDLE_RETENTION,
+            reservation_log: None,
+            holds: Default::default(),
+            prepared: Default::default(),
+            next_prepared_id: 0,
+            open_orders: Default::default(),
+            max_open_orders: None,
+            mid_samples: Default::default(),
+            reference_prices: Default::default(),
+            net_balance_updates: false,
+            trade_tape: Default::default(),
+            trade_tape_size: DEFAULT_TRADE_TAPE_SIZE,
+            latencies: Default::default(),
+            conversion_rates: Default::default(),
+            deferred_maker_fees: Default::default(),
+            order_reservations: Default::default(),
+            pending_balance_deltas: None,
+            fee_reservations: Default::default(),
+            clock: 0,
+            rested_at: Default::default(),
+            balance_journal: None,
         }
     }
 
@@ -92,7 +416,17 @@ impl Orderbook {
         fee_structures: BTreeMap<AccountId, AccountFee>,
     ) -> Self {
         let mut fees_collector = FeeCollector::initialize();
-        fees_collector.fee_structure = fee_structures;
+        fees_collector.fee_structure = fee_structures
+            .into_iter()
+            .map(|(main, fee)| (main, fee.into()))
+            .collect();
+        let mut open_orders: BTreeMap<AccountId, usize> = BTreeMap::new();
+        let mut order_reservations = BTreeMap::new();
+        for order in bid_books.values().chain(ask_books.values()).flatten() {
+            *open_orders.entry(order.main_account.clone()).or_default() += 1;
+            let (asset, amount) = resting_reservation(order);
+            order_reservations.insert(order.id, BTreeMap::from([(asset, amount)]));
+        }
         Self {
             trading_pairs,
             pricelevels: Default::default(),
@@ -100,6 +434,37 @@ impl Orderbook {
             ask_books,
             balances,
             fees_collector,
+            implied_pairs: Default::default(),
+            pair_settings: Default::default(),
+            ladders: Default::default(),
+            triggered_rungs: Default::default(),
+            self_trade_cancels: Default::default(),
+            sub_step_residuals: Default::default(),
+            dust_account: None,
+            max_published_levels: None,
+            candles: Default::default(),
+            candle_interval: DEFAULT_CANDLE_INTERVAL,
+            candle_retention: DEFAULT_CANDLE_RETENTION,
+            reservation_log: None,
+            holds: Default::default(),
+            prepared: Default::default(),
+            next_prepared_id: 0,
+            open_orders,
+            max_open_orders: None,
+            mid_samples: Default::default(),
+            reference_prices: Default::default(),
+            net_balance_updates: false,
+            trade_tape: Default::default(),
+            trade_tape_size: DEFAULT_TRADE_TAPE_SIZE,
+            latencies: Default::default(),
+            conversion_rates: Default::default(),
+            deferred_maker_fees: Default::default(),
+            order_reservations,
+            pending_balance_deltas: None,
+            fee_reservations: Default::default(),
+            clock: 0,
+            rested_at: Default::default(),
+            balance_journal: None,
         }
     }
 
@@ -113,15 +478,105 @@ impl Orderbook {
             .update_fee_structure(main, maker_fraction, taker_fraction);
     }
 
+    pub fn set_fee_assets(&mut self, maker_fee_asset: FeeAsset, taker_fee_asset: FeeAsset) {
+        self.fees_collector
+            .set_fee_assets(maker_fee_asset, taker_fee_asset);
+    }
+
+    /// Accrues fees to the given pot account, the balances accrued by the previous pot are
+    /// migrated to it.
+    pub fn set_pot(&mut self, pot: AccountId) {
+        let previous = self.fees_collector.set_pot(pot.clone());
+        if previous == pot {
+            return;
+        }
+        let accrued: Vec<AssetId> = self
+            .balances
+            .keys()
+            .filter(|(main, _)| *main == previous)
+            .map(|(_, asset)| *asset)
+            .collect();
+        for asset in accrued {
+            if let Some((free, reserved)) = self.balances.remove(&(previous.clone(), asset)) {
+                let balance = self.balances.entry((pot.clone(), asset)).or_default();
+                balance.0 = balance.0.saturating_add(free);
+                balance.1 = balance.1.saturating_add(reserved);
+                self.log_reservation(&previous, asset, -reserved, ReservationReason::PotMigration);
+                self.log_reservation(&pot, asset, reserved, ReservationReason::PotMigration);
+            }
+        }
+    }
+
+    pub fn set_size_discounts(&mut self, schedule: Vec<(Decimal, Decimal)>) {
+        self.fees_collector.set_size_discounts(schedule);
+    }
+
+    pub fn set_fee_free_until(&mut self, main: &AccountId, stid: u64) {
+        self.fees_collector.set_fee_free_until(main, stid);
+    }
+
+    pub fn set_default_fee(&mut self, fee: AccountFee) {
+        self.fees_collector.set_default_fee(fee);
+    }
+
+    pub fn update_flat_fee(&mut self, main: &AccountId, flat_fee: Decimal) {
+        self.fees_collector.update_flat_fee(main, flat_fee);
+    }
+
+    pub fn update_priority_boost(&mut self, main: &AccountId, priority_boost: u32) {
+        self.fees_collector
+            .update_priority_boost(main, priority_boost);
+    }
+
+    pub fn set_pair_settings(&mut self, pair: TradingPair, settings: PairSettings) {
+        self.pair_settings.insert(pair, settings);
+    }
+
+    // Returns a mutable reference to the engine side settings of the pair.
+    pub fn pair_settings_mut(&mut self, pair: TradingPair) -> &mut PairSettings {
+        self.pair_settings.entry(pair).or_default()
+    }
+
+    // Returns the engine side settings of the pair, defaults if none are configured.
+    pub fn pair_settings(&self, pair: &TradingPair) -> PairSettings {
+        self.pair_settings.get(pair).cloned().unwrap_or_default()
+    }
+
     // This function will get the market config for the given pair.
-    // If the pair is not found in the config, it will return the default config.
-    pub fn get_pair_config(&self, pair: &TradingPair) -> Option<TradingPairConfig> {
-        let config = self.trading_pairs.get(pair).cloned();
-        config
+    // If the pair is not registered, it will return None.
+    pub fn get_pair_config(&self, pair: &TradingPair) -> Option<&TradingPairConfig> {
+        self.trading_pairs.get(pair)
+    }
+
+    // Returns true if the pair is registered
+    pub fn is_registered(&self, pair: &TradingPair) -> bool {
+        self.trading_pairs.contains_key(pair)
+    }
+
+    // Returns the registered pairs
+    pub fn list_pairs(&self) -> Vec<TradingPair> {
+        self.trading_pairs.keys().copied().collect()
+    }
+
+    /// Returns the latest time supplied by the caller, in milliseconds. Order timestamps are set
+    /// by clients and are never used as the current time.
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    /// Advances the clock to `now`, in milliseconds. The clock never moves backwards.
+    pub fn advance_clock(&mut self, now: u64) {
+        self.clock = self.clock.max(now);
     }
 
-    // Check if the order can match
+    // Check if the order can match at the time of the clock
     pub fn will_match(&self, order: &Order) -> bool {
+        self.will_match_at(order, self.clock)
+    }
+
+    /// Checks if the order can match at `now`, in milliseconds. Makers that outlived the order
+    /// ttl of the pair are skipped, so the order is checked against the best live maker.
+    pub fn will_match_at(&self, order: &Order, now: u64) -> bool {
         if order.order_type == OrderType::MARKET {
             return true;
         }
@@ -129,15 +584,20 @@ impl Orderbook {
             OrderSide::Ask => self.bid_books.get(&order.pair),
             OrderSide::Bid => self.ask_books.get(&order.pair),
         };
-        if let Some(book) = book {
-            if let Some(open_order) = book.peek() {
-                return match order.side {
-                    OrderSide::Ask => order.price <= open_order.price,
-                    OrderSide::Bid => order.price >= open_order.price,
-                };
-            }
-        }
-        false
+        let settings = self.pair_settings(&order.pair);
+        // Only expired makers are passed over, without a ttl the top of the book is the best
+        let best = book.and_then(|book| match settings.order_ttl {
+            None => book.peek(),
+            Some(ttl) => book
+                .iter()
+                .filter(|maker| !is_expired(rested_since(maker, &self.rested_at), ttl, now))
+                .max(),
+        });
+        // Self trade makers still need matching to be cancelled
+        best.is_some_and(|maker| {
+            let rested = rested_since(maker, &self.rested_at);
+            match_decision(order, maker, rested, &settings, now) != MatchDecision::No
+        })
     }
 
     pub fn match_order(
@@ -145,10 +605,11 @@ impl Orderbook {
         config: &TradingPairConfig,
         taker: &mut Order,
         trade_changes: &mut Vec<Trade>,
+        now: u64,
     ) {
         match taker.order_type {
-            OrderType::LIMIT => self.match_limit(taker, trade_changes, config),
-            OrderType::MARKET => self.match_market(taker, trade_changes, config),
+            OrderType::LIMIT => self.match_limit(taker, trade_changes, config, now),
+            OrderType::MARKET => self.match_market(taker, trade_changes, config, now),
         }
     }
 
@@ -159,12 +620,16 @@ impl Orderbook {
         taker: &mut Order,
         trade_changes: &mut Vec<Trade>,
         config: &TradingPairConfig,
+        now: u64,
     ) {
-        self.match_side(taker, trade_changes, config);
+        self.match_side(taker, trade_changes, config, now);
         // close the order if the available volume to trade is less than min config for the market
-        if taker.available_volume(None).lt(&config.min_volume()) {
+        if is_below_min_volume(taker.available_volume(None), config) {
             taker.status = OrderStatus::CLOSED;
         }
+        if taker.status == OrderStatus::CANCELLED {
+            self.change_status_of_order_in_trade(taker, trade_changes);
+        }
     }
 
     // This function will match the order with the opposite side of the book
@@ -177,17 +642,22 @@ impl Orderbook {
         taker: &mut Order,
         trade_changes: &mut Vec<Trade>,
         config: &TradingPairConfig,
+        now: u64,
     ) {
-        self.match_side(taker, trade_changes, config);
+        self.match_side(taker, trade_changes, config, now);
         //close the order as market orders cannot stay open
         taker.status = OrderStatus::CLOSED;
-        self.change_status_of_order_in_trade(trade_changes);
+        self.change_status_of_order_in_trade(taker, trade_changes);
     }
 
-    pub fn change_status_of_order_in_trade(&self, trade_changes: &mut [Trade]) {
-        let last_index = trade_changes.len().saturating_sub(1);
-        if let Some(last_trade) = trade_changes.get_mut(last_index) {
-            last_trade.taker.status = OrderStatus::CLOSED;
+    /// Applies the final status of the taker to every trade it took part in, independent of
+    /// the order of the trades.
+    pub fn change_status_of_order_in_trade(&self, taker: &Order, trade_changes: &mut [Trade]) {
+        for trade in trade_changes
+            .iter_mut()
+            .filter(|trade| trade.taker.id == taker.id)
+        {
+            trade.taker.status = taker.status;
         }
     }
 
@@ -205,6 +675,11 @@ impl Orderbook {
 
         //go through the trades and update the modified orders
         for trade in &changes.trades {
+            // Closed makers are dropped from the book
+            if trade.maker.status == OrderStatus::CLOSED {
+                self.count_open_order(&trade.maker.main_account, false);
+                self.rested_at.remove(&trade.maker.id);
+            }
             //update the maker order
             let mut maker = trade.maker.clone();
             maker.stid = changes.stid;
@@ -213,6 +688,29 @@ impl Orderbook {
         Ok(())
     }
 
+    // Rounds the average filled price of the published orders to the precision of their pair.
+    // Resting orders and the accounting keep the exact average.
+    pub(crate) fn round_published_avg_prices(&self, changes: &mut OrderExecutionResult) {
+        let traded = changes
+            .trades
+            .iter_mut()
+            .flat_map(|trade| [&mut trade.maker, &mut trade.taker]);
+        for order in changes.modified_orders.values_mut().chain(traded) {
+            self.round_published_avg_price(order);
+        }
+    }
+
+    // Rounds the average filled price of a published order to the precision of its pair
+    pub(crate) fn round_published_avg_price(&self, order: &mut Order) {
+        let precision = self
+            .pair_settings
+            .get(&order.pair)
+            .and_then(|settings| settings.avg_price_precision);
+        if let Some(dp) = precision {
+            order.avg_filled_price = order.avg_filled_price.round_dp(dp);
+        }
+    }
+
     pub fn insert_order(&mut self, order: &Order) -> anyhow::Result<()> {
         let book = match order.side {
             OrderSide::Ask => self.ask_books.get_mut(&order.pair),
@@ -221,12 +719,101 @@ impl Orderbook {
         //add to the orderbook
         if let Some(item) = book {
             item.push(order.clone());
+            self.count_open_order(&order.main_account, true);
+            self.rested_at.entry(order.id).or_insert(self.clock);
             Ok(())
         } else {
             Err(anyhow!(anyhow::Error::msg("order book not opened")))
         }
     }
 
+    /// Limits the number of orders an account can have resting in the books.
+    pub fn set_max_open_orders(&mut self, max_open_orders: Option<usize>) {
+        self.max_open_orders = max_open_orders;
+    }
+
+    /// Returns the number of orders of the account resting in the books.
+    pub fn open_orders_of(&self, main: &AccountId) -> usize {
+        self.open_orders.get(main).copied().unwrap_or_default()
+    }
+
+    // Counts an order of the account entering or leaving the books
+    pub(crate) fn count_open_order(&mut self, main: &AccountId, opened: bool) {
+        let count = self.open_orders.entry(main.clone()).or_default();
+        *count = if opened {
+            count.saturating_add(1)
+        } else {
+            count.saturating_sub(1)
+        };
+        if *count == 0 {
+            self.open_orders.remove(main);
+        }
+    }
+
+    // Rejects orders with a price or qty outside the bounds of the pair
+    fn check_bounds(&self, order: &Order) -> Result<(), Error> {
+        let settings = self.pair_settings(&order.pair);
+        let within = |value: Decimal, min: Option<Decimal>, max: Option<Decimal>| {
+            min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+        };
+        let priced = order.order_type == OrderType::LIMIT || !order.price.is_zero();
+        if priced && !within(order.price, settings.min_price, settings.max_price) {
+            return Err(Error::PriceOutOfBounds);
+        }
+        let sized = order.order_type == OrderType::LIMIT || !order.qty.is_zero();
+        if sized && !within(order.qty, settings.min_qty, settings.max_qty) {
+            return Err(Error::QtyOutOfBounds);
+        }
+        let volume = |price: Option<Decimal>, qty: Option<Decimal>| {
+            price.zip(qty).map(|(price, qty)| price.saturating_mul(qty))
+        };
+        let min_quote = volume(settings.min_price, settings.min_qty);
+        let max_quote = volume(settings.max_price, settings.max_qty);
+        if !order.quote_order_qty.is_zero() && !within(order.quote_order_qty, min_quote, max_quote)
+        {
+            return Err(Error::QuoteOutOfBounds);
+        }
+        Ok(())
+    }
+
+    // Returns true if the order would rest at a new price level of a pair that is at its cap,
+    // once the price levels are reduced by the trades of its execution
+    fn exceeds_price_level_cap(
+        &self,
+        order: &Order,
+        trades: &[Trade],
+        config: &TradingPairConfig,
+    ) -> bool {
+        let max_levels = self
+            .pair_settings
+            .get(&order.pair)
+            .and_then(|settings| settings.max_price_levels);
+        let Some(max_levels) = max_levels else {
+            return false;
+        };
+        if order.order_type != OrderType::LIMIT {
+            return false;
+        }
+        let mut levels: BTreeMap<(OrderSide, Decimal), Decimal> = [OrderSide::Bid, OrderSide::Ask]
+            .into_iter()
+            .flat_map(|side| {
+                self.pricelevels
+                    .range((order.pair, side, Decimal::MIN)..=(order.pair, side, Decimal::MAX))
+                    .map(|((_, side, price), qty)| ((*side, *price), *qty))
+            })
+            .collect();
+        for trade in trades {
+            if let Some(qty) = levels.get_mut(&(trade.maker.side, trade.maker.price)) {
+                *qty = qty.saturating_sub(trade.amount);
+            }
+        }
+        // Levels are removed once they fall below the min volume, see `reduce_from_pricelevel`
+        levels.retain(|(_, price), qty| {
+            !qty.is_zero() && !is_below_min_volume(price.saturating_mul(*qty), config)
+        });
+        !levels.contains_key(&(order.side, order.price)) && levels.len() >= max_levels
+    }
+
     pub fn settle_price_level_updates(
         &mut self,
         config: &TradingPairConfig,
@@ -255,7 +842,7 @@ impl Orderbook {
             self.reduce_from_pricelevel(
                 config,
                 trade.maker.pair,
-                trade.price,
+                trade.maker.price,
                 trade.amount,
                 trade.maker.side,
                 &mut changes.pricelevels,
@@ -263,6 +850,62 @@ impl Orderbook {
         }
     }
 
+    /// Replaces the changed price levels of pairs with a display tick size by the aggregated
+    /// display levels they fall in. Bids are rounded down and asks up to the display tick, the
+    /// internal price levels keep their full precision.
+    pub fn aggregate_display_levels(&self, changes: &mut OrderExecutionResult) {
+        let changed = std::mem::take(&mut changes.pricelevels);
+        for ((pair, side, price), qty) in changed {
+            let Some(tick) = self.pair_settings(&pair).display_tick_size else {
+                changes.pricelevels.insert((pair, side, price), qty);
+                continue;
+            };
+            let round_up = side == OrderSide::Ask;
+            let display_price = round_to_tick(price, tick, round_up);
+            let display_qty = self
+                .pricelevels
+                .iter()
+                .filter(|((level_pair, level_side, level_price), _)| {
+                    *level_pair == pair
+                        && *level_side == side
+                        && round_to_tick(*level_price, tick, round_up) == display_price
+                })
+                .fold(Decimal::zero(), |total, (_, qty)| {
+                    total.saturating_add(*qty)
+                });
+            changes
+                .pricelevels
+                .insert((pair, side, display_price), display_qty);
+        }
+    }
+
+    // Sets the maximum number of changed price levels published per result
+    pub fn set_max_published_levels(&mut self, max_levels: Option<usize>) {
+        self.max_published_levels = max_levels;
+    }
+
+    // Truncates the price level changes to the configured maximum. When truncated, the result
+    // is flagged so the subscriber pulls a full snapshot instead of applying the deltas.
+    pub fn cap_published_levels(&self, changes: &mut OrderExecutionResult) {
+        let Some(max_levels) = self.max_published_levels else {
+            return;
+        };
+        if changes.pricelevels.len() <= max_levels {
+            return;
+        }
+        let dropped: Vec<_> = changes
+            .pricelevels
+            .keys()
+            .skip(max_levels)
+            .cloned()
+            .collect();
+        for key in dropped {
+            changes.pricelevels.remove(&key);
+        }
+        changes.resync_required = true;
+        log::info!(target:"engine","Price level changes truncated to {:?}, resync required",max_levels);
+    }
+
     pub fn add_to_pricelevel(
         &mut self,
         config: &TradingPairConfig,
@@ -280,8 +923,7 @@ impl Orderbook {
             })
             .or_insert(qty);
         let vol = price.saturating_mul(q);
-        q = vol
-            .lt(&config.min_volume())
+        q = is_below_min_volume(vol, config)
             .then(Decimal::zero)
             .unwrap_or(q);
         // the price level q zero we remove it.
@@ -311,8 +953,7 @@ impl Orderbook {
             .or_insert(qty);
 
         let vol = price.saturating_mul(q);
-        q = vol
-            .lt(&config.min_volume())
+        q = is_below_min_volume(vol, config)
             .then(Decimal::zero)
             .unwrap_or(q);
         // the price level s zero we remove it.
@@ -324,7 +965,8 @@ impl Orderbook {
         log::info!(target:"engine","Update (Dec) price level: {:?} - {:?} - {:?}: qty: {:?}",pair,side,price,q);
     }
 
-    /// Updates the fees for order in memory
+    /// Updates the fee of a resting order in memory. The fee is changed in place, so the heap
+    /// keeps its layout and no order changes its priority.
     pub fn update_in_memory_order_state_with_fee(&mut self, order: &Order) {
         let book_option = match order.side {
             OrderSide::Ask => self.ask_books.get_mut(&order.pair),
@@ -332,32 +974,67 @@ impl Orderbook {
         };
 
         if let Some(book) = book_option {
-            println!("book len: {:?}", book.len());
-            let mut new_book = BinaryHeap::new();
-
-            while let Some(mut stored_order) = book.pop() {
-                if stored_order.id == order.id {
-                    stored_order.fee = order.fee;
-                    new_book.push(stored_order);
-                    break;
-                }
-                new_book.push(stored_order);
+            let mut orders = std::mem::take(book).into_vec();
+            if let Some(stored_order) = orders.iter_mut().find(|stored| stored.id == order.id) {
+                let before = stored_order.clone();
+                stored_order.fee = order.fee;
+                // The priority of an order must not depend on its fee
+                debug_assert_eq!(before.cmp(stored_order), std::cmp::Ordering::Equal);
             }
-
-            book.append(&mut new_book);
-            println!("book len: {:?}", book.len());
+            // The layout is still a valid heap, so rebuilding it moves no order
+            *book = BinaryHeap::from(orders);
         }
     }
 
+    /// Nets the balance changes of all trades of an execution per account and asset, so every
+    /// balance is written once after the trades are settled.
+    pub fn set_balance_netting(&mut self, enabled: bool) {
+        self.net_balance_updates = enabled;
+    }
+
+    /// Settles the balances and fees of the trades in the result. With balance netting
+    /// enabled, the balance changes of the trades are accumulated per account and asset and
+    /// every touched balance is written once after all trades are settled.
     pub fn settle_trades(
         &mut self,
         trading_pair_config: TradingPairConfig,
+        now: u64,
+        changes: &mut OrderExecutionResult,
+    ) {
+        if self.net_balance_updates {
+            self.pending_balance_deltas = Some(BTreeMap::new());
+        }
+        self.settle_trade_legs(trading_pair_config, now, changes);
+        // Write the net change of every touched balance once
+        for ((main, asset), (free_delta, reserved_delta)) in
+            self.pending_balance_deltas.take().unwrap_or_default()
+        {
+            self.adjust_balance(
+                &main,
+                asset,
+                free_delta,
+                reserved_delta,
+                ReservationReason::TradeSettle,
+                changes,
+            );
+        }
+        self.record_trades(&changes.trades);
+    }
+
+    // Settles the trades leg by leg on the balances
+    fn settle_trade_legs(
+        &mut self,
+        trading_pair_config: TradingPairConfig,
+        now: u64,
         changes: &mut OrderExecutionResult,
     ) {
         info!(target:"engine", "setting {:?} trades", changes.trades.len());
+        // The trades are taken out of the result while their balance changes are applied to it
+        let mut trades = std::mem::take(&mut changes.trades);
         // We only need to settle trades right now.
-        for trade in &mut changes.trades {
+        for trade in &mut trades {
             let trade_id = trade.trade_id();
+            self.record_candle(now, trade);
             let Trade {
                 maker,
                 taker,
@@ -366,125 +1043,244 @@ impl Orderbook {
                 ..
             } = trade;
 
-            // Check if underpriced execution
-            debug_assert!((*price).eq(&maker.price));
-            match taker.side {
-                OrderSide::Ask => {
-                    // Ignore - reservation happens in qty so price is not affecting it
-                }
-                OrderSide::Bid => {
-                    if *price < taker.price {
-                        let diff = taker.price.saturating_sub(*price);
-                        let to_unreserve = diff.saturating_mul(*amount);
-                        let final_state = self
-                            .balances
-                            .entry((taker.main_account.clone(), taker.pair.quote))
-                            .and_modify(|(free, reserved)| {
-                                *reserved =
-                                    reserved.saturating_sub(to_unreserve).max(Decimal::zero());
-                                *free = Order::rounding_off(free.saturating_add(to_unreserve));
-                            })
-                            .or_insert((Decimal::zero(), Decimal::zero()));
-                        changes
-                            .balances
-                            .insert((taker.main_account.clone(), taker.pair.quote), *final_state);
-                    }
+            // Check if underpriced execution, the maker never trades worse than its price
+            debug_assert!(match maker.side {
+                OrderSide::Ask => *price >= maker.price,
+                OrderSide::Bid => *price <= maker.price,
+            });
+            // Limit bids reserve the quote at their limit price, so any limit bid that trades
+            // below it gets the difference unreserved. Market bids return what they did not spend
+            // once matched. Asks reserve in qty so price does not affect them.
+            for order in [&*maker, &*taker] {
+                if order.order_type == OrderType::LIMIT
+                    && order.side == OrderSide::Bid
+                    && *price < order.price
+                {
+                    let diff = order.price.saturating_sub(*price);
+                    let to_unreserve = diff
+                        .saturating_mul(*amount)
+                        .min(self.reserved_balance(&order.main_account, order.pair.quote));
+                    self.adjust_balance(
+                        &order.main_account,
+                        order.pair.quote,
+                        to_unreserve,
+                        -to_unreserve,
+                        ReservationReason::TradeSettle,
+                        changes,
+                    );
+                    self.track_reservation(order.id, order.pair.quote, -to_unreserve);
                 }
             }
 
+            // Filled ladder rungs post their next rung once the order is settled
+            if maker.status == OrderStatus::CLOSED && self.ladders.contains_key(&maker.id) {
+                self.triggered_rungs.push(maker.id);
+            }
+
             let maker_main = maker.main_account.clone();
             let quantity = amount;
             for order in [maker, taker] {
-                let min_volume = trading_pair_config.min_volume;
-
                 // Calculate asset flow
-                let (receiving_asset, mut recv_amt, give_away_asset, lost_amt) =
+                let (receiving_asset, recv_amt, give_away_asset, lost_amt) =
                     calculate_assets_flows_from_trade(*price, order.side, order.pair, *quantity);
                 info!(target:"engine",
                     "receiving asset: {:?}, recv_amt: {:?}, give_away: {:?}, lost_amt: {:?}",
                     receiving_asset, recv_amt, give_away_asset, lost_amt
                 );
-                let un_reserve_balance =
-                    check_unreserved_balance_for_close_limit_orders_in_trades(order, min_volume);
+                let un_reserve_balance = check_unreserved_balance_for_close_limit_orders_in_trades(
+                    order,
+                    &trading_pair_config,
+                );
 
                 let is_maker = order.main_account == maker_main;
 
+                // Residual of a closed maker is either returned or donated to the pot
+                let donate_residual = is_maker
+                    && !un_reserve_balance.is_zero()
+                    && self.pair_settings(&order.pair).residual_policy
+                        == ResidualPolicy::DonateResidualToPot;
+                let refund = if donate_residual {
+                    Decimal::zero()
+                } else {
+                    un_reserve_balance
+                };
+
                 // Collect fees
-                let receipt = self.fees_collector.settle_trade_fees(
-                    &order.main_account,
+                // Filled quantity includes this trade, so it is the first fill if they are equal
+                let first_fill = order.filled_quantity == *quantity;
+                let mut flows = TradeFlows {
+                    recv_asset: receiving_asset,
+                    recv_amt,
+                    give_away_asset,
+                    lost_amt,
+                };
+                let mut receipt = self.fees_collector.settle_trade_fees(
+                    order,
                     trade_id,
                     is_maker,
-                    &mut recv_amt,
-                    receiving_asset,
+                    first_fill,
+                    changes.stid,
+                    &mut flows,
                 );
+                let recv_amt = flows.recv_amt;
+                // Fees in the given away asset are paid from the fee reserved with the order.
+                // Orders reserved without it pay from the free balance, as far as it goes.
+                let mut give_away_fee = flows.lost_amt.saturating_sub(lost_amt);
+                let mut reserved_fee = Decimal::zero();
+                if !give_away_fee.is_zero() {
+                    reserved_fee =
+                        give_away_fee.min(self.reserved_fee_of(&order.id, give_away_asset));
+                    let (free, _) = self.balance_of(&order.main_account, give_away_asset);
+                    give_away_fee = reserved_fee
+                        .saturating_add(give_away_fee.saturating_sub(reserved_fee).min(free));
+                    receipt.amt = give_away_fee;
+                }
 
                 // Update the collect fees in the order, note this is cumulative fees.
+                // Modified and resting orders are updated once from the receipts after all trades.
                 order.fee = Order::rounding_off(order.fee.saturating_add(receipt.amt));
-                changes.modified_orders.entry(order.id).and_modify(|o| {
-                    o.fee = order.fee;
-                });
 
-                self.update_in_memory_order_state_with_fee(order);
                 // Add fees to fees account
-                let final_state = self
-                    .balances
-                    .entry((self.fees_collector.pot.clone(), receipt.asset))
-                    .and_modify(|(free, _)| {
-                        *free = Order::rounding_off(free.saturating_add(receipt.amt));
-                    })
-                    .or_insert((receipt.amt, Decimal::zero()));
-
-                // Apply the final state of fees account to changes cache
-                changes.balances.insert(
-                    (self.fees_collector.pot.clone(), receipt.asset),
-                    *final_state,
+                let pot = self.fees_collector.pot.clone();
+                self.adjust_balance(
+                    &pot,
+                    receipt.asset,
+                    receipt.amt,
+                    Decimal::zero(),
+                    ReservationReason::TradeSettle,
+                    changes,
                 );
+                self.fees_collector
+                    .record_revenue(changes.stid, receipt.asset, receipt.amt);
+                self.fees_collector
+                    .record_fee_paid(&receipt.user, receipt.asset, receipt.amt);
+                changes.fees.push(receipt);
 
                 // Reduce the give_away_asset balance of the user by the lost_amt
-                let final_state = self
-                    .balances
-                    .entry((order.main_account.clone(), give_away_asset))
-                    .and_modify(|(free, reserved)| {
-                        *reserved =
-                            reserved.saturating_sub(lost_amt.saturating_add(un_reserve_balance));
-                        *free = Order::rounding_off(free.saturating_add(un_reserve_balance));
-                    })
-                    .or_insert((Decimal::zero(), Decimal::zero()));
-
-                // Apply the final state to changes cache
-                changes
-                    .balances
-                    .insert((order.main_account.clone(), give_away_asset), *final_state);
+                let final_state = self.adjust_balance(
+                    &order.main_account,
+                    give_away_asset,
+                    refund.saturating_sub(give_away_fee.saturating_sub(reserved_fee)),
+                    -lost_amt
+                        .saturating_add(un_reserve_balance)
+                        .saturating_add(reserved_fee),
+                    ReservationReason::TradeSettle,
+                    changes,
+                );
+                self.track_fee_reservation(order.id, give_away_asset, -reserved_fee);
+                self.track_reservation(
+                    order.id,
+                    give_away_asset,
+                    -lost_amt.saturating_add(un_reserve_balance),
+                );
+
+                if donate_residual {
+                    self.adjust_balance(
+                        &pot,
+                        give_away_asset,
+                        un_reserve_balance,
+                        Decimal::zero(),
+                        ReservationReason::TradeSettle,
+                        changes,
+                    );
+                }
                 info!(target:"engine",
                     "giveaway asset: {:?}, final state: {:?}",
                     give_away_asset, final_state
                 );
 
                 // Increase the receiving_asset balance of the user by the recv_amt
-                let final_state = self
-                    .balances
-                    .entry((order.main_account.clone(), receiving_asset))
-                    .and_modify(|(free, _reserved)| {
-                        *free = Order::rounding_off(free.saturating_add(recv_amt))
-                    })
-                    .or_insert((recv_amt, Decimal::zero()));
-
-                // Apply the final state to changes cache
-                changes
-                    .balances
-                    .insert((order.main_account.clone(), receiving_asset), *final_state);
+                let final_state = self.adjust_balance(
+                    &order.main_account,
+                    receiving_asset,
+                    recv_amt,
+                    Decimal::zero(),
+                    ReservationReason::TradeSettle,
+                    changes,
+                );
 
                 info!(target:"engine",
                     "receiving asset: {:?}, final state: {:?}",
                     receiving_asset, final_state
                 );
+
+                // The flat settlement fee is charged once, at the first fill of the order
+                if first_fill {
+                    self.charge_settlement_fee(order, changes);
+                }
+            }
+        }
+        changes.trades = trades;
+        self.apply_fee_receipts(changes);
+    }
+
+    /// Applies the fees collected in the result to the modified orders and to the resting
+    /// orders in memory. Every affected book is rebuilt once, instead of once per trade.
+    /// On pairs deferring maker fees, the maker fees of orders still resting are accrued
+    /// aside and applied once the order is closed or cancelled.
+    /// Returns the number of resting orders updated.
+    pub fn apply_fee_receipts(&mut self, changes: &mut OrderExecutionResult) -> usize {
+        let mut accrued: BTreeMap<OrderId, Decimal> = BTreeMap::new();
+        for receipt in &changes.fees {
+            let resting_maker = receipt.is_maker
+                && changes
+                    .modified_orders
+                    .get(&receipt.order_id)
+                    .is_some_and(|order| {
+                        order.status == OrderStatus::OPEN
+                            && self.pair_settings(&order.pair).defer_maker_fees
+                    });
+            let fees = if resting_maker {
+                &mut self.deferred_maker_fees
+            } else {
+                &mut accrued
+            };
+            let fee = fees.entry(receipt.order_id).or_default();
+            *fee = fee.saturating_add(receipt.amt);
+        }
+        let mut books: BTreeMap<(TradingPair, OrderSide), BTreeMap<OrderId, Decimal>> =
+            BTreeMap::new();
+        for (order_id, fee) in accrued {
+            if let Some(order) = changes.modified_orders.get_mut(&order_id) {
+                let fee = fee.saturating_add(self.take_deferred_maker_fee(&order_id));
+                order.fee = Order::rounding_off(order.fee.saturating_add(fee));
+                books
+                    .entry((order.pair, order.side))
+                    .or_default()
+                    .insert(order_id, fee);
             }
         }
+        let mut updated = 0;
+        for ((pair, side), fees) in books {
+            let book = match side {
+                OrderSide::Ask => self.ask_books.get_mut(&pair),
+                OrderSide::Bid => self.bid_books.get_mut(&pair),
+            };
+            if let Some(book) = book {
+                let mut orders = std::mem::take(book).into_vec();
+                for order in orders.iter_mut() {
+                    if let Some(fee) = fees.get(&order.id) {
+                        order.fee = Order::rounding_off(order.fee.saturating_add(*fee));
+                        updated += 1;
+                    }
+                }
+                *book = BinaryHeap::from(orders);
+            }
+        }
+        updated
+    }
+
+    // Removes and returns the maker fees deferred for the order
+    pub(crate) fn take_deferred_maker_fee(&mut self, order_id: &OrderId) -> Decimal {
+        self.deferred_maker_fees
+            .remove(order_id)
+            .unwrap_or_default()
     }
 
     pub fn free_reserve_balance_of_market_order(
         &mut self,
         order: &Order,
+        reserved: Decimal,
         changes: &mut OrderExecutionResult,
     ) -> anyhow::Result<()> {
         //Market Order will never get inserted in order-book hence we can unreserve the balances
@@ -495,15 +1291,30 @@ impl Orderbook {
                     let unfilled_amount = order.qty.saturating_sub(order.filled_quantity);
                     (unfilled_amount, order.pair.base)
                 }
+                // Buys return what their reservation, the quote budget or the estimate of a base
+                // qty buy, did not spend at the trade prices
                 OrderSide::Bid => {
-                    let unfilled_volume = order.quote_order_qty.saturating_sub(
-                        order.avg_filled_price.saturating_mul(order.filled_quantity),
-                    );
-                    (unfilled_volume, order.pair.quote)
+                    let spent: Decimal = changes
+                        .trades
+                        .iter()
+                        .filter(|trade| trade.taker.id == order.id)
+                        .map(|trade| trade.price.saturating_mul(trade.amount))
+                        .sum();
+                    (
+                        reserved.saturating_sub(spent).max(Decimal::zero()),
+                        order.pair.quote,
+                    )
                 }
             };
             if !unfilled_amount.is_zero() {
-                self.unreserve_balance(unfilled_amount, asset, order.main_account.clone(), changes);
+                self.unreserve_balance(
+                    unfilled_amount,
+                    asset,
+                    order.main_account.clone(),
+                    ReservationReason::MarketRefund,
+                    changes,
+                );
+                self.track_reservation(order.id, asset, -unfilled_amount);
                 log::info!(target:"engine","Un-reserving unfilled balance for market order: {:?}",unfilled_amount);
                 return Ok(());
             }
@@ -511,12 +1322,8 @@ impl Orderbook {
         Ok(())
     }
 
-    // Updates the balance map
-    pub fn reserve_balances(
-        &mut self,
-        order: &Order,
-        changes: &mut OrderExecutionResult,
-    ) -> anyhow::Result<()> {
+    // Returns the asset and amount that must be reserved for the order
+    pub fn reservation_of(&self, order: &Order) -> (AssetId, Decimal) {
         let (asset, amount) = match (order.side, order.order_type) {
             (OrderSide::Bid, OrderType::LIMIT) => (order.pair.quote, order.available_volume(None)),
             (OrderSide::Ask, OrderType::LIMIT) | (OrderSide::Ask, OrderType::MARKET) => (
@@ -525,39 +1332,199 @@ impl Orderbook {
             ),
             (OrderSide::Bid, OrderType::MARKET) => {
                 if order.quote_order_qty.is_zero() {
-                    (
-                        order.pair.base,
-                        order.qty.saturating_sub(order.filled_quantity),
-                    )
+                    (order.pair.quote, self.market_buy_reserve(order))
                 } else {
                     (order.pair.quote, order.quote_order_qty)
                 }
             }
         };
+        (asset, Order::rounding_off(amount))
+    }
+
+    // Estimates the quote a market buy defined by a base qty spends: the cost of taking its qty
+    // from the asks of the pair plus the reserve buffer percentage of the pair. Protected buys
+    // never trade above their protection price and reserve it like a limit bid.
+    fn market_buy_reserve(&self, order: &Order) -> Decimal {
+        let unfilled = order.qty.saturating_sub(order.filled_quantity);
+        if let Some(price) = protection_price(order) {
+            return price.saturating_mul(unfilled);
+        }
+        let mut remaining = unfilled;
+        let mut cost = Decimal::zero();
+        for (price, qty) in self.book_levels(&order.pair, OrderSide::Ask) {
+            if remaining.is_zero() {
+                break;
+            }
+            let taken = remaining.min(qty);
+            cost = cost.saturating_add(price.saturating_mul(taken));
+            remaining = remaining.saturating_sub(taken);
+        }
+        let buffer = self.pair_settings(&order.pair).market_buy_reserve_buffer;
+        cost.saturating_add(
+            cost.saturating_mul(buffer)
+                .checked_div(Decimal::ONE_HUNDRED)
+                .unwrap_or_default(),
+        )
+    }
+
+    // Updates the balance map
+    pub fn reserve_balances(
+        &mut self,
+        order: &Order,
+        changes: &mut OrderExecutionResult,
+    ) -> anyhow::Result<()> {
+        let (asset, amount) = self.reservation_of(order);
         log::debug!(target: "matching","Reserving {:?} of {:?}", asset,amount);
-        let amount = Order::rounding_off(amount);
-        let mut is_success = false;
-        let final_state = self
-            .balances
-            .entry((order.main_account.clone(), asset))
-            .and_modify(|(free, reserved)| {
-                if *free >= amount {
-                    *free = free.saturating_sub(amount);
-                    *reserved = reserved.saturating_add(amount);
-                    is_success = true;
-                } else {
-                    log::error!(target:"engine","Balance is corrupted: free: {:?},\
-                     amount: {:?}, asset: {:?}, main: {:?} ",free,amount,asset,order.main_account)
-                }
-            })
-            .or_insert((Decimal::zero(), Decimal::zero()));
-        if is_success {
-            changes
-                .balances
-                .insert((order.main_account.clone(), asset), *final_state);
+        let Some(&(free, reserved)) = self.balances.get(&(order.main_account.clone(), asset))
+        else {
+            return Err(Error::InsufficientBalance.into());
+        };
+        // Balances can never be negative, if they are the engine state is broken
+        if free.is_sign_negative() || reserved.is_sign_negative() {
+            log::error!(target:"engine","Balance is corrupted: free: {:?}, reserved: {:?},\
+             asset: {:?}, main: {:?} ",free,reserved,asset,order.main_account);
+            return Err(Error::BalanceCorruption.into());
+        }
+        // Held funds are being withdrawn and can not be reserved
+        let held = self.held_balance(&order.main_account, asset);
+        // Fees in the given away asset are reserved on top, the unpaid part is released when
+        // the order closes or is cancelled
+        let fee = self
+            .fees_collector
+            .max_give_away_fee(&order.main_account, order.side, amount);
+        if free.saturating_sub(held) < amount.saturating_add(fee) {
+            log::info!(target:"engine","Insufficient balance: free: {:?}, held: {:?}, amount: {:?},\
+             fee: {:?}, asset: {:?}, main: {:?} ",free,held,amount,fee,asset,order.main_account);
+            return Err(Error::InsufficientBalance.into());
+        }
+        let total = amount.saturating_add(fee);
+        self.adjust_balance(
+            &order.main_account,
+            asset,
+            -total,
+            total,
+            ReservationReason::OrderEntry,
+            changes,
+        );
+        self.track_reservation(order.id, asset, amount);
+        self.track_fee_reservation(order.id, asset, fee);
+        Ok(())
+    }
+
+    // Returns the flat settlement fee and its asset charged to the given order when it fills
+    pub fn settlement_fee_of(&self, order: &Order) -> Option<(AssetId, Decimal)> {
+        let settings = self.pair_settings(&order.pair);
+        if settings.settlement_fee.is_zero() {
+            return None;
+        }
+        let asset = settings.settlement_fee_asset.unwrap_or(order.pair.quote);
+        Some((asset, settings.settlement_fee))
+    }
+
+    // Returns the settlement fee reserved with the order. Fees in the asset the order receives
+    // are paid from the proceeds and not reserved.
+    fn reserved_settlement_fee_of(&self, order: &Order) -> Option<(AssetId, Decimal)> {
+        let (fee_asset, fee) = self.settlement_fee_of(order)?;
+        let receiving_asset = match order.side {
+            OrderSide::Ask => order.pair.quote,
+            OrderSide::Bid => order.pair.base,
+        };
+        (fee_asset != receiving_asset).then_some((fee_asset, fee))
+    }
+
+    // Ensures the account can reserve the settlement fee on top of the order and the fees it
+    // gives away.
+    pub fn check_settlement_fee_balance(&self, order: &Order) -> Result<(), Error> {
+        let Some((fee_asset, fee)) = self.reserved_settlement_fee_of(order) else {
             return Ok(());
+        };
+        let free = self
+            .balances
+            .get(&(order.main_account.clone(), fee_asset))
+            .map(|(free, _)| *free)
+            .unwrap_or_default();
+        let held = self.held_balance(&order.main_account, fee_asset);
+        let (reserve_asset, reserve_amount) = self.reservation_of(order);
+        let reserved = if reserve_asset == fee_asset {
+            reserve_amount.saturating_add(self.fees_collector.max_give_away_fee(
+                &order.main_account,
+                order.side,
+                reserve_amount,
+            ))
+        } else {
+            Decimal::zero()
+        };
+        if free.saturating_sub(held).saturating_sub(reserved) < fee {
+            return Err(Error::InsufficientSettlementFeeBalance);
+        }
+        Ok(())
+    }
+
+    // Reserves the settlement fee on top of the order, the part it does not pay is released
+    // when the order closes or is cancelled
+    fn reserve_settlement_fee(&mut self, order: &Order, changes: &mut OrderExecutionResult) {
+        let Some((asset, fee)) = self.reserved_settlement_fee_of(order) else {
+            return;
+        };
+        self.adjust_balance(
+            &order.main_account,
+            asset,
+            -fee,
+            fee,
+            ReservationReason::OrderEntry,
+            changes,
+        );
+        self.track_fee_reservation(order.id, asset, fee);
+    }
+
+    // Debits the flat settlement fee of the order into the pot, at its first fill as maker or
+    // taker. The fee is paid from what is reserved with the order, orders reserved without it
+    // pay from the free balance as far as it goes.
+    pub fn charge_settlement_fee(&mut self, order: &Order, changes: &mut OrderExecutionResult) {
+        let Some((asset, fee)) = self.settlement_fee_of(order) else {
+            return;
+        };
+        let reserved = fee.min(self.reserved_fee_of(&order.id, asset));
+        if !reserved.is_zero() {
+            self.adjust_balance(
+                &order.main_account,
+                asset,
+                reserved,
+                -reserved,
+                ReservationReason::TradeSettle,
+                changes,
+            );
+            self.track_fee_reservation(order.id, asset, -reserved);
+        }
+        let charged = self.charge_to_pot(&order.main_account, asset, fee, changes);
+        log::info!(target:"engine","Charged settlement fee: {:?} of {:?}",charged,asset);
+    }
+
+    // Moves the fee from the free balance of the account to the fee pot, as far as the free
+    // balance goes. Returns the charged amount.
+    pub(crate) fn charge_to_pot(
+        &mut self,
+        main: &AccountId,
+        asset: AssetId,
+        fee: Decimal,
+        changes: &mut OrderExecutionResult,
+    ) -> Decimal {
+        let (free, _) = self.balance_of(main, asset);
+        let charged = fee.min(free).max(Decimal::zero());
+        let pot = self.fees_collector.pot.clone();
+        for (account, delta) in [(main, -charged), (&pot, charged)] {
+            self.adjust_balance(
+                account,
+                asset,
+                delta,
+                Decimal::zero(),
+                ReservationReason::TradeSettle,
+                changes,
+            );
         }
-        Err(anyhow::Error::msg("Error while reserving assets for order"))
+        self.fees_collector
+            .record_revenue(changes.stid, asset, charged);
+        charged
     }
 
     pub fn unreserve_balance(
@@ -565,91 +1532,60 @@ impl Orderbook {
         amount: Decimal,
         asset: AssetId,
         main: AccountId,
+        reason: ReservationReason,
         changes: &mut OrderExecutionResult,
     ) {
-        let final_state = self
-            .balances
-            .entry((main.clone(), asset))
-            .and_modify(|(free, reserved)| {
-                *reserved = reserved.saturating_sub(amount).max(Decimal::zero());
-                *free = Order::rounding_off(free.saturating_add(amount));
-            })
-            .or_insert((Decimal::zero(), Decimal::zero()));
-        changes.balances.insert((main, asset), *final_state);
+        // Never credit more than the account actually reserves
+        let amount = amount.min(self.reserved_balance(&main, asset));
+        self.adjust_balance(&main, asset, amount, -amount, reason, changes);
     }
 
-    // match two orders and add the trade to the changes and modified orders to the StateChanges
+    // match two orders and add the trade to the changes and modified orders to the StateChanges,
+    // makers are checked for expiry at `now`
     pub fn match_side(
         &mut self,
         taker: &mut Order,
         trade_changes: &mut Vec<Trade>,
         config: &TradingPairConfig,
+        now: u64,
     ) {
         let start = std::time::Instant::now();
-        let mut trades = Vec::new();
         let mut default = BinaryHeap::new();
+        let settings = self.pair_settings(&taker.pair);
+        let fees_collector = &self.fees_collector;
+        let boosted = fees_collector.has_priority_boosts();
+        // Expired makers and crossing makers of the taker's own account are cancelled
+        // instead of matched
+        let mut selector = MakerSelector::new(taker, &settings, config, &self.rested_at, now);
 
         let book = match taker.side {
             OrderSide::Ask => self.bid_books.get_mut(&taker.pair).unwrap_or(&mut default),
             OrderSide::Bid => self.ask_books.get_mut(&taker.pair).unwrap_or(&mut default),
         };
 
-        // Consume until the cache is empty
-        while !book.is_empty() {
-            // Get the first(best) order from the book
-            if let Some(mut other) = book.pop() {
-                //if takers volume is less than the min volume for the market,
-                // close the taker order and push the other order back into the book
-
-                if taker
-                    .available_volume(Some(other.price))
-                    .lt(&config.min_volume())
-                {
-                    taker.status = OrderStatus::CLOSED;
-                    book.push(other);
-                    break;
-                }
-
-                if !will_orders_match(taker, &other) {
-                    // other is added back into the book
-                    book.push(other);
-                    break;
-                }
-
-                if let Some(mut trade) = execute(taker, &mut other, config.qty_step_size) {
-                    if trade
-                        .maker
-                        .available_volume(Some(other.price))
-                        .lt(&config.min_volume())
-                    {
-                        // We will be dropping the maker order below if this condition is true
-                        //why is maker not being removed from the heap ?
-                        trade.maker.status = OrderStatus::CLOSED
-                    }
-
-                    // Check if other has enough volume to save it back to queue otherwise close it
-                    if !other.available_volume(None).lt(&config.min_volume()) {
-                        book.push(other.clone());
-                        println!(
-                            "Other available volume true: {:?}, len: {:?}",
-                            other.available_volume(None),
-                            book.len()
-                        );
-                    } else {
-                        println!(
-                            "Other available volume false: {:?}",
-                            other.available_volume(None)
-                        );
-                        other.status = OrderStatus::CLOSED
-                    }
-                    trades.push(trade);
+        let mut trades = match_against_book(taker, book, config, &settings, |book, taker| {
+            selector.next(book, taker, |book| {
+                if boosted {
+                    pop_best_maker(book, |main| fees_collector.priority_boost(main))
                 } else {
-                    // Other is not changed here so no need to update state change
-                    book.push(other);
-                    break;
+                    book.pop()
                 }
-            }
+            })
+        });
+        let MakerSelector {
+            skipped: mut self_trades,
+            mut residuals,
+            kept_whole,
+            level_capped,
+            ..
+        } = selector;
+        // The remainder of a limit taker stopped by the level cap or passing over makers kept
+        // whole would rest crossed
+        let crossed = level_capped || !kept_whole.is_empty();
+        if crossed && taker.status == OrderStatus::OPEN {
+            taker.status = OrderStatus::CANCELLED;
         }
+        book.extend(kept_whole);
         info!(
             "Matched limit order: {:?} and generated {:?} trades",
             taker.id,
@@ -657,47 +1593,205 @@ impl Orderbook {
         );
         info!(target:"engine","[fn:match_side] took {:?}",start.elapsed());
         trade_changes.append(&mut trades);
-        println!("Book len: {:?}", book.len());
+        for maker in self_trades.iter().chain(&residuals) {
+            self.count_open_order(&maker.main_account, false);
+            self.rested_at.remove(&maker.id);
+        }
+        self.self_trade_cancels.append(&mut self_trades);
+        self.sub_step_residuals.append(&mut residuals);
     }
 
-    pub fn add_trading_pair(&mut self, config: TradingPairConfig) {
+    pub fn add_trading_pair(&mut self, config: TradingPairConfig) -> Result<(), Error> {
         let pair = TradingPair::from(config.quote_asset, config.base_asset);
+        validate_pair_config(&config, &self.pair_settings(&pair))?;
         self.trading_pairs.insert(pair, config);
         self.bid_books.insert(pair, Default::default());
         self.ask_books.insert(pair, Default::default());
+        Ok(())
     }
 
+    /// Processes the order at the time of the clock, see `process_order_at`.
     pub fn process_order(
         &mut self,
-        mut order: Order,
+        order: Order,
         stid: u64,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        self.process_order_at(order, stid, self.clock)
+    }
+
+    /// Processes the order, trades are recorded in the candles of the bucket containing `now`.
+    pub fn process_order_at(
+        &mut self,
+        order: Order,
+        stid: u64,
+        now: u64,
     ) -> anyhow::Result<OrderExecutionResult> {
         let start = std::time::Instant::now();
         log::info!("Starting to process order {order:?}");
+        let accepted = self.accept_order_at(order, stid, now)?;
+        let execution_result = self.match_accepted(accepted)?;
+        let elapsed = start.elapsed();
+        info!(target:"engine","[fn:process_order] took {:?}", elapsed);
+        self.record_latency(elapsed);
+        Ok(execution_result)
+    }
+    /// Validates the order and reserves its balance at the time of the clock, see
+    /// `accept_order_at`.
+    pub fn accept_order(&mut self, order: Order, stid: u64) -> anyhow::Result<AcceptedOrder> {
+        self.accept_order_at(order, stid, self.clock)
+    }
+
+    /// Validates the order and reserves its balance without matching it. The returned
+    /// acknowledgement must be passed to `match_accepted` to complete the execution.
+    pub fn accept_order_at(
+        &mut self,
+        order: Order,
+        stid: u64,
+        now: u64,
+    ) -> anyhow::Result<AcceptedOrder> {
+        self.advance_clock(now);
         // Get the pair config if present otherwise return error.
         let config = self
             .get_pair_config(&order.pair)
+            .cloned()
             .ok_or(Error::TradingPairConfigNotFound)?;
+        // Market orders must define either the base qty or the quote budget
+        if order.order_type == OrderType::MARKET
+            && order.qty.is_zero()
+            && order.quote_order_qty.is_zero()
+        {
+            return Err(Error::InvalidMarketOrder.into());
+        }
+        // Extreme prices and quantities are rejected before they reach any arithmetic
+        self.check_bounds(&order)?;
+        // Market orders can not rest until the auction of an auction only pair
+        if order.order_type == OrderType::MARKET && self.pair_settings(&order.pair).auction_only {
+            return Err(Error::AuctionOnly.into());
+        }
 
-        let mut execution_result = OrderExecutionResult::new(stid);
+        // State of prepared executions can not change until they are committed or rolled back
+        self.ensure_not_frozen(&order.pair, &order.main_account)?;
+        // Protected orders need a fresh reference price
+        self.check_reference_price(&order, now)?;
+        // Limit orders may rest, so the account must be below its open order cap
+        if order.order_type == OrderType::LIMIT
+            && self
+                .max_open_orders
+                .is_some_and(|max| self.open_orders_of(&order.main_account) >= max)
+        {
+            return Err(Error::TooManyOpenOrders.into());
+        }
+        // Orders that can not trade now rest at a price level, which must fit in the level cap
+        let rests_untraded =
+            self.pair_settings(&order.pair).auction_only || !self.will_match_at(&order, now);
+        if rests_untraded && self.exceeds_price_level_cap(&order, &[], &config) {
+            return Err(Error::TooManyPriceLevels.into());
+        }
+
+        let mut changes = OrderExecutionResult::new(stid);
+        changes.taker = Some(order.id);
 
+        // Check the settlement fee can be paid before touching any balance
+        self.check_settlement_fee_balance(&order)?;
         // Reserve balances
-        self.reserve_balances(&order, &mut execution_result)?;
+        let (_, reserved) = self.reservation_of(&order);
+        self.reserve_balances(&order, &mut changes)?;
+        self.reserve_settlement_fee(&order, &mut changes);
+        Ok(AcceptedOrder {
+            order,
+            config,
+            now,
+            reserved,
+            changes,
+        })
+    }
+
+    /// Matches and settles an accepted order, its result includes the reservation changes.
+    pub fn match_accepted(
+        &mut self,
+        accepted: AcceptedOrder,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        let AcceptedOrder {
+            mut order,
+            config,
+            now,
+            reserved,
+            changes: mut execution_result,
+        } = accepted;
+        let stid = execution_result.stid;
+        let opposite_side = match order.side {
+            OrderSide::Ask => OrderSide::Bid,
+            OrderSide::Bid => OrderSide::Ask,
+        };
+        let opposite_resting = self.best_price(&order.pair, opposite_side).is_some();
+        // Orders of auction only pairs rest until the next auction
+        let auction_only = self.pair_settings(&order.pair).auction_only;
+        // Expired makers are cancelled instead of matched
+        if !auction_only {
+            self.cancel_expired_makers(&order, now, &mut execution_result);
+        }
         log::info!("checking if match can happen");
-        if self.will_match(&order) {
+        // Base qty market buys never spend more than the estimate they reserved
+        let budget_capped = order.order_type == OrderType::MARKET
+            && order.side == OrderSide::Bid
+            && order.quote_order_qty.is_zero();
+        if budget_capped {
+            order.quote_order_qty = reserved;
+        }
+        if !auction_only && self.will_match_at(&order, now) {
             // Order cannot match so insert.
-            self.match_order(&config, &mut order, &mut execution_result.trades);
+            self.match_order(&config, &mut order, &mut execution_result.trades, now);
+        }
+        // The cap is internal, the order is published as defined by its base qty
+        if budget_capped {
+            order.quote_order_qty = Decimal::zero();
+            for trade in execution_result
+                .trades
+                .iter_mut()
+                .filter(|trade| trade.taker.id == order.id)
+            {
+                trade.taker.quote_order_qty = Decimal::zero();
+            }
         }
         log::info!("generated {:?} trades", execution_result.trades.len());
+        // The remainder only rests if its price level fits in the cap once the trades settled
+        if order.status == OrderStatus::OPEN
+            && self.exceeds_price_level_cap(&order, &execution_result.trades, &config)
+        {
+            order.status = OrderStatus::CANCELLED;
+            self.change_status_of_order_in_trade(&order, &mut execution_result.trades);
+        }
         // settle order updates from trades
         self.settle_order_updates(&order, &mut execution_result)?;
         //Settle all price level updates from trades
         self.settle_price_level_updates(&config, &order, &mut execution_result);
         // Settle all balances from trades
-        self.settle_trades(config, &mut execution_result);
+        self.settle_trades(config, now, &mut execution_result);
+        // Anchor a missing reference price at the first fill of a protected order
+        self.anchor_reference_price(&order, &execution_result.trades, now);
+        // Cancel the makers skipped as expired or to prevent self trades
+        self.cancel_self_trade_makers(&mut execution_result);
+        // Close the makers whose residual is below one qty step
+        self.close_sub_step_residuals(&mut execution_result);
         // free reserve balance for market order
-        self.free_reserve_balance_of_market_order(&order, &mut execution_result)?;
-        info!(target:"engine","[fn:process_order] took {:?}", start.elapsed());
+        self.free_reserve_balance_of_market_order(&order, reserved, &mut execution_result)?;
+        // Release what rounding left reserved for the closed orders
+        self.release_closed_order_residuals(&mut execution_result);
+        // Post the next rungs of ladders filled by this order
+        self.advance_ladders(stid, &mut execution_result);
+        // Publish the price levels at the display tick
+        self.aggregate_display_levels(&mut execution_result);
+        // Limit the published price level changes
+        self.cap_published_levels(&mut execution_result);
+        // Publish the average filled prices at the precision of the pair
+        self.round_published_avg_prices(&mut execution_result);
+        // Flag the opposite side if the order took its last resting order
+        if opposite_resting && self.best_price(&order.pair, opposite_side).is_none() {
+            log::info!(target:"engine","Emptied {:?} side of {:?}",opposite_side,order.pair);
+            execution_result
+                .emptied_sides
+                .insert((order.pair, opposite_side));
+        }
         Ok(execution_result)
     }
 }
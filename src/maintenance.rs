@@ -0,0 +1,260 @@
+use crate::utils::resting_reservation;
+use crate::{OrderExecutionResult, Orderbook, ReservationReason};
+use orderbook_primitives::types::{OrderId, OrderSide, OrderStatus, TradingPair};
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+impl Orderbook {
+    /// Sets the account that receives swept dust, the fee pot is used if not set.
+    pub fn set_dust_account(&mut self, account: Option<AccountId>) {
+        self.dust_account = account;
+    }
+
+    /// Moves every free balance of the account below `threshold` to the dust account.
+    /// Reserved balances are never swept. Rejected while an execution of the account, or of a
+    /// pair it has orders resting in, is prepared.
+    pub fn sweep_dust(
+        &mut self,
+        account: &AccountId,
+        threshold: Decimal,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        self.ensure_account_not_frozen(account)?;
+        let mut changes = OrderExecutionResult::default();
+        let sink = self
+            .dust_account
+            .clone()
+            .unwrap_or_else(|| self.fees_collector.pot.clone());
+        if *account == sink {
+            return Ok(changes);
+        }
+        let dust: Vec<_> = self
+            .balances
+            .iter()
+            .filter(|((main, _), (free, _))| {
+                main == account && *free > Decimal::ZERO && *free < threshold
+            })
+            .map(|((_, asset), (free, _))| (*asset, *free))
+            .collect();
+        for (asset, amount) in dust {
+            self.move_free_balance(account, &sink, asset, amount, &mut changes);
+            log::info!(target:"engine","Swept dust of {:?}: {:?} of {:?}",account,amount,asset);
+        }
+        Ok(changes)
+    }
+
+    // Moves free balance of the asset from one account to another
+    fn move_free_balance(
+        &mut self,
+        from: &AccountId,
+        to: &AccountId,
+        asset: AssetId,
+        amount: Decimal,
+        changes: &mut OrderExecutionResult,
+    ) {
+        self.journal_balance(from, asset);
+        self.journal_balance(to, asset);
+        let final_state = self
+            .balances
+            .entry((from.clone(), asset))
+            .and_modify(|(free, _)| *free = free.saturating_sub(amount))
+            .or_default();
+        changes.balances.insert((from.clone(), asset), *final_state);
+        changes.record_balance_delta((from.clone(), asset), -amount);
+        let final_state = self
+            .balances
+            .entry((to.clone(), asset))
+            .and_modify(|(free, _)| *free = free.saturating_add(amount))
+            .or_insert((amount, Decimal::ZERO));
+        changes.balances.insert((to.clone(), asset), *final_state);
+        changes.record_balance_delta((to.clone(), asset), amount);
+    }
+
+    // Releases the reservations left behind by rounding when orders close. Whatever is still
+    // tracked as reserved for a closed order is returned to the free balance of its account, or
+    // swept to the dust account if the pair is configured so. Unpaid reserved fees are returned
+    // and so is the unfilled remainder of a cancelled taker.
+    pub(crate) fn release_closed_order_residuals(&mut self, changes: &mut OrderExecutionResult) {
+        let closed: Vec<(OrderId, AccountId, TradingPair, OrderStatus)> = changes
+            .modified_orders
+            .values()
+            .filter(|order| order.status != OrderStatus::OPEN)
+            .map(|order| {
+                (
+                    order.id,
+                    order.main_account.clone(),
+                    order.pair,
+                    order.status,
+                )
+            })
+            .collect();
+        for (order_id, main, pair, status) in closed {
+            self.release_reserved_fees(&order_id, &main, changes);
+            for (asset, residual) in self.take_order_reservation(&order_id) {
+                // The unfilled remainder of a cancelled order is never swept
+                if status == OrderStatus::CANCELLED {
+                    self.unreserve_balance(
+                        residual,
+                        asset,
+                        main.clone(),
+                        ReservationReason::Cancel,
+                        changes,
+                    );
+                } else {
+                    self.release_residual(&main, asset, residual, &pair, changes);
+                }
+            }
+        }
+    }
+
+    // Releases a reserved residual of the account, sweeping it if the pair is configured so
+    fn release_residual(
+        &mut self,
+        main: &AccountId,
+        asset: AssetId,
+        residual: Decimal,
+        pair: &TradingPair,
+        changes: &mut OrderExecutionResult,
+    ) {
+        // Never release more than the account actually reserves
+        let residual = residual.min(self.reserved_balance(main, asset));
+        if residual <= Decimal::ZERO {
+            return;
+        }
+        log::info!(target:"engine","Releasing reserved residual of {:?}: {:?} of {:?}",main,residual,asset);
+        self.unreserve_balance(
+            residual,
+            asset,
+            main.clone(),
+            ReservationReason::RoundingResidual,
+            changes,
+        );
+        let sink = self
+            .dust_account
+            .clone()
+            .unwrap_or_else(|| self.fees_collector.pot.clone());
+        if self.pair_settings(pair).sweep_reserve_residuals && *main != sink {
+            self.move_free_balance(main, &sink, asset, residual, changes);
+        }
+    }
+
+    /// Returns the reserved balance per asset backing the account's resting orders, what is
+    /// tracked as reserved for the orders themselves and the fees reserved on top of them.
+    pub fn backed_reservations(&self, account: &AccountId) -> BTreeMap<AssetId, Decimal> {
+        let mut backed: BTreeMap<AssetId, Decimal> = BTreeMap::new();
+        let mut add = |asset: AssetId, amount: Decimal| {
+            let reserved = backed.entry(asset).or_default();
+            *reserved = reserved.saturating_add(amount);
+        };
+        for book in self.bid_books.values().chain(self.ask_books.values()) {
+            for order in book.iter().filter(|order| order.main_account == *account) {
+                match self.order_reservations.get(&order.id) {
+                    Some(tracked) => tracked
+                        .iter()
+                        .for_each(|(asset, amount)| add(*asset, *amount)),
+                    None => {
+                        let (asset, amount) = resting_reservation(order);
+                        add(asset, amount);
+                    }
+                }
+                for (asset, fee) in self.fee_reservations.get(&order.id).into_iter().flatten() {
+                    add(*asset, *fee);
+                }
+            }
+        }
+        backed
+    }
+
+    /// Releases any reserved balance of the account that is not backed by a resting order,
+    /// e.g. a reservation left behind by a market order. Rejected while an execution of the
+    /// account, or of a pair it has orders resting in, is prepared.
+    pub fn reclaim_market_order_reservations(
+        &mut self,
+        account: &AccountId,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        self.ensure_account_not_frozen(account)?;
+        let mut changes = OrderExecutionResult::default();
+        let backed = self.backed_reservations(account);
+        let unbacked: Vec<_> = self
+            .balances
+            .iter()
+            .filter(|((main, _), _)| main == account)
+            .filter_map(|((_, asset), (_, reserved))| {
+                let excess =
+                    reserved.saturating_sub(backed.get(asset).copied().unwrap_or_default());
+                (excess > Decimal::ZERO).then_some((*asset, excess))
+            })
+            .collect();
+        for (asset, excess) in unbacked {
+            log::info!(target:"engine","Reclaiming unbacked reservation of {:?}: {:?} of {:?}",account,excess,asset);
+            self.unreserve_balance(
+                excess,
+                asset,
+                account.clone(),
+                ReservationReason::MarketRefund,
+                &mut changes,
+            );
+        }
+        Ok(changes)
+    }
+
+    /// Reconciles the account with its actual on-chain balance per asset. Resting orders are
+    /// cancelled, worst priced first, until the reserved balance no longer exceeds the actual
+    /// balance, the free balance is then set to what remains of the actual balance. Rejected
+    /// while an execution of the account, or of a pair it has orders resting in, is prepared.
+    pub fn reconcile_account(
+        &mut self,
+        account: &AccountId,
+        actual: BTreeMap<AssetId, Decimal>,
+    ) -> anyhow::Result<OrderExecutionResult> {
+        self.ensure_account_not_frozen(account)?;
+        let mut changes = OrderExecutionResult::default();
+        for (asset, total) in actual {
+            loop {
+                let reserved = self
+                    .balances
+                    .get(&(account.clone(), asset))
+                    .map(|(_, reserved)| *reserved)
+                    .unwrap_or_default();
+                if reserved <= total {
+                    break;
+                }
+                // Asks reserve the base asset and bids the quote asset, the worst priced ask
+                // is the highest one and the worst priced bid the lowest one.
+                let worst = self
+                    .resting_orders_of(account)
+                    .into_iter()
+                    .filter(|order| match order.side {
+                        OrderSide::Ask => order.pair.base == asset,
+                        OrderSide::Bid => order.pair.quote == asset,
+                    })
+                    .max_by(|a, b| {
+                        let by_price = match a.side {
+                            OrderSide::Ask => a.price.cmp(&b.price),
+                            OrderSide::Bid => b.price.cmp(&a.price),
+                        };
+                        by_price.then(a.timestamp.cmp(&b.timestamp))
+                    });
+                let Some(worst) = worst else {
+                    log::error!(target:"engine","Reserved balance of {:?} for {:?} is not backed by orders",account,asset);
+                    break;
+                };
+                let order = self
+                    .take_resting_order(&worst.pair, worst.side, &worst.id)
+                    .ok_or_else(|| anyhow::anyhow!("resting order not found in book"))?;
+                self.cancel_resting_order(order, &mut changes);
+            }
+            let final_state = self
+                .balances
+                .entry((account.clone(), asset))
+                .and_modify(|(free, reserved)| {
+                    *free = total.saturating_sub(*reserved).max(Decimal::ZERO);
+                })
+                .or_insert((total, Decimal::ZERO));
+            changes
+                .balances
+                .insert((account.clone(), asset), *final_state);
+        }
+        Ok(changes)
+    }
+}
@@ -0,0 +1,571 @@
+use crate::decision::MakerSelector;
+use crate::fees::{FeeOverride, TradeFlows};
+use crate::utils::{
+    calculate_assets_flows_from_trade, is_below_min_volume, match_against_book, pop_best_maker,
+    rested_since,
+};
+use crate::{OrderExecutionResult, Orderbook};
+use orderbook_primitives::types::{Order, OrderId, OrderSide, OrderType, Trade, TradingPair};
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+/// Number of entries held by the engine and a rough estimate of their size in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub resting_orders: usize,
+    pub price_levels: usize,
+    pub balance_entries: usize,
+    pub fee_structure_entries: usize,
+    // Size of the stored entries, without the overhead of the containers
+    pub estimated_bytes: usize,
+}
+
+/// Expected role of an order if it was processed against the current book.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MakerTakerHint {
+    /// Does not cross the book and rests entirely
+    PureMaker,
+    /// Is completely filled or closed without resting
+    PureTaker,
+    /// Crosses the book and rests the remainder
+    Mixed,
+}
+
+/// Internal liquidity that crosses the prices of an external market.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArbOpportunity {
+    // Side of the internal book to take liquidity from: buying internal asks below the
+    // external bid, or selling to internal bids above the external ask
+    pub side: OrderSide,
+    // Best internal price of that side
+    pub internal_price: Decimal,
+    // External price the liquidity is crossed against
+    pub external_price: Decimal,
+    // Internal quantity resting at prices crossing the external price
+    pub qty: Decimal,
+}
+
+impl Orderbook {
+    /// Returns the price of the best resting order on the given side of the book.
+    pub fn best_price(&self, pair: &TradingPair, side: OrderSide) -> Option<Decimal> {
+        let book = match side {
+            OrderSide::Ask => self.ask_books.get(pair),
+            OrderSide::Bid => self.bid_books.get(pair),
+        };
+        book.and_then(|book| book.peek()).map(|order| order.price)
+    }
+
+    /// Returns the price the next unit of an order of the given side fills at, the best price
+    /// of the opposite side.
+    pub fn marginal_price(&self, pair: &TradingPair, side: OrderSide) -> Option<Decimal> {
+        match side {
+            OrderSide::Ask => self.best_price(pair, OrderSide::Bid),
+            OrderSide::Bid => self.best_price(pair, OrderSide::Ask),
+        }
+    }
+
+    /// Returns the best bid and best ask of the pair.
+    pub fn bbo(&self, pair: &TradingPair) -> (Option<Decimal>, Option<Decimal>) {
+        (
+            self.best_price(pair, OrderSide::Bid),
+            self.best_price(pair, OrderSide::Ask),
+        )
+    }
+
+    /// Returns the midpoint of the best bid and best ask of the pair.
+    pub fn mid_price(&self, pair: &TradingPair) -> Option<Decimal> {
+        match self.bbo(pair) {
+            (Some(bid), Some(ask)) => bid.saturating_add(ask).checked_div(Decimal::TWO),
+            _ => None,
+        }
+    }
+
+    /// Scores the liquidity of the pair resting within `depth_pct` percent of the mid price.
+    /// Each price level in the band contributes its notional weighted linearly by its distance
+    /// to the mid price: `score = sum(price * qty * (1 - |price - mid| / band))` with
+    /// `band = mid * depth_pct / 100`, so deeper and tighter books score higher.
+    /// Returns zero if the pair has no mid price.
+    pub fn liquidity_score(&self, pair: &TradingPair, depth_pct: Decimal) -> Decimal {
+        let Some(mid) = self.mid_price(pair) else {
+            return Decimal::ZERO;
+        };
+        let band = mid
+            .saturating_mul(depth_pct)
+            .checked_div(Decimal::ONE_HUNDRED)
+            .unwrap_or_default();
+        if band <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+        let mut score = Decimal::ZERO;
+        for side in [OrderSide::Bid, OrderSide::Ask] {
+            for (price, qty) in self.book_levels(pair, side) {
+                let distance = price.saturating_sub(mid).abs();
+                if distance > band {
+                    // Levels are best first, so the rest of the side is outside the band too
+                    break;
+                }
+                let weight =
+                    Decimal::ONE.saturating_sub(distance.checked_div(band).unwrap_or_default());
+                score = score.saturating_add(price.saturating_mul(qty).saturating_mul(weight));
+            }
+        }
+        score
+    }
+
+    /// Returns the resting orders of the pair with the distance of their price from the mid
+    /// price, closest first. Returns nothing if the pair has no mid price.
+    pub fn orders_by_distance_from_mid(&self, pair: &TradingPair) -> Vec<(Order, Decimal)> {
+        let Some(mid) = self.mid_price(pair) else {
+            return vec![];
+        };
+        let mut orders: Vec<(Order, Decimal)> =
+            [self.bid_books.get(pair), self.ask_books.get(pair)]
+                .into_iter()
+                .flatten()
+                .flat_map(|book| book.iter())
+                .map(|order| (order.clone(), order.price.saturating_sub(mid).abs()))
+                .collect();
+        orders.sort_by(|(_, a), (_, b)| a.cmp(b));
+        orders
+    }
+
+    /// Returns the difference between the best ask and the best bid of the pair.
+    pub fn spread(&self, pair: &TradingPair) -> Option<Decimal> {
+        match self.bbo(pair) {
+            (Some(bid), Some(ask)) => Some(ask.saturating_sub(bid)),
+            _ => None,
+        }
+    }
+
+    /// Estimates the profit in quote per unit of base a maker of the account earns by posting
+    /// at the best bid and ask of the pair and getting filled on both sides. The maker fee is
+    /// paid on the notional of both legs, which add up to twice the mid price:
+    /// `capture = spread - 2 * mid * f`. Negative if the fees exceed the spread.
+    pub fn spread_capture(&self, account: &AccountId, pair: &TradingPair) -> Option<Decimal> {
+        let spread = self.spread(pair)?;
+        let mid = self.mid_price(pair)?;
+        let fee = self.fees_collector.fee_structure_of(account);
+        let fees = mid
+            .saturating_mul(Decimal::TWO)
+            .saturating_mul(fee.maker_fraction);
+        Some(spread.saturating_sub(fees))
+    }
+
+    /// Returns the spread around the mid price a maker of the account needs to cover the maker
+    /// fees of buying and selling back. Buying at `mid - s/2` and selling the received base at
+    /// `mid + s/2` breaks even when `(1 - f)^2 * (mid + s/2) = mid - s/2`.
+    /// Returns zero if the pair has no mid price.
+    pub fn breakeven_spread(&self, account: &AccountId, pair: &TradingPair) -> Decimal {
+        let Some(mid) = self.mid_price(pair) else {
+            return Decimal::ZERO;
+        };
+        let fee = self.fees_collector.fee_structure_of(account);
+        let net = Decimal::ONE.saturating_sub(fee.maker_fraction);
+        let round_trip = net.saturating_mul(net);
+        mid.saturating_mul(Decimal::TWO)
+            .saturating_mul(Decimal::ONE.saturating_sub(round_trip))
+            .checked_div(Decimal::ONE.saturating_add(round_trip))
+            .unwrap_or_default()
+    }
+
+    /// Returns true if a resting order at `price` on `side` would become the new best price of
+    /// that side. Any price improves an empty side, a price equal to the best only joins it.
+    pub fn would_improve_bbo(&self, pair: &TradingPair, side: OrderSide, price: Decimal) -> bool {
+        let (best_bid, best_ask) = self.bbo(pair);
+        match side {
+            OrderSide::Bid => best_bid.is_none_or(|best| price > best),
+            OrderSide::Ask => best_ask.is_none_or(|best| price < best),
+        }
+    }
+
+    /// Iterates over the resting orders of one side of the book without cloning them. The
+    /// orders come in heap order, not in priority order, so callers needing the best orders
+    /// first have to sort them.
+    pub fn iter_orders(&self, pair: &TradingPair, side: OrderSide) -> impl Iterator<Item = &Order> {
+        let book = match side {
+            OrderSide::Ask => self.ask_books.get(pair),
+            OrderSide::Bid => self.bid_books.get(pair),
+        };
+        book.into_iter().flat_map(|book| book.iter())
+    }
+
+    /// Returns the age of every resting order of the pair at `now`, oldest first. Like the min
+    /// resting time of cancels, the age is measured from the time of the engine clock when the
+    /// order started resting, not from its client timestamp.
+    pub fn age_profile(&self, pair: &TradingPair, now: u64) -> Vec<(OrderId, u64)> {
+        let mut ages: Vec<(OrderId, u64)> = [OrderSide::Bid, OrderSide::Ask]
+            .into_iter()
+            .flat_map(|side| self.iter_orders(pair, side))
+            .map(|order| {
+                let inserted = rested_since(order, &self.rested_at);
+                (order.id, now.saturating_sub(inserted))
+            })
+            .collect();
+        ages.sort_by(|(a_id, a_age), (b_id, b_age)| b_age.cmp(a_age).then(a_id.cmp(b_id)));
+        ages
+    }
+
+    /// Returns the (price, qty) levels of one side of the book, best price first.
+    pub fn book_levels(&self, pair: &TradingPair, side: OrderSide) -> Vec<(Decimal, Decimal)> {
+        let levels = self
+            .pricelevels
+            .range((*pair, side, Decimal::MIN)..=(*pair, side, Decimal::MAX))
+            .map(|((_, _, price), qty)| (*price, *qty));
+        match side {
+            OrderSide::Ask => levels.collect(),
+            OrderSide::Bid => levels.rev().collect(),
+        }
+    }
+
+    /// Compares the best prices of the pair against the external market and reports internal
+    /// liquidity that can be traded at a profit against it. Locked prices are no opportunity.
+    pub fn detect_arbitrage(
+        &self,
+        pair: &TradingPair,
+        external_bid: Decimal,
+        external_ask: Decimal,
+    ) -> Option<ArbOpportunity> {
+        let crossing = |side: OrderSide, external_price: Decimal| {
+            let levels: Vec<(Decimal, Decimal)> = self
+                .book_levels(pair, side)
+                .into_iter()
+                .take_while(|(price, _)| match side {
+                    OrderSide::Ask => *price < external_price,
+                    OrderSide::Bid => *price > external_price,
+                })
+                .collect();
+            let (internal_price, _) = *levels.first()?;
+            Some(ArbOpportunity {
+                side,
+                internal_price,
+                external_price,
+                qty: levels
+                    .iter()
+                    .fold(Decimal::ZERO, |total, (_, qty)| total.saturating_add(*qty)),
+            })
+        };
+        crossing(OrderSide::Ask, external_bid).or_else(|| crossing(OrderSide::Bid, external_ask))
+    }
+
+    /// Returns the resting quantity at the best price of the given side.
+    pub fn best_level(&self, pair: &TradingPair, side: OrderSide) -> Option<(Decimal, Decimal)> {
+        let price = self.best_price(pair, side)?;
+        let qty = self.pricelevels.get(&(*pair, side, price))?;
+        Some((price, *qty))
+    }
+
+    /// Returns the microprice of the pair, the best bid and ask weighted by the size resting
+    /// on the opposite side: `(bid * ask_size + ask * bid_size) / (bid_size + ask_size)`.
+    pub fn microprice(&self, pair: &TradingPair) -> Option<Decimal> {
+        let (bid, bid_size) = self.best_level(pair, OrderSide::Bid)?;
+        let (ask, ask_size) = self.best_level(pair, OrderSide::Ask)?;
+        bid.saturating_mul(ask_size)
+            .saturating_add(ask.saturating_mul(bid_size))
+            .checked_div(bid_size.saturating_add(ask_size))
+    }
+
+    /// Returns the average of the VWAP to buy `depth_qty` and the VWAP to sell `depth_qty`
+    /// against the book, a mid that a small order at the top of the book cannot move.
+    /// Returns `None` if either side holds less than `depth_qty`.
+    pub fn weighted_mid(&self, pair: &TradingPair, depth_qty: Decimal) -> Option<Decimal> {
+        let buy = self.vwap_to_fill(pair, OrderSide::Ask, depth_qty)?;
+        let sell = self.vwap_to_fill(pair, OrderSide::Bid, depth_qty)?;
+        buy.saturating_add(sell).checked_div(Decimal::TWO)
+    }
+
+    /// Returns the percentage the mid price moves by if a market order of the given side took
+    /// `shock_qty` from the book, positive if it moves up. Returns `None` if the pair has no mid
+    /// price or the order would take all liquidity of the opposite side.
+    pub fn shock_impact(
+        &self,
+        pair: &TradingPair,
+        side: OrderSide,
+        shock_qty: Decimal,
+    ) -> Option<Decimal> {
+        let mid = self.mid_price(pair)?;
+        let resting_side = match side {
+            OrderSide::Ask => OrderSide::Bid,
+            OrderSide::Bid => OrderSide::Ask,
+        };
+        // Best price left on the opposite side once the shock is taken
+        let mut remaining = shock_qty;
+        let mut shocked_price = None;
+        for (price, qty) in self.book_levels(pair, resting_side) {
+            if qty > remaining {
+                shocked_price = Some(price);
+                break;
+            }
+            remaining = remaining.saturating_sub(qty);
+        }
+        let shocked_mid = shocked_price?
+            .saturating_add(self.best_price(pair, side)?)
+            .checked_div(Decimal::TWO)?;
+        shocked_mid
+            .saturating_sub(mid)
+            .checked_div(mid)
+            .map(|change| change.saturating_mul(Decimal::ONE_HUNDRED))
+    }
+
+    /// Returns the `(cumulative_qty, avg_price)` points of an order of the given side sweeping
+    /// the book, sampled at `steps` even steps up to `max_qty`. The curve ends early where the
+    /// book is too thin to fill the next step.
+    pub fn fill_curve(
+        &self,
+        pair: &TradingPair,
+        side: OrderSide,
+        max_qty: Decimal,
+        steps: usize,
+    ) -> Vec<(Decimal, Decimal)> {
+        let resting_side = match side {
+            OrderSide::Ask => OrderSide::Bid,
+            OrderSide::Bid => OrderSide::Ask,
+        };
+        (1..=steps)
+            .filter_map(|index| {
+                max_qty
+                    .saturating_mul(Decimal::from(index))
+                    .checked_div(Decimal::from(steps))
+            })
+            .map_while(|qty| Some((qty, self.vwap_to_fill(pair, resting_side, qty)?)))
+            .collect()
+    }
+
+    // Average price of taking `qty` from the given resting side, best price first
+    fn vwap_to_fill(&self, pair: &TradingPair, side: OrderSide, qty: Decimal) -> Option<Decimal> {
+        if qty <= Decimal::ZERO {
+            return None;
+        }
+        let mut remaining = qty;
+        let mut notional = Decimal::ZERO;
+        for (price, level_qty) in self.book_levels(pair, side) {
+            let taken = remaining.min(level_qty);
+            notional = notional.saturating_add(price.saturating_mul(taken));
+            remaining = remaining.saturating_sub(taken);
+            if remaining.is_zero() {
+                return notional.checked_div(qty);
+            }
+        }
+        None
+    }
+
+    /// Returns the price level of the given resting side at which the cumulative resting
+    /// quantity, walking from the best price, reaches `cumulative_qty`.
+    /// Returns `None` if the book is too thin.
+    pub fn price_at_depth(
+        &self,
+        pair: &TradingPair,
+        side: OrderSide,
+        cumulative_qty: Decimal,
+    ) -> Option<Decimal> {
+        let mut cumulative = Decimal::ZERO;
+        for (price, qty) in self.book_levels(pair, side) {
+            cumulative = cumulative.saturating_add(qty);
+            if cumulative >= cumulative_qty {
+                return Some(price);
+            }
+        }
+        None
+    }
+
+    /// Returns the number of resting orders, price levels, balance and fee structure entries
+    /// held by the engine together with an estimate of their size.
+    pub fn memory_stats(&self) -> MemoryStats {
+        let resting_orders = self
+            .bid_books
+            .values()
+            .chain(self.ask_books.values())
+            .map(|book| book.len())
+            .sum();
+        let price_levels = self.pricelevels.len();
+        let balance_entries = self.balances.len();
+        let fee_structure_entries = self.fees_collector.fee_structure.len();
+        let estimated_bytes = resting_orders * size_of::<Order>()
+            + price_levels * size_of::<((TradingPair, OrderSide, Decimal), Decimal)>()
+            + balance_entries * size_of::<((AccountId, AssetId), (Decimal, Decimal))>()
+            + fee_structure_entries * size_of::<(AccountId, FeeOverride)>();
+        MemoryStats {
+            resting_orders,
+            price_levels,
+            balance_entries,
+            fee_structure_entries,
+            estimated_bytes,
+        }
+    }
+
+    /// Returns the limit price an order of the given side needs to fully fill `target_qty`
+    /// against the resting liquidity, the worst price it would touch.
+    /// Returns `None` if the liquidity is insufficient.
+    pub fn price_to_fill(
+        &self,
+        pair: &TradingPair,
+        side: OrderSide,
+        target_qty: Decimal,
+    ) -> Option<Decimal> {
+        if target_qty <= Decimal::ZERO {
+            return None;
+        }
+        let resting_side = match side {
+            OrderSide::Ask => OrderSide::Bid,
+            OrderSide::Bid => OrderSide::Ask,
+        };
+        self.price_at_depth(pair, resting_side, target_qty)
+    }
+
+    /// Returns the trade fees collected in the result per asset, which is the increase of the
+    /// fee pot caused by the trades of the result.
+    pub fn pending_fees(&self, result: &OrderExecutionResult) -> BTreeMap<AssetId, Decimal> {
+        let mut pending = BTreeMap::new();
+        for receipt in &result.fees {
+            let amount: &mut Decimal = pending.entry(receipt.asset).or_default();
+            *amount = amount.saturating_add(receipt.amt);
+        }
+        pending
+    }
+
+    /// Returns the asset the account receives and the amount it nets after fees if it traded
+    /// the given `(price, qty)` fills on one order of the given side, as maker or as taker.
+    pub fn net_proceeds(
+        &self,
+        main: &AccountId,
+        side: OrderSide,
+        pair: TradingPair,
+        fills: Vec<(Decimal, Decimal)>,
+        is_maker: bool,
+    ) -> (AssetId, Decimal) {
+        let recv_asset = match side {
+            OrderSide::Ask => pair.quote,
+            OrderSide::Bid => pair.base,
+        };
+        let mut proceeds = Decimal::ZERO;
+        for (index, (price, qty)) in fills.into_iter().enumerate() {
+            let (recv_asset, recv_amt, give_away_asset, lost_amt) =
+                calculate_assets_flows_from_trade(price, side, pair, qty);
+            let mut flows = TradeFlows {
+                recv_asset,
+                recv_amt,
+                give_away_asset,
+                lost_amt,
+            };
+            self.fees_collector
+                .apply_trade_fee(main, pair, is_maker, index == 0, &mut flows);
+            proceeds = proceeds.saturating_add(flows.recv_amt);
+        }
+        (recv_asset, proceeds)
+    }
+
+    /// Returns the fees accrued to the pot per asset by executions with a stid of at least
+    /// `since_stid`, including settlement and cancel fees.
+    pub fn fee_revenue(&self, since_stid: u64) -> BTreeMap<AssetId, Decimal> {
+        self.fees_collector.fee_revenue(since_stid)
+    }
+
+    /// Returns the total fees the account paid per asset on its trades.
+    pub fn fees_paid_by(&self, account: &AccountId) -> BTreeMap<AssetId, Decimal> {
+        self.fees_collector.fees_paid_by(account)
+    }
+
+    /// Returns the `(maker_id, price, fillable_qty)` sequence the taker would match against if it
+    /// was processed now. The matching runs on a copy of the book, so nothing is modified.
+    pub fn matching_preview(&self, taker: &Order) -> Vec<(OrderId, Decimal, Decimal)> {
+        let Some(config) = self.trading_pairs.get(&taker.pair) else {
+            return vec![];
+        };
+        if !self.will_match(taker) {
+            return vec![];
+        }
+        let mut book = match taker.side {
+            OrderSide::Ask => self.bid_books.get(&taker.pair),
+            OrderSide::Bid => self.ask_books.get(&taker.pair),
+        }
+        .cloned()
+        .unwrap_or_default();
+        let mut taker = taker.clone();
+        let boosted = self.fees_collector.has_priority_boosts();
+        let settings = self.pair_settings(&taker.pair);
+        // Makers are selected as by matching, skipped makers are left out of the preview
+        let mut selector =
+            MakerSelector::new(&taker, &settings, config, &self.rested_at, self.clock);
+        match_against_book(&mut taker, &mut book, config, &settings, |book, taker| {
+            selector.next(book, taker, |book| {
+                if boosted {
+                    pop_best_maker(book, |main| self.fees_collector.priority_boost(main))
+                } else {
+                    book.pop()
+                }
+            })
+        })
+        .into_iter()
+        .map(|trade| (trade.maker.id, trade.price, trade.amount))
+        .collect()
+    }
+
+    /// Classifies whether the order would be a pure maker, a pure taker or cross the book and
+    /// then rest, which lets clients estimate their fees before submission.
+    pub fn maker_classification(&self, order: &Order) -> MakerTakerHint {
+        if order.order_type == OrderType::MARKET {
+            return MakerTakerHint::PureTaker;
+        }
+        let fills = self.matching_preview(order);
+        if fills.is_empty() {
+            return MakerTakerHint::PureMaker;
+        }
+        let filled: Decimal = fills.iter().map(|(_, _, qty)| *qty).sum();
+        let remaining = order
+            .qty
+            .saturating_sub(order.filled_quantity)
+            .saturating_sub(filled);
+        let below_min_volume = self.trading_pairs.get(&order.pair).is_some_and(|config| {
+            is_below_min_volume(remaining.saturating_mul(order.price), config)
+        });
+        // A remainder below min volume is closed instead of resting
+        if below_min_volume {
+            MakerTakerHint::PureTaker
+        } else {
+            MakerTakerHint::Mixed
+        }
+    }
+}
+
+/// Returns the trades of the result involving the account together with whether the account
+/// was the maker. Self trades are returned twice, once for each role.
+pub fn trades_for_account<'a>(
+    result: &'a OrderExecutionResult,
+    account: &AccountId,
+) -> Vec<(&'a Trade, bool)> {
+    let mut trades = Vec::new();
+    for trade in &result.trades {
+        if trade.maker.main_account == *account {
+            trades.push((trade, true));
+        }
+        if trade.taker.main_account == *account {
+            trades.push((trade, false));
+        }
+    }
+    trades
+}
+
+/// Returns the trades of the result executed at exactly the given price. Prices are compared
+/// by value, so a price is matched whatever its scale, e.g. `1.50` matches `1.5`.
+pub fn trades_at_price(result: &OrderExecutionResult, price: Decimal) -> Vec<&Trade> {
+    result
+        .trades
+        .iter()
+        .filter(|trade| trade.price == price)
+        .collect()
+}
+
+/// Returns the quote notional traded in the maker role and in the taker role across the trades
+/// of the result. Every trade has one maker and one taker, so the two are always equal, they are
+/// returned per role for fee models that weight the roles separately.
+pub fn maker_taker_volume(result: &OrderExecutionResult) -> (Decimal, Decimal) {
+    let mut maker_volume = Decimal::ZERO;
+    let mut taker_volume = Decimal::ZERO;
+    for trade in &result.trades {
+        let notional = trade.price.saturating_mul(trade.amount);
+        maker_volume = maker_volume.saturating_add(notional);
+        taker_volume = taker_volume.saturating_add(notional);
+    }
+    (maker_volume, taker_volume)
+}
@@ -0,0 +1,54 @@
+use crate::error::Error;
+use crate::settings::NoReferencePolicy;
+use crate::utils::protection_price;
+use crate::Orderbook;
+use orderbook_primitives::types::{Order, Trade, TradingPair};
+use rust_decimal::Decimal;
+
+impl Orderbook {
+    /// Sets the reference price of the pair, as observed at `now` in milliseconds.
+    pub fn set_reference_price(&mut self, pair: TradingPair, price: Decimal, now: u64) {
+        self.reference_prices.insert(pair, (price, now));
+    }
+
+    /// Returns the reference price of the pair and the time it was set at.
+    pub fn reference_price(&self, pair: &TradingPair) -> Option<(Decimal, u64)> {
+        self.reference_prices.get(pair).copied()
+    }
+
+    // Rejects protected orders if the pair has a reference price ttl and its reference price
+    // is older than the ttl at `now`. A missing reference price is handled by the no reference
+    // policy of the pair.
+    pub(crate) fn check_reference_price(&self, order: &Order, now: u64) -> Result<(), Error> {
+        let settings = self.pair_settings(&order.pair);
+        let Some(ttl) = settings.reference_price_ttl else {
+            return Ok(());
+        };
+        if protection_price(order).is_none() {
+            return Ok(());
+        }
+        match self.reference_price(&order.pair) {
+            Some((_, set_at)) if now.saturating_sub(set_at) <= ttl => Ok(()),
+            None if settings.no_reference_policy == NoReferencePolicy::AnchorToFirstFill => Ok(()),
+            _ => Err(Error::StaleReferencePrice),
+        }
+    }
+
+    // Sets the price of the first fill of a protected order as the reference price of a pair
+    // that has none yet, if its policy anchors to the first fill
+    pub(crate) fn anchor_reference_price(&mut self, order: &Order, trades: &[Trade], now: u64) {
+        if protection_price(order).is_none() || self.reference_prices.contains_key(&order.pair) {
+            return;
+        }
+        let settings = self.pair_settings(&order.pair);
+        if settings.reference_price_ttl.is_none()
+            || settings.no_reference_policy != NoReferencePolicy::AnchorToFirstFill
+        {
+            return;
+        }
+        if let Some(first) = trades.first() {
+            log::info!(target:"engine","Anchored reference price of {:?} at {:?}",order.pair,first.price);
+            self.set_reference_price(order.pair, first.price, now);
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{OrderExecutionResult, Orderbook};
+use anyhow::anyhow;
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::Order;
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+impl Orderbook {
+    /// Replays a recorded stream of `(order, stid)` on a fresh engine with the given pair
+    /// configs and starting balances. Returns the final engine and the result of every order,
+    /// fails on the first order that cannot be processed.
+    pub fn replay(
+        configs: Vec<TradingPairConfig>,
+        balances: BTreeMap<(AccountId, AssetId), (Decimal, Decimal)>,
+        orders: Vec<(Order, u64)>,
+    ) -> anyhow::Result<(Orderbook, Vec<OrderExecutionResult>)> {
+        let mut orderbook = Orderbook::new();
+        for config in configs {
+            orderbook.add_trading_pair(config)?;
+        }
+        orderbook.balances = balances;
+        let mut results = Vec::with_capacity(orders.len());
+        for (order, stid) in orders {
+            let order_id = order.id;
+            let result = orderbook.process_order(order, stid).map_err(|err| {
+                anyhow!("replay of order {order_id:?} at stid {stid} failed: {err}")
+            })?;
+            results.push(result);
+        }
+        Ok((orderbook, results))
+    }
+}
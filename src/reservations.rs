@@ -0,0 +1,256 @@
+use crate::{OrderExecutionResult, Orderbook};
+use orderbook_primitives::types::{Order, OrderId};
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::prelude::Zero;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+/// A change of the reserved balance of an account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReservationEvent {
+    pub account: AccountId,
+    pub asset: AssetId,
+    // Change of the reserved balance, negative when balance is unreserved
+    pub delta: Decimal,
+    pub reason: ReservationReason,
+}
+
+/// Why a reserved balance changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReservationReason {
+    /// Reserved for a new order
+    OrderEntry,
+    /// Consumed or released while settling a trade
+    TradeSettle,
+    /// Released by a cancelled order
+    Cancel,
+    /// Released from the unfilled part of a market order
+    MarketRefund,
+    /// Released as a rounding residual of a closed order
+    RoundingResidual,
+    /// Moved along with the balances of a replaced fee pot
+    PotMigration,
+    /// Released from the fees reserved with an order that it did not pay
+    FeeRefund,
+}
+
+// Order => reserved balance per asset
+type OrderReservations = BTreeMap<OrderId, BTreeMap<AssetId, Decimal>>;
+
+// Adjusts the tracked reservation of the order, assets it no longer holds are dropped
+fn adjust_tracked(
+    tracked: &mut OrderReservations,
+    order_id: OrderId,
+    asset: AssetId,
+    delta: Decimal,
+) {
+    let reservation = tracked.entry(order_id).or_default();
+    let held = reservation.entry(asset).or_default();
+    *held = held.saturating_add(delta);
+    if *held <= Decimal::zero() {
+        reservation.remove(&asset);
+    }
+    if reservation.is_empty() {
+        tracked.remove(&order_id);
+    }
+}
+
+impl Orderbook {
+    /// Enables or disables the reservation log. Disabling drops the recorded events.
+    pub fn set_reservation_logging(&mut self, enabled: bool) {
+        match (enabled, self.reservation_log.is_some()) {
+            (true, false) => self.reservation_log = Some(Vec::new()),
+            (false, _) => self.reservation_log = None,
+            _ => {}
+        }
+    }
+
+    /// Returns the reserved balance changes recorded since the log was enabled or cleared.
+    pub fn reservation_log(&self) -> &[ReservationEvent] {
+        self.reservation_log.as_deref().unwrap_or_default()
+    }
+
+    /// Drops the recorded reservation events, logging stays enabled.
+    pub fn clear_reservation_log(&mut self) {
+        if let Some(log) = self.reservation_log.as_mut() {
+            log.clear();
+        }
+    }
+
+    // Records the balance of the account before its first change while an execution is prepared
+    pub(crate) fn journal_balance(&mut self, main: &AccountId, asset: AssetId) {
+        if let Some(journal) = self.balance_journal.as_mut() {
+            let key = (main.clone(), asset);
+            if !journal.contains_key(&key) {
+                let before = self.balances.get(&key).copied();
+                journal.insert(key, before);
+            }
+        }
+    }
+
+    // Records a change of the reserved balance if logging is enabled
+    pub(crate) fn log_reservation(
+        &mut self,
+        account: &AccountId,
+        asset: AssetId,
+        delta: Decimal,
+        reason: ReservationReason,
+    ) {
+        if delta.is_zero() {
+            return;
+        }
+        if let Some(log) = self.reservation_log.as_mut() {
+            log.push(ReservationEvent {
+                account: account.clone(),
+                asset,
+                delta,
+                reason,
+            });
+        }
+    }
+
+    // Applies the deltas to the free and reserved balance of the account, publishes the final
+    // state to the changes and logs the reserved change. Balances are clamped at zero.
+    pub(crate) fn adjust_balance(
+        &mut self,
+        main: &AccountId,
+        asset: AssetId,
+        free_delta: Decimal,
+        reserved_delta: Decimal,
+        reason: ReservationReason,
+        changes: &mut OrderExecutionResult,
+    ) -> (Decimal, Decimal) {
+        // While netting, the change is only accumulated and written once settled
+        if let Some(pending) = self.pending_balance_deltas.as_mut() {
+            let delta = pending.entry((main.clone(), asset)).or_default();
+            delta.0 = delta.0.saturating_add(free_delta);
+            delta.1 = delta.1.saturating_add(reserved_delta);
+            return self.balance_of(main, asset);
+        }
+        self.journal_balance(main, asset);
+        let balance = self.balances.entry((main.clone(), asset)).or_default();
+        let (free_before, reserved_before) = *balance;
+        let free = Order::rounding_off(free_before.saturating_add(free_delta));
+        let reserved = reserved_before.saturating_add(reserved_delta);
+        if free.is_sign_negative() || reserved.is_sign_negative() {
+            log::error!(target:"engine","Balance of {:?} in {:?} would go negative: free: {:?}, \
+             reserved: {:?}",main,asset,free,reserved);
+        }
+        *balance = (free.max(Decimal::zero()), reserved.max(Decimal::zero()));
+        let final_state = *balance;
+        changes.balances.insert((main.clone(), asset), final_state);
+        changes.record_balance_delta(
+            (main.clone(), asset),
+            final_state
+                .0
+                .saturating_add(final_state.1)
+                .saturating_sub(free_before.saturating_add(reserved_before)),
+        );
+        self.log_reservation(
+            main,
+            asset,
+            final_state.1.saturating_sub(reserved_before),
+            reason,
+        );
+        final_state
+    }
+
+    // Adjusts the reservation tracked for the order
+    pub(crate) fn track_reservation(&mut self, order_id: OrderId, asset: AssetId, delta: Decimal) {
+        adjust_tracked(&mut self.order_reservations, order_id, asset, delta);
+    }
+
+    // Adjusts the fees tracked as reserved on top of the order
+    pub(crate) fn track_fee_reservation(
+        &mut self,
+        order_id: OrderId,
+        asset: AssetId,
+        delta: Decimal,
+    ) {
+        adjust_tracked(&mut self.fee_reservations, order_id, asset, delta);
+    }
+
+    // Returns the fee still reserved on top of the order in the asset
+    pub(crate) fn reserved_fee_of(&self, order_id: &OrderId, asset: AssetId) -> Decimal {
+        self.fee_reservations
+            .get(order_id)
+            .and_then(|fees| fees.get(&asset))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Releases the fees still reserved on top of the order to the free balance of its account
+    pub(crate) fn release_reserved_fees(
+        &mut self,
+        order_id: &OrderId,
+        main: &AccountId,
+        changes: &mut OrderExecutionResult,
+    ) {
+        for (asset, fee) in self.fee_reservations.remove(order_id).unwrap_or_default() {
+            self.adjust_balance(
+                main,
+                asset,
+                fee,
+                -fee,
+                ReservationReason::FeeRefund,
+                changes,
+            );
+        }
+    }
+
+    // Stops tracking the reservation of the order and returns what it still holds per asset
+    pub(crate) fn take_order_reservation(
+        &mut self,
+        order_id: &OrderId,
+    ) -> BTreeMap<AssetId, Decimal> {
+        self.order_reservations.remove(order_id).unwrap_or_default()
+    }
+
+    /// Holds part of the free balance of the account, e.g. while it is withdrawn on-chain.
+    /// Held funds can not be reserved by new orders. Holds add up.
+    pub fn hold(&mut self, account: &AccountId, asset: AssetId, amount: Decimal) {
+        let held = self.holds.entry((account.clone(), asset)).or_default();
+        *held = held.saturating_add(amount);
+    }
+
+    /// Releases up to `amount` of the held balance of the account.
+    pub fn release(&mut self, account: &AccountId, asset: AssetId, amount: Decimal) {
+        let key = (account.clone(), asset);
+        if let Some(held) = self.holds.get_mut(&key) {
+            *held = held.saturating_sub(amount);
+            if *held <= Decimal::zero() {
+                self.holds.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the held balance of the account
+    pub fn held_balance(&self, account: &AccountId, asset: AssetId) -> Decimal {
+        self.holds
+            .get(&(account.clone(), asset))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Returns the reserved balance of the account
+    pub(crate) fn reserved_balance(&self, account: &AccountId, asset: AssetId) -> Decimal {
+        self.balance_of(account, asset).1
+    }
+
+    // Returns the (free, reserved) balance of the account including the changes accumulated
+    // while netting
+    pub(crate) fn balance_of(&self, account: &AccountId, asset: AssetId) -> (Decimal, Decimal) {
+        let key = (account.clone(), asset);
+        let (free, reserved) = self.balances.get(&key).copied().unwrap_or_default();
+        let (free_delta, reserved_delta) = self
+            .pending_balance_deltas
+            .as_ref()
+            .and_then(|pending| pending.get(&key))
+            .copied()
+            .unwrap_or_default();
+        (
+            free.saturating_add(free_delta),
+            reserved.saturating_add(reserved_delta),
+        )
+    }
+}
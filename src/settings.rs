@@ -0,0 +1,104 @@
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+
+/// Engine side settings of a trading pair which are not part of its `TradingPairConfig`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PairSettings {
+    // Handling of the reserved residual of makers closed below min volume
+    pub residual_policy: ResidualPolicy,
+    // Flat fee charged once per order that generates at least one trade
+    pub settlement_fee: Decimal,
+    // Asset of the settlement fee, the quote asset of the pair if not set
+    pub settlement_fee_asset: Option<AssetId>,
+    // Price at which matched orders trade
+    pub price_mode: PriceMode,
+    // Cancel resting makers instead of trading them against a taker of the same account
+    pub cancel_on_self_trade: bool,
+    // Fee in the quote asset charged for cancelling an order
+    pub cancel_fee: Decimal,
+    // Orders resting at least this long, in milliseconds, are cancelled without a fee
+    pub cancel_fee_exempt_age: Option<u64>,
+    // Orders can only be cancelled after resting this long, in milliseconds
+    pub min_resting_time: Option<u64>,
+    // Decimal places the average filled price of orders is rounded to, unrounded if not set
+    pub avg_price_precision: Option<u32>,
+    // Maximum number of price levels a limit order sweeps, the remainder rests
+    pub max_sweep_levels: Option<usize>,
+    // Protected market orders are rejected if the reference price is older than this, in
+    // milliseconds
+    pub reference_price_ttl: Option<u64>,
+    // Tick size price levels are published at, the internal levels keep their full precision
+    pub display_tick_size: Option<Decimal>,
+    // Handling of fills that would leave a maker below the min volume
+    pub min_volume_policy: MinVolumePolicy,
+    // Sweep the rounding residuals reserved for closed orders to the dust account instead of
+    // returning them to the free balance
+    pub sweep_reserve_residuals: bool,
+    // Maximum number of price levels of the pair, orders opening a new level beyond it are
+    // rejected and the remainder of takers that would is cancelled
+    pub max_price_levels: Option<usize>,
+    // Handling of protected market orders while the pair has no reference price yet
+    pub no_reference_policy: NoReferencePolicy,
+    // Orders resting longer than this, in milliseconds, are expired and no longer match
+    pub order_ttl: Option<u64>,
+    // Percentage added to the estimated cost reserved for market buys defined by a base qty
+    pub market_buy_reserve_buffer: Decimal,
+    // Orders rest without matching and only trade in `run_auction`
+    pub auction_only: bool,
+    // Inclusive price range of orders, protected market orders are checked by their
+    // protection price
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    // Inclusive base qty range of orders. Quote budgets are bounded by the volume range the
+    // price and qty bounds span
+    pub min_qty: Option<Decimal>,
+    pub max_qty: Option<Decimal>,
+    // Maker fees of resting orders are applied once when they close or are cancelled, instead
+    // of after every trade
+    pub defer_maker_fees: bool,
+}
+
+/// What happens to the reserved residual of a maker that is closed because its remaining
+/// volume dropped below the min volume of the market.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResidualPolicy {
+    /// Unreserve the residual back to the maker's free balance
+    #[default]
+    ReturnResidual,
+    /// Move the residual to the fee pot
+    DonateResidualToPot,
+}
+
+/// Price at which a taker and a maker trade.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PriceMode {
+    /// Trade at the price of the maker
+    #[default]
+    MakerPrice,
+    /// Limit takers trade at the midpoint of both limit prices, rounded to the price tick
+    /// towards the maker price. Market takers trade at the maker price.
+    Midpoint,
+}
+
+/// What happens to a maker that a fill would leave with a residual below the min volume of the
+/// market.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MinVolumePolicy {
+    /// Fill the maker and close it with its residual
+    #[default]
+    CloseResidual,
+    /// Skip the maker and keep it whole, the taker matches other makers instead. The remainder of
+    /// a limit taker that passed over such a maker is cancelled instead of resting crossed.
+    KeepMakerWhole,
+}
+
+/// What happens to a protected market order that needs a reference price while the pair has
+/// none yet, e.g. before its first trade.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoReferencePolicy {
+    /// Reject the order as if the reference price was stale
+    #[default]
+    Reject,
+    /// Accept the order and use the price of its first fill as the reference price
+    AnchorToFirstFill,
+}
@@ -0,0 +1,35 @@
+use crate::{OrderExecutionResult, Orderbook};
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::Decimal;
+
+/// Net change of the total balance of an account in one asset, to be settled on chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SettlementInstruction {
+    pub account: AccountId,
+    pub asset: AssetId,
+    // Credited to the account if positive, debited if negative
+    pub amount: Decimal,
+}
+
+impl Orderbook {
+    /// Nets the balance changes recorded while processing the result into one instruction per
+    /// account and asset. Trades, fees, settlement and cancel fees, residuals donated to the
+    /// pot and sweeps are all included, moves between the free and the reserved balance of an
+    /// account need no transfer and are not. Results decoded from their compact encoding carry
+    /// no balance changes and yield no instructions.
+    pub fn settlement_instructions(
+        &self,
+        result: &OrderExecutionResult,
+    ) -> Vec<SettlementInstruction> {
+        result
+            .balance_deltas
+            .iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .map(|((account, asset), amount)| SettlementInstruction {
+                account: account.clone(),
+                asset: *asset,
+                amount: *amount,
+            })
+            .collect()
+    }
+}
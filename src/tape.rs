@@ -0,0 +1,75 @@
+use crate::Orderbook;
+use orderbook_primitives::types::{OrderSide, Trade, TradingPair};
+use rust_decimal::Decimal;
+
+/// Default number of trades kept per pair for the trade tape
+pub const DEFAULT_TRADE_TAPE_SIZE: usize = 1000;
+
+impl Orderbook {
+    /// Sets the number of trades kept per pair. Pairs holding more drop their oldest trades.
+    pub fn set_trade_tape_size(&mut self, size: usize) {
+        self.trade_tape_size = size;
+        for tape in self.trade_tape.values_mut() {
+            let excess = tape.len().saturating_sub(size);
+            tape.drain(..excess);
+        }
+    }
+
+    // Appends settled trades to the tape of their pair, dropping the oldest beyond its size
+    pub(crate) fn record_trades(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            let tape = self.trade_tape.entry(trade.maker.pair).or_default();
+            tape.push_back(trade.clone());
+            if tape.len() > self.trade_tape_size {
+                tape.pop_front();
+            }
+        }
+    }
+
+    /// Returns the latest `n` trades of the pair, newest first.
+    pub fn recent_trades(&self, pair: &TradingPair, n: usize) -> Vec<Trade> {
+        self.trade_tape
+            .get(pair)
+            .map(|tape| tape.iter().rev().take(n).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Estimates how likely a new order at `price` on `side` of the pair gets filled, between
+    /// 0 and 1. The order joins the back of the queue, behind the resting volume at its price
+    /// and better, and the volume on the tape is taken as the trade rate that works through
+    /// it: `estimate = traded / (traded + queued)`. Orders crossing the opposite side fill
+    /// right away and estimate 1, pairs without trades on the tape estimate 0. This is a
+    /// heuristic for maker strategies, not a guarantee.
+    pub fn fill_estimate(&self, pair: &TradingPair, side: OrderSide, price: Decimal) -> Decimal {
+        let crosses = match side {
+            OrderSide::Bid => self
+                .best_price(pair, OrderSide::Ask)
+                .is_some_and(|ask| price >= ask),
+            OrderSide::Ask => self
+                .best_price(pair, OrderSide::Bid)
+                .is_some_and(|bid| price <= bid),
+        };
+        if crosses {
+            return Decimal::ONE;
+        }
+        let queued: Decimal = self
+            .book_levels(pair, side)
+            .into_iter()
+            .take_while(|(level, _)| match side {
+                OrderSide::Bid => *level >= price,
+                OrderSide::Ask => *level <= price,
+            })
+            .map(|(_, qty)| qty)
+            .sum();
+        let traded: Decimal = self
+            .trade_tape
+            .get(pair)
+            .into_iter()
+            .flatten()
+            .map(|trade| trade.amount)
+            .sum();
+        traded
+            .checked_div(traded.saturating_add(queued))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,48 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_accepted_order_is_acked_before_matching() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let order = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(5),
+        1,
+    );
+
+    let accepted = orderbook.accept_order(order.clone(), 1).unwrap();
+    assert_eq!(accepted.order().id, order.id);
+    let reserved = (Decimal::from(990), Decimal::from(10));
+    assert_eq!(
+        accepted.reservation().balances[&(alice.clone(), pair.quote)],
+        reserved
+    );
+    // Nothing is matched or resting yet
+    assert!(orderbook.bid_books[&pair].is_empty());
+
+    let result = orderbook.match_accepted(accepted).unwrap();
+    assert!(result.trades.is_empty());
+    assert_eq!(result.modified_orders[&order.id].status, OrderStatus::OPEN);
+    assert_eq!(result.balances[&(alice.clone(), pair.quote)], reserved);
+    assert_eq!(orderbook.bid_books[&pair].len(), 1);
+
+    // Orders failing the reservation are not acked
+    let order = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(500),
+        2,
+    );
+    let err = orderbook.accept_order(order, 2).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InsufficientBalance)
+    ));
+}
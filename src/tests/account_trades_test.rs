@@ -0,0 +1,117 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use crate::{maker_taker_volume, trades_at_price, trades_for_account, OrderExecutionResult};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_trades_for_account() {
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    for (main, timestamp) in [(&alice, 1), (&carol, 2)] {
+        let maker = limit_order(
+            main,
+            OrderSide::Ask,
+            Decimal::from(1),
+            Decimal::from(2),
+            timestamp,
+        );
+        orderbook.process_order(maker, timestamp as u64).unwrap();
+    }
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(4), 3);
+    let result = orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+
+    let alice_trades = trades_for_account(&result, &alice);
+    assert_eq!(alice_trades.len(), 1);
+    assert!(alice_trades[0].1);
+    assert_eq!(alice_trades[0].0.maker.main_account, alice);
+
+    let bob_trades = trades_for_account(&result, &bob);
+    assert_eq!(bob_trades.len(), 2);
+    assert!(bob_trades.iter().all(|(_, is_maker)| !is_maker));
+
+    assert!(trades_for_account(&result, &account(4)).is_empty());
+}
+
+#[test]
+pub fn test_trades_for_account_returns_both_roles_of_self_trade() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    let taker = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(1),
+        Decimal::from(2),
+        2,
+    );
+    let result = orderbook.process_order(taker, 2).unwrap();
+
+    let roles: Vec<bool> = trades_for_account(&result, &alice)
+        .into_iter()
+        .map(|(_, is_maker)| is_maker)
+        .collect();
+    assert_eq!(roles, vec![true, false]);
+}
+
+#[test]
+pub fn test_trades_at_price() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for (timestamp, price) in [
+        (1, Decimal::new(15, 1)),
+        (2, Decimal::from(2)),
+        (3, Decimal::new(150, 2)),
+    ] {
+        let maker = limit_order(&alice, OrderSide::Ask, price, Decimal::from(1), timestamp);
+        orderbook.process_order(maker, timestamp as u64).unwrap();
+    }
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(2), Decimal::from(3), 4);
+    let result = orderbook.process_order(taker, 4).unwrap();
+    assert_eq!(result.trades.len(), 3);
+
+    // Both makers at 1.5 match, whatever the scale of the price
+    let trades = trades_at_price(&result, Decimal::new(15, 1));
+    assert_eq!(trades.len(), 2);
+    assert!(trades
+        .iter()
+        .all(|trade| trade.price == Decimal::new(15, 1)));
+    assert_eq!(trades_at_price(&result, Decimal::from(2)).len(), 1);
+    assert!(trades_at_price(&result, Decimal::new(1500000001, 9)).is_empty());
+}
+
+#[test]
+pub fn test_maker_taker_volume() {
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    for (main, timestamp, price) in [(&alice, 1, 2), (&carol, 2, 3)] {
+        let maker = limit_order(
+            main,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(2),
+            timestamp,
+        );
+        orderbook.process_order(maker, timestamp as u64).unwrap();
+    }
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(3), Decimal::from(3), 3);
+    let result = orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+
+    // 2 at 2 from alice and 1 at 3 from carol
+    assert_eq!(
+        maker_taker_volume(&result),
+        (Decimal::from(7), Decimal::from(7))
+    );
+    assert_eq!(
+        maker_taker_volume(&OrderExecutionResult::default()),
+        (Decimal::ZERO, Decimal::ZERO)
+    );
+}
@@ -0,0 +1,226 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::Orderbook;
+use orderbook_primitives::types::{Order, OrderSide, OrderStatus, OrderType};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_auction_only_orders_rest_until_the_auction() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::ZERO, Decimal::ZERO);
+    }
+    orderbook.pair_settings_mut(pair).auction_only = true;
+    let first_bid = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(12),
+        Decimal::from(2),
+        1,
+    );
+    let orders = [
+        (&alice, OrderSide::Bid, 12, 2, 1),
+        (&bob, OrderSide::Ask, 10, 1, 2),
+        (&bob, OrderSide::Ask, 11, 2, 3),
+        (&alice, OrderSide::Bid, 9, 1, 4),
+    ];
+    for (main, side, price, qty, ts) in orders {
+        let order = limit_order(main, side, Decimal::from(price), Decimal::from(qty), ts);
+        let result = orderbook
+            .process_order_at(order, ts as u64, ts as u64)
+            .unwrap();
+        assert!(result.trades.is_empty());
+    }
+    // The books are crossed but nothing traded
+    assert_eq!(
+        orderbook.bbo(&pair),
+        (Some(Decimal::from(12)), Some(Decimal::from(10)))
+    );
+    let mut market = limit_order(&bob, OrderSide::Ask, Decimal::ZERO, Decimal::ONE, 5);
+    market.order_type = OrderType::MARKET;
+    let err = orderbook.process_order(market, 5).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::AuctionOnly)
+    ));
+
+    // 11 and 12 both execute 2 leaving 1 unmatched, the lower price wins
+    assert_eq!(
+        orderbook.auction_clearing_price(&pair),
+        Some(Decimal::from(11))
+    );
+    let result = orderbook.run_auction(pair, 6, 6).unwrap();
+    let fills: Vec<_> = result
+        .trades
+        .iter()
+        .map(|trade| (trade.price, trade.amount))
+        .collect();
+    assert_eq!(
+        fills,
+        vec![
+            (Decimal::from(11), Decimal::ONE),
+            (Decimal::from(11), Decimal::ONE)
+        ]
+    );
+    assert_eq!(
+        result.modified_orders[&first_bid.id].status,
+        OrderStatus::CLOSED
+    );
+    assert_eq!(
+        orderbook.bbo(&pair),
+        (Some(Decimal::from(9)), Some(Decimal::from(11)))
+    );
+    assert_eq!(orderbook.auction_clearing_price(&pair), None);
+    // Alice bought 2 at 11 with 24 reserved for them, the bid at 9 stays reserved
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(969), Decimal::from(9))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(1002), Decimal::ZERO)
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::from(997), Decimal::ONE)
+    );
+}
+
+fn self_crossing_auction(cancel_on_self_trade: bool) -> (Orderbook, Order) {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::ZERO, Decimal::ZERO);
+    }
+    let settings = orderbook.pair_settings_mut(pair);
+    settings.auction_only = true;
+    settings.cancel_on_self_trade = cancel_on_self_trade;
+    let bid = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(12),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order_at(bid.clone(), 1, 1).unwrap();
+    let orders = [(&alice, 10, 1, 2), (&bob, 11, 2, 3)];
+    for (main, price, qty, ts) in orders {
+        let ask = limit_order(
+            main,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook
+            .process_order_at(ask, ts as u64, ts as u64)
+            .unwrap();
+    }
+    (orderbook, bid)
+}
+
+#[test]
+pub fn test_auction_cancels_the_earlier_self_crossing_order() {
+    let pair = test_pair();
+    let (mut orderbook, bid) = self_crossing_auction(true);
+    let result = orderbook.run_auction(pair, 4, 4).unwrap();
+    // Alice's bid rested first and is cancelled instead of buying from her own ask
+    assert!(result.trades.is_empty());
+    assert_eq!(
+        result.modified_orders[&bid.id].status,
+        OrderStatus::CANCELLED
+    );
+    assert_eq!(orderbook.best_price(&pair, OrderSide::Bid), None);
+    assert_eq!(
+        orderbook.balances[&(account(1), pair.quote)],
+        (Decimal::from(1000), Decimal::ZERO)
+    );
+    assert_eq!(orderbook.open_orders_of(&account(1)), 1);
+}
+
+#[test]
+pub fn test_auction_waits_for_prepared_executions() {
+    let pair = test_pair();
+    let (mut orderbook, _) = self_crossing_auction(false);
+    let pending = limit_order(
+        &account(2),
+        OrderSide::Bid,
+        Decimal::from(5),
+        Decimal::ONE,
+        4,
+    );
+    let (prepared, _) = orderbook.prepare_order(pending, 4).unwrap();
+    let err = orderbook.run_auction(pair, 5, 5).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::ExecutionPending)
+    ));
+    orderbook.rollback(prepared);
+    let result = orderbook.run_auction(pair, 5, 5).unwrap();
+    assert!(!result.trades.is_empty());
+}
+
+#[test]
+pub fn test_auction_allows_self_crossing_without_self_trade_prevention() {
+    let pair = test_pair();
+    let (mut orderbook, bid) = self_crossing_auction(false);
+    let result = orderbook.run_auction(pair, 4, 4).unwrap();
+    let sellers: Vec<_> = result
+        .trades
+        .iter()
+        .map(|trade| {
+            let ask = if trade.maker.side == OrderSide::Ask {
+                &trade.maker
+            } else {
+                &trade.taker
+            };
+            (ask.main_account.clone(), trade.price)
+        })
+        .collect();
+    assert_eq!(
+        sellers,
+        vec![
+            (account(1), Decimal::from(11)),
+            (account(2), Decimal::from(11))
+        ]
+    );
+    assert_eq!(result.modified_orders[&bid.id].status, OrderStatus::CLOSED);
+}
+
+#[test]
+pub fn test_auction_posts_the_next_rung_of_filled_ladders() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::ZERO, Decimal::ZERO);
+    }
+    orderbook.pair_settings_mut(pair).auction_only = true;
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, 1);
+    orderbook.process_order_at(bid, 1, 1).unwrap();
+    // The first rung rests after the bid and takes in the auction
+    orderbook.advance_clock(2);
+    let base_order = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 2);
+    orderbook
+        .register_ladder(base_order, 2, Decimal::ONE)
+        .unwrap();
+
+    let result = orderbook.run_auction(pair, 3, 3).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    // The second rung is posted by the auction itself and rests until the next one
+    let rung = result
+        .modified_orders
+        .values()
+        .find(|order| order.main_account == alice && order.price == Decimal::from(11))
+        .unwrap();
+    assert_eq!(rung.status, OrderStatus::OPEN);
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(11))
+    );
+    assert!(orderbook.ladders.is_empty());
+    assert!(orderbook.triggered_rungs.is_empty());
+}
@@ -0,0 +1,114 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{Order, OrderSide};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_verify_trade() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(5),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::from(3), 2);
+    orderbook.process_order(maker, 1).unwrap();
+    let result = orderbook.process_order(taker, 2).unwrap();
+    let trade = result.trades[0].clone();
+    assert_eq!(orderbook.verify_trade(&trade), Ok(()));
+
+    let mut tampered = trade.clone();
+    tampered.price = Decimal::from(11);
+    assert!(orderbook.verify_trade(&tampered).is_err());
+
+    let mut tampered = trade.clone();
+    tampered.amount = Decimal::from(4);
+    assert!(orderbook.verify_trade(&tampered).is_err());
+
+    let mut tampered = trade;
+    tampered.taker.price = Decimal::from(9);
+    assert!(orderbook.verify_trade(&tampered).is_err());
+}
+
+#[test]
+pub fn test_state_root_is_deterministic() {
+    let (alice, bob) = (account(1), account(2));
+    let history = [
+        limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(10),
+            Decimal::from(5),
+            1,
+        ),
+        limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(11),
+            Decimal::from(5),
+            2,
+        ),
+        limit_order(&bob, OrderSide::Bid, Decimal::from(9), Decimal::from(2), 3),
+        limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(7), 4),
+    ];
+    let replay = |orders: &[Order]| {
+        let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+        for (stid, order) in orders.iter().enumerate() {
+            orderbook.process_order(order.clone(), stid as u64).unwrap();
+        }
+        orderbook.state_root()
+    };
+    let root = replay(&history);
+    assert_eq!(root, replay(&history));
+    assert_ne!(root, replay(&history[..3]));
+}
+
+#[test]
+pub fn test_dump_state_of_pair() {
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    let pair = test_pair();
+    let bids = [
+        limit_order(
+            &alice,
+            OrderSide::Bid,
+            Decimal::from(8),
+            Decimal::from(1),
+            1,
+        ),
+        limit_order(&bob, OrderSide::Bid, Decimal::from(9), Decimal::from(2), 2),
+    ];
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(3),
+        3,
+    );
+    for order in bids.iter().chain([&ask]) {
+        orderbook
+            .process_order(order.clone(), order.timestamp as u64)
+            .unwrap();
+    }
+
+    let dump = orderbook.dump_state(&pair);
+    assert_eq!(dump.pair, pair);
+    let bid_ids: Vec<_> = dump.bids.iter().map(|order| order.id).collect();
+    assert_eq!(bid_ids, vec![bids[1].id, bids[0].id]);
+    assert_eq!(dump.asks.len(), 1);
+    assert_eq!(dump.asks[0].id, ask.id);
+    assert_eq!(dump.price_levels.len(), 3);
+    assert_eq!(
+        dump.price_levels[&(OrderSide::Bid, Decimal::from(9))],
+        Decimal::from(2)
+    );
+    // Only accounts with resting orders are included, carol has none
+    assert_eq!(dump.balances.len(), 4);
+    assert_eq!(
+        dump.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(982), Decimal::from(18))
+    );
+    assert!(!dump.balances.contains_key(&(carol, pair.base)));
+}
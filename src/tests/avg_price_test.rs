@@ -0,0 +1,89 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::ReservationReason;
+use orderbook_primitives::types::{OrderSide, OrderType};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_avg_filled_price_is_rounded_to_pair_precision() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).avg_price_precision = Some(2);
+    let cheap = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(1),
+        1,
+    );
+    let expensive = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(2),
+        2,
+    );
+    orderbook.process_order(cheap, 1).unwrap();
+    orderbook.process_order(expensive, 2).unwrap();
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(2), Decimal::from(3), 3);
+    let result = orderbook.process_order(taker.clone(), 3).unwrap();
+    // (1 * 1 + 2 * 2) / 3 = 1.666..
+    assert_eq!(
+        result.modified_orders[&taker.id].avg_filled_price,
+        Decimal::new(167, 2)
+    );
+    // Refunds are settled at the trade prices, bob pays exactly 5 quote
+    assert_eq!(
+        orderbook.balances[&(bob, pair.quote)],
+        (Decimal::from(995), Decimal::from(0))
+    );
+}
+
+#[test]
+pub fn test_quote_budget_refund_uses_exact_fills() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&bob, Decimal::from(0), Decimal::from(0));
+    orderbook.pair_settings_mut(pair).avg_price_precision = Some(2);
+    orderbook.set_reservation_logging(true);
+    for (ts, price, qty) in [(1, 1, 1), (2, 2, 2)] {
+        let ask = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+
+    // Spends 5 of a budget of 10 at an average of 1.666..
+    let mut taker = limit_order(&bob, OrderSide::Bid, Decimal::from(0), Decimal::from(0), 3);
+    taker.order_type = OrderType::MARKET;
+    taker.quote_order_qty = Decimal::from(10);
+    let result = orderbook.process_order(taker.clone(), 3).unwrap();
+    assert_eq!(
+        result.modified_orders[&taker.id].avg_filled_price,
+        Decimal::new(167, 2)
+    );
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(995), Decimal::from(0))
+    );
+    // The unspent budget is refunded at once, nothing is left to release as a residual
+    let refunds: Vec<_> = orderbook
+        .reservation_log()
+        .iter()
+        .filter(|event| event.account == bob && event.delta < Decimal::ZERO)
+        .map(|event| (event.reason, event.delta))
+        .collect();
+    assert_eq!(
+        refunds.last(),
+        Some(&(ReservationReason::MarketRefund, Decimal::from(-5)))
+    );
+    assert!(refunds
+        .iter()
+        .all(|(reason, _)| *reason != ReservationReason::RoundingResidual));
+}
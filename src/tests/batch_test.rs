@@ -0,0 +1,62 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::BatchMode;
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+use sp_core::H256;
+
+#[test]
+pub fn test_best_effort_batch_skips_failing_order() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let batch = || {
+        vec![
+            limit_order(
+                &alice,
+                OrderSide::Ask,
+                Decimal::from(10),
+                Decimal::from(2),
+                1,
+            ),
+            // Needs 20000 quote with only 1000 free
+            limit_order(
+                &bob,
+                OrderSide::Bid,
+                Decimal::from(10),
+                Decimal::from(2000),
+                2,
+            ),
+            limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(1), 3),
+        ]
+    };
+
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let (result, errors) = orderbook.process_orders(batch(), 1, BatchMode::BestEffort);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, 1);
+    assert!(matches!(
+        errors[0].1.downcast_ref::<Error>(),
+        Some(Error::InsufficientBalance)
+    ));
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(
+        orderbook.best_level(&pair, OrderSide::Ask),
+        Some((Decimal::from(10), Decimal::from(1)))
+    );
+    // The failing order was rolled back, nothing of it is left reserved or frozen
+    assert!(!orderbook
+        .order_reservations
+        .contains_key(&H256::repeat_byte(2)));
+    assert!(orderbook.prepared.is_empty());
+
+    // Failing fast leaves the order after the failure unprocessed
+    let mut orderbook = funded_orderbook(&[alice, bob]);
+    let (result, errors) = orderbook.process_orders(batch(), 1, BatchMode::FailFast);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, 1);
+    assert!(result.trades.is_empty());
+    assert_eq!(
+        orderbook.best_level(&pair, OrderSide::Ask),
+        Some((Decimal::from(10), Decimal::from(2)))
+    );
+}
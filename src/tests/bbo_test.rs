@@ -0,0 +1,203 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_would_improve_bbo() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    let price = |p: i64| Decimal::from(p);
+
+    // Any price improves an empty side
+    assert!(orderbook.would_improve_bbo(&pair, OrderSide::Bid, price(1)));
+    assert!(orderbook.would_improve_bbo(&pair, OrderSide::Ask, price(100)));
+
+    let bid = limit_order(&alice, OrderSide::Bid, price(9), Decimal::from(1), 1);
+    let ask = limit_order(&alice, OrderSide::Ask, price(11), Decimal::from(1), 2);
+    orderbook.process_order(bid, 1).unwrap();
+    orderbook.process_order(ask, 2).unwrap();
+    assert_eq!(orderbook.bbo(&pair), (Some(price(9)), Some(price(11))));
+
+    assert!(orderbook.would_improve_bbo(&pair, OrderSide::Bid, price(10)));
+    assert!(!orderbook.would_improve_bbo(&pair, OrderSide::Bid, price(9)));
+    assert!(!orderbook.would_improve_bbo(&pair, OrderSide::Bid, price(8)));
+
+    assert!(orderbook.would_improve_bbo(&pair, OrderSide::Ask, price(10)));
+    assert!(!orderbook.would_improve_bbo(&pair, OrderSide::Ask, price(11)));
+    assert!(!orderbook.would_improve_bbo(&pair, OrderSide::Ask, price(12)));
+}
+
+#[test]
+pub fn test_microprice() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    assert_eq!(orderbook.microprice(&pair), None);
+
+    let bid = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(9),
+        Decimal::from(1),
+        1,
+    );
+    orderbook.process_order(bid, 1).unwrap();
+    assert_eq!(orderbook.microprice(&pair), None);
+
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(11),
+        Decimal::from(3),
+        2,
+    );
+    orderbook.process_order(ask, 2).unwrap();
+    // (9 * 3 + 11 * 1) / 4, skewed toward the thinner bid side
+    assert_eq!(orderbook.microprice(&pair), Some(Decimal::new(95, 1)));
+}
+
+#[test]
+pub fn test_breakeven_spread() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(2, 3));
+    assert_eq!(orderbook.breakeven_spread(&alice, &pair), Decimal::ZERO);
+    for (ts, side, price) in [(1, OrderSide::Bid, 99), (2, OrderSide::Ask, 101)] {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(1), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    assert_eq!(orderbook.mid_price(&pair), Some(Decimal::from(100)));
+
+    // (1 - 0.001)^2 = 0.998001, so s = 200 * 0.001999 / 1.998001
+    let expected = Decimal::from(200) * Decimal::new(1999, 6) / Decimal::new(1998001, 6);
+    assert_eq!(orderbook.breakeven_spread(&alice, &pair), expected);
+    // Buying at mid - s/2 and selling at mid + s/2 nets back the spent quote
+    let half = expected / Decimal::TWO;
+    let base = Decimal::ONE / (Decimal::from(100) - half) * Decimal::new(999, 3);
+    let quote = base * (Decimal::from(100) + half) * Decimal::new(999, 3);
+    assert!((quote - Decimal::ONE).abs() < Decimal::new(1, 12));
+}
+
+#[test]
+pub fn test_spread_capture() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let pair = test_pair();
+    orderbook.update_fee_structure(&bob, Decimal::new(1, 3), Decimal::new(2, 3));
+    assert_eq!(orderbook.spread_capture(&bob, &pair), None);
+    for (ts, side, price) in [(1, OrderSide::Bid, 99), (2, OrderSide::Ask, 101)] {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(1), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    assert_eq!(orderbook.spread(&pair), Some(Decimal::from(2)));
+
+    // Buying at 99 and selling at 101 pays 0.1% on both legs: 2 - 0.099 - 0.101
+    let expected = Decimal::from(2) - Decimal::new(99, 3) - Decimal::new(101, 3);
+    assert_eq!(orderbook.spread_capture(&bob, &pair), Some(expected));
+    // Fees above the spread make the capture negative
+    orderbook.update_fee_structure(&bob, Decimal::new(2, 2), Decimal::new(2, 2));
+    assert_eq!(
+        orderbook.spread_capture(&bob, &pair),
+        Some(Decimal::from(-2))
+    );
+}
+
+#[test]
+pub fn test_detect_arbitrage_against_external_prices() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let orders = [
+        (OrderSide::Bid, 9, 1, 1),
+        (OrderSide::Bid, 8, 2, 2),
+        (OrderSide::Ask, 11, 1, 3),
+        (OrderSide::Ask, 12, 3, 4),
+    ];
+    for (side, price, qty, ts) in orders {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(qty), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+
+    // Internal asks below the external bid can be bought and sold externally
+    let arb = orderbook
+        .detect_arbitrage(&pair, Decimal::new(125, 1), Decimal::from(13))
+        .unwrap();
+    assert_eq!(arb.side, OrderSide::Ask);
+    assert_eq!(arb.internal_price, Decimal::from(11));
+    assert_eq!(arb.qty, Decimal::from(4));
+    // Internal bids above the external ask can be sold to
+    let arb = orderbook
+        .detect_arbitrage(&pair, Decimal::from(7), Decimal::new(85, 1))
+        .unwrap();
+    assert_eq!(arb.side, OrderSide::Bid);
+    assert_eq!(arb.internal_price, Decimal::from(9));
+    assert_eq!(arb.qty, Decimal::from(1));
+    // Inside and locked external prices are no opportunity
+    assert_eq!(
+        orderbook.detect_arbitrage(&pair, Decimal::from(10), Decimal::new(105, 1)),
+        None
+    );
+    assert_eq!(
+        orderbook.detect_arbitrage(&pair, Decimal::from(11), Decimal::from(12)),
+        None
+    );
+}
+
+#[test]
+pub fn test_marginal_price_is_the_opposite_touch() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    assert_eq!(orderbook.marginal_price(&pair, OrderSide::Bid), None);
+    let orders = [
+        (OrderSide::Bid, 9, 1),
+        (OrderSide::Bid, 8, 2),
+        (OrderSide::Ask, 11, 3),
+        (OrderSide::Ask, 12, 4),
+    ];
+    for (side, price, ts) in orders {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(1), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    assert_eq!(
+        orderbook.marginal_price(&pair, OrderSide::Bid),
+        Some(Decimal::from(11))
+    );
+    assert_eq!(
+        orderbook.marginal_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(9))
+    );
+}
+
+#[test]
+pub fn test_weighted_mid() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    assert_eq!(orderbook.weighted_mid(&pair, Decimal::from(4)), None);
+
+    let levels = [
+        (1, OrderSide::Bid, 9, 1),
+        (2, OrderSide::Bid, 7, 10),
+        (3, OrderSide::Ask, 11, 10),
+    ];
+    for (ts, side, price, qty) in levels {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(qty), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    assert_eq!(orderbook.mid_price(&pair), Some(Decimal::from(10)));
+    // Buying 4 costs 11, selling 4 gets (9 * 1 + 7 * 3) / 4 = 7.5, so the thin bid pulls the
+    // mid down
+    assert_eq!(
+        orderbook.weighted_mid(&pair, Decimal::from(4)),
+        Some(Decimal::new(925, 2))
+    );
+    // Within the best levels it equals the simple mid
+    assert_eq!(
+        orderbook.weighted_mid(&pair, Decimal::from(1)),
+        Some(Decimal::from(10))
+    );
+    assert_eq!(orderbook.weighted_mid(&pair, Decimal::from(12)), None);
+}
@@ -0,0 +1,78 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderType};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_orders_outside_the_bounds_are_rejected() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let settings = orderbook.pair_settings_mut(pair);
+    settings.min_price = Some(Decimal::new(1, 4));
+    settings.max_price = Some(Decimal::from(1_000_000));
+    settings.min_qty = Some(Decimal::new(1, 3));
+    settings.max_qty = Some(Decimal::from(1000));
+
+    // Orders at the bounds are accepted
+    let at_bounds = [
+        (OrderSide::Bid, Decimal::new(1, 4), Decimal::ONE),
+        (OrderSide::Ask, Decimal::from(1_000_000), Decimal::new(1, 3)),
+        (OrderSide::Ask, Decimal::ONE, Decimal::from(999)),
+    ];
+    for (ts, (side, price, qty)) in at_bounds.into_iter().enumerate() {
+        let order = limit_order(&alice, side, price, qty, ts as i64 + 1);
+        orderbook.process_order(order, ts as u64 + 1).unwrap();
+    }
+
+    let beyond = [
+        (OrderSide::Bid, Decimal::new(9, 5), Decimal::ONE),
+        (OrderSide::Ask, Decimal::from(1_000_001), Decimal::ONE),
+        (OrderSide::Bid, Decimal::ONE, Decimal::new(9, 4)),
+        (OrderSide::Bid, Decimal::new(1, 4), Decimal::new(10001, 1)),
+    ];
+    let errors: Vec<_> = beyond
+        .into_iter()
+        .enumerate()
+        .map(|(ts, (side, price, qty))| {
+            let order = limit_order(&alice, side, price, qty, ts as i64 + 4);
+            orderbook.process_order(order, ts as u64 + 4).unwrap_err()
+        })
+        .collect();
+    assert!(matches!(
+        errors[0].downcast_ref::<Error>(),
+        Some(Error::PriceOutOfBounds)
+    ));
+    assert!(matches!(
+        errors[1].downcast_ref::<Error>(),
+        Some(Error::PriceOutOfBounds)
+    ));
+    assert!(matches!(
+        errors[2].downcast_ref::<Error>(),
+        Some(Error::QtyOutOfBounds)
+    ));
+    assert!(matches!(
+        errors[3].downcast_ref::<Error>(),
+        Some(Error::QtyOutOfBounds)
+    ));
+
+    // Quote budget market buys are bounded by the volume range of the bounds
+    let market = |quote_order_qty: Decimal, ts: i64| {
+        let mut market = limit_order(&alice, OrderSide::Bid, Decimal::ZERO, Decimal::ZERO, ts);
+        market.order_type = OrderType::MARKET;
+        market.quote_order_qty = quote_order_qty;
+        market
+    };
+    assert!(orderbook
+        .process_order(market(Decimal::from(5), 8), 8)
+        .is_ok());
+    for (ts, quote) in [(9, Decimal::new(9, 8)), (10, Decimal::from(1_000_000_001))] {
+        let err = orderbook
+            .process_order(market(quote, ts), ts as u64)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::QuoteOutOfBounds)
+        ));
+    }
+}
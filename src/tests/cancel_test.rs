@@ -0,0 +1,268 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_cancel_fee_is_charged_on_fresh_orders_only() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    orderbook.pair_settings_mut(pair).cancel_fee = Decimal::from(1);
+    orderbook.pair_settings_mut(pair).cancel_fee_exempt_age = Some(60_000);
+    let pot = orderbook.fees_collector.pot.clone();
+
+    // Cancelled a second after it was placed, so the fee is charged
+    let fresh = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(1),
+        1_000,
+    );
+    orderbook.process_order_at(fresh.clone(), 1, 1_000).unwrap();
+    let result = orderbook
+        .cancel_order(pair, OrderSide::Ask, &fresh.id, 2_000, 2)
+        .unwrap();
+    assert_eq!(
+        result.modified_orders[&fresh.id].status,
+        OrderStatus::CANCELLED
+    );
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(1000), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(999), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(pot.clone(), pair.quote)].0,
+        Decimal::from(1)
+    );
+
+    // Cancelled after resting longer than the exempt age
+    let aged = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(5),
+        Decimal::from(1),
+        2_000,
+    );
+    orderbook.process_order_at(aged.clone(), 3, 2_000).unwrap();
+    orderbook
+        .cancel_order(pair, OrderSide::Bid, &aged.id, 70_000, 4)
+        .unwrap();
+    assert_eq!(
+        orderbook.balances[&(alice, pair.quote)],
+        (Decimal::from(999), Decimal::from(0))
+    );
+    assert_eq!(orderbook.balances[&(pot, pair.quote)].0, Decimal::from(1));
+    assert!(orderbook.bid_books[&pair].is_empty());
+    assert!(orderbook.ask_books[&pair].is_empty());
+
+    // Orders which are not resting cannot be cancelled
+    assert!(orderbook
+        .cancel_order(pair, OrderSide::Bid, &aged.id, 70_000, 5)
+        .is_err());
+}
+
+#[test]
+pub fn test_cancel_before_min_resting_time_is_rejected() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    orderbook.pair_settings_mut(pair).min_resting_time = Some(500);
+    let order = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(1),
+        1_000,
+    );
+    orderbook.process_order_at(order.clone(), 1, 1_000).unwrap();
+
+    let err = orderbook
+        .cancel_order(pair, OrderSide::Ask, &order.id, 1_499, 2)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::MinRestingTimeNotMet)
+    ));
+    // The order keeps resting with its reservation
+    assert_eq!(orderbook.ask_books[&pair].len(), 1);
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(999), Decimal::from(1))
+    );
+
+    orderbook
+        .cancel_order(pair, OrderSide::Ask, &order.id, 1_500, 3)
+        .unwrap();
+    assert!(orderbook.ask_books[&pair].is_empty());
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(1000), Decimal::from(0))
+    );
+}
+
+#[test]
+pub fn test_cancel_at_price_cancels_the_accounts_level() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let orders = [
+        (&alice, 10, 1),
+        (&alice, 10, 2),
+        (&alice, 10, 3),
+        (&alice, 11, 4),
+        (&bob, 10, 5),
+    ];
+    for (main, price, ts) in orders {
+        let order = limit_order(
+            main,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+
+    let result = orderbook
+        .cancel_at_price(pair, OrderSide::Ask, Decimal::from(10), &alice, 6)
+        .unwrap();
+    assert_eq!(result.modified_orders.len(), 3);
+    assert!(result
+        .modified_orders
+        .values()
+        .all(|order| order.status == OrderStatus::CANCELLED && order.main_account == alice));
+    // Only the order at 11 is still reserved
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(999), Decimal::from(1))
+    );
+    assert_eq!(
+        orderbook.pricelevels[&(pair, OrderSide::Ask, Decimal::from(10))],
+        Decimal::from(1)
+    );
+    assert_eq!(orderbook.ask_books[&pair].len(), 2);
+}
+
+#[test]
+pub fn test_reset_pair_cancels_resting_orders() {
+    let pair = test_pair();
+    let alice = account(1);
+    let bob = account(2);
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let bid = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(9),
+        Decimal::from(2),
+        1,
+    );
+    let ask = limit_order(&bob, OrderSide::Ask, Decimal::from(11), Decimal::from(3), 2);
+    orderbook.process_order(bid.clone(), 1).unwrap();
+    orderbook
+        .register_ladder(ask.clone(), 2, Decimal::from(1))
+        .unwrap();
+
+    // The pair can not be reset while one of its executions is prepared
+    let pending = limit_order(&alice, OrderSide::Bid, Decimal::from(8), Decimal::ONE, 4);
+    let (prepared, _) = orderbook.prepare_order(pending, 2).unwrap();
+    let err = orderbook.reset_pair(pair, 3).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::ExecutionPending)
+    ));
+    orderbook.rollback(prepared);
+
+    let result = orderbook.reset_pair(pair, 3).unwrap();
+    assert_eq!(result.stid, 3);
+    // The ladder of the cancelled rung posts no further rungs
+    assert!(orderbook.ladders.is_empty());
+    assert_eq!(
+        result.modified_orders[&bid.id].status,
+        OrderStatus::CANCELLED
+    );
+    assert_eq!(
+        result.modified_orders[&ask.id].status,
+        OrderStatus::CANCELLED
+    );
+    assert!(orderbook.bid_books[&pair].is_empty());
+    assert!(orderbook.ask_books[&pair].is_empty());
+    assert!(orderbook.book_levels(&pair, OrderSide::Bid).is_empty());
+    assert!(orderbook.book_levels(&pair, OrderSide::Ask).is_empty());
+    assert_eq!(orderbook.open_orders_of(&alice), 0);
+    for main in [&alice, &bob] {
+        for asset in [pair.base, pair.quote] {
+            assert_eq!(
+                orderbook.balances[&(main.clone(), asset)],
+                (Decimal::from(1000), Decimal::from(0))
+            );
+        }
+    }
+
+    // The pair is still registered and accepts orders
+    let again = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(9),
+        Decimal::from(2),
+        3,
+    );
+    orderbook.process_order(again, 3).unwrap();
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Bid),
+        Some(Decimal::from(9))
+    );
+}
+
+#[test]
+pub fn test_cancel_after_partial_fill_keeps_paid_fees() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(1, 3));
+    orderbook.update_fee_structure(&bob, Decimal::new(2, 3), Decimal::new(2, 3));
+    let pot = orderbook.fees_collector.pot.clone();
+
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(4),
+        1,
+    );
+    orderbook.process_order(ask.clone(), 1).unwrap();
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(1), 2);
+    let result = orderbook.process_order(bid, 2).unwrap();
+    // The maker paid 0.01 quote on its filled part
+    assert_eq!(result.modified_orders[&ask.id].fee, Decimal::new(1, 2));
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(996), Decimal::from(3))
+    );
+
+    let result = orderbook
+        .cancel_order(pair, OrderSide::Ask, &ask.id, 2, 3)
+        .unwrap();
+    let cancelled = &result.modified_orders[&ask.id];
+    assert_eq!(cancelled.status, OrderStatus::CANCELLED);
+    assert_eq!(cancelled.fee, Decimal::new(1, 2));
+    // Only the unfilled 3 base are released, the fee is not refunded
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(999), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.quote)],
+        (Decimal::new(100999, 2), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(pot.clone(), pair.quote)].0,
+        Decimal::new(1, 2)
+    );
+    assert_eq!(orderbook.balances[&(pot, pair.base)].0, Decimal::new(2, 3));
+}
@@ -0,0 +1,105 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::{Candle, Orderbook};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+fn trade_at(
+    orderbook: &mut Orderbook,
+    now: u64,
+    asks: &[(i64, i64)],
+    bid: (i64, i64),
+    id: &mut i64,
+) {
+    for (price, qty) in asks {
+        *id += 1;
+        let ask = limit_order(
+            &account(1),
+            OrderSide::Ask,
+            Decimal::from(*price),
+            Decimal::from(*qty),
+            *id,
+        );
+        orderbook.process_order_at(ask, *id as u64, now).unwrap();
+    }
+    *id += 1;
+    let bid = limit_order(
+        &account(2),
+        OrderSide::Bid,
+        Decimal::from(bid.0),
+        Decimal::from(bid.1),
+        *id,
+    );
+    let result = orderbook.process_order_at(bid, *id as u64, now).unwrap();
+    assert!(!result.trades.is_empty());
+}
+
+#[test]
+pub fn test_candles_across_two_buckets() {
+    let pair = test_pair();
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    orderbook.set_candle_interval(60_000);
+    let mut id = 0;
+    // First minute trades at 10, 12 and 11
+    trade_at(&mut orderbook, 1_000, &[(10, 2), (12, 1)], (12, 3), &mut id);
+    trade_at(&mut orderbook, 30_000, &[(11, 1)], (11, 1), &mut id);
+    // Second minute trades at 9 and 13
+    trade_at(&mut orderbook, 61_000, &[(9, 1), (13, 1)], (13, 2), &mut id);
+
+    let first = Candle {
+        start: 0,
+        open: Decimal::from(10),
+        high: Decimal::from(12),
+        low: Decimal::from(10),
+        close: Decimal::from(11),
+        volume: Decimal::from(4),
+    };
+    let second = Candle {
+        start: 60_000,
+        open: Decimal::from(9),
+        high: Decimal::from(13),
+        low: Decimal::from(9),
+        close: Decimal::from(13),
+        volume: Decimal::from(2),
+    };
+    assert_eq!(orderbook.candles(pair, 60_000, 10), vec![first, second]);
+    assert_eq!(orderbook.candles(pair, 60_000, 1), vec![second]);
+
+    // Both minutes aggregate into one two minute candle
+    let aggregated = Candle {
+        start: 0,
+        open: Decimal::from(10),
+        high: Decimal::from(13),
+        low: Decimal::from(9),
+        close: Decimal::from(13),
+        volume: Decimal::from(6),
+    };
+    assert_eq!(orderbook.candles(pair, 120_000, 10), vec![aggregated]);
+}
+
+#[test]
+pub fn test_oldest_candles_are_pruned() {
+    let pair = test_pair();
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    orderbook.set_candle_interval(60_000);
+    orderbook.set_candle_retention(2);
+    let mut id = 0;
+    for (minute, price) in [(0, 10), (1, 11), (2, 12)] {
+        trade_at(
+            &mut orderbook,
+            minute * 60_000,
+            &[(price, 1)],
+            (price, 1),
+            &mut id,
+        );
+    }
+    let starts: Vec<u64> = orderbook
+        .candles(pair, 60_000, 10)
+        .iter()
+        .map(|candle| candle.start)
+        .collect();
+    assert_eq!(starts, vec![60_000, 120_000]);
+
+    orderbook.set_candle_retention(1);
+    assert_eq!(orderbook.candles(pair, 60_000, 10).len(), 1);
+    assert_eq!(orderbook.candles(pair, 60_000, 10)[0].start, 120_000);
+}
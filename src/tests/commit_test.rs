@@ -0,0 +1,137 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::Orderbook;
+use orderbook_primitives::types::{Order, OrderSide};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+fn resting_orders(orderbook: &Orderbook) -> (Vec<Order>, Vec<Order>) {
+    let pair = test_pair();
+    (
+        orderbook.bid_books[&pair].clone().into_sorted_vec(),
+        orderbook.ask_books[&pair].clone().into_sorted_vec(),
+    )
+}
+
+#[test]
+pub fn test_commit_applies_prepared_execution() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(4),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(2), 2);
+    let (prepared, result) = orderbook.prepare_order(taker, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    // The pair and the traded accounts are frozen until committed
+    let other = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(1),
+        3,
+    );
+    let err = orderbook.process_order(other.clone(), 3).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::ExecutionPending)
+    ));
+
+    orderbook.commit(prepared);
+    for (key, balance) in &result.balances {
+        assert_eq!(orderbook.balances[key], *balance);
+    }
+    assert_eq!(
+        orderbook.balances[&(bob, pair.quote)],
+        (Decimal::from(998), Decimal::from(0))
+    );
+    orderbook.process_order(other, 3).unwrap();
+}
+
+#[test]
+pub fn test_rollback_reverts_prepared_execution() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_reservation_logging(true);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(4),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    let balances = orderbook.balances.clone();
+    let reservation_log = orderbook.reservation_log().to_vec();
+    let pricelevels = orderbook.pricelevels.clone();
+    let books = resting_orders(&orderbook);
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(6), 2);
+    let (prepared, result) = orderbook.prepare_order(taker.clone(), 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    // The unfilled part of the taker rests until rolled back
+    assert_eq!(orderbook.bid_books[&pair].len(), 1);
+
+    orderbook.rollback(prepared);
+    assert_eq!(orderbook.balances, balances);
+    assert_eq!(orderbook.reservation_log(), reservation_log.as_slice());
+    assert_eq!(orderbook.pricelevels, pricelevels);
+    assert_eq!(resting_orders(&orderbook), books);
+    assert!(orderbook.fee_revenue(0).is_empty());
+    // Nothing is frozen anymore
+    orderbook.process_order(taker, 3).unwrap();
+}
+
+#[test]
+pub fn test_maintenance_is_rejected_while_execution_is_prepared() {
+    let pair = test_pair();
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(4),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    // Carol only has an order resting in the pair and is not part of the execution
+    let resting = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::new(5, 1),
+        Decimal::from(2),
+        2,
+    );
+    orderbook.process_order(resting, 2).unwrap();
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(2), 3);
+    let (prepared, _) = orderbook.prepare_order(taker, 3).unwrap();
+    for main in [&bob, &carol] {
+        let actual = BTreeMap::from([(pair.quote, Decimal::from(1000))]);
+        let errors = [
+            orderbook.sweep_dust(main, Decimal::from(1)).unwrap_err(),
+            orderbook
+                .reclaim_market_order_reservations(main)
+                .unwrap_err(),
+            orderbook.reconcile_account(main, actual).unwrap_err(),
+        ];
+        for err in errors {
+            assert!(matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::ExecutionPending)
+            ));
+        }
+    }
+
+    orderbook.rollback(prepared);
+    orderbook.reclaim_market_order_reservations(&carol).unwrap();
+}
@@ -0,0 +1,53 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_fee_revenue_converted_into_one_asset() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(1, 3));
+    }
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(10),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    for stid in 2..=4 {
+        let taker = limit_order(
+            &bob,
+            OrderSide::Bid,
+            Decimal::from(2),
+            Decimal::from(1),
+            stid as i64,
+        );
+        orderbook.process_order(taker, stid).unwrap();
+    }
+    // 0.003 base from bob and 0.006 quote from alice
+    assert_eq!(orderbook.fee_revenue_in(0, pair.quote), None);
+
+    orderbook.set_conversion_rate(pair.base, pair.quote, Decimal::from(2));
+    assert_eq!(
+        orderbook.convert(Decimal::from(3), pair.base, pair.quote),
+        Some(Decimal::from(6))
+    );
+    assert_eq!(
+        orderbook.fee_revenue_in(0, pair.quote),
+        Some(Decimal::new(12, 3))
+    );
+    // The reverse direction uses the reciprocal rate
+    assert_eq!(
+        orderbook.fee_revenue_in(0, pair.base),
+        Some(Decimal::new(6, 3))
+    );
+    // No rate into an unrelated asset
+    let other = AssetId::Asset(7);
+    assert_eq!(orderbook.convert(Decimal::ONE, pair.base, other), None);
+    assert_eq!(orderbook.fee_revenue_in(0, other), None);
+}
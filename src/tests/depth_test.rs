@@ -0,0 +1,208 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_price_at_depth() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    for (ts, price, qty) in [(1, 10, 5), (2, 11, 5), (3, 12, 10)] {
+        let order = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    for (ts, price) in [(4, 8), (5, 9)] {
+        let order = limit_order(
+            &alice,
+            OrderSide::Bid,
+            Decimal::from(price),
+            Decimal::from(5),
+            ts,
+        );
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+
+    let depth = |side, qty| orderbook.price_at_depth(&pair, side, Decimal::from(qty));
+    assert_eq!(depth(OrderSide::Ask, 3), Some(Decimal::from(10)));
+    assert_eq!(depth(OrderSide::Ask, 5), Some(Decimal::from(10)));
+    assert_eq!(depth(OrderSide::Ask, 6), Some(Decimal::from(11)));
+    assert_eq!(depth(OrderSide::Ask, 20), Some(Decimal::from(12)));
+    assert_eq!(depth(OrderSide::Ask, 21), None);
+    assert_eq!(depth(OrderSide::Bid, 5), Some(Decimal::from(9)));
+    assert_eq!(depth(OrderSide::Bid, 10), Some(Decimal::from(8)));
+    assert_eq!(depth(OrderSide::Bid, 11), None);
+}
+
+#[test]
+pub fn test_price_to_fill() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    for (ts, price, qty) in [(1, 10, 5), (2, 11, 5)] {
+        let order = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    // A buy of 7 has to reach the second level
+    assert_eq!(
+        orderbook.price_to_fill(&pair, OrderSide::Bid, Decimal::from(7)),
+        Some(Decimal::from(11))
+    );
+    assert_eq!(
+        orderbook.price_to_fill(&pair, OrderSide::Bid, Decimal::from(5)),
+        Some(Decimal::from(10))
+    );
+    // Not enough liquidity on either side
+    assert_eq!(
+        orderbook.price_to_fill(&pair, OrderSide::Bid, Decimal::from(11)),
+        None
+    );
+    assert_eq!(
+        orderbook.price_to_fill(&pair, OrderSide::Ask, Decimal::from(1)),
+        None
+    );
+}
+
+#[test]
+pub fn test_liquidity_score_prefers_tight_deep_books() {
+    let alice = account(1);
+    let pair = test_pair();
+    let score_of = |levels: [(i64, Decimal, Decimal); 2]| {
+        let mut orderbook = funded_orderbook(&[alice.clone()]);
+        for (ts, price, qty) in levels {
+            let side = if ts == 1 {
+                OrderSide::Bid
+            } else {
+                OrderSide::Ask
+            };
+            let order = limit_order(&alice, side, price, qty, ts);
+            orderbook.process_order(order, ts as u64).unwrap();
+        }
+        orderbook.liquidity_score(&pair, Decimal::from(5))
+    };
+    // Both books have a mid price of 100, so the band is 5 wide
+    let tight = score_of([
+        (1, Decimal::new(995, 1), Decimal::from(10)),
+        (2, Decimal::new(1005, 1), Decimal::from(10)),
+    ]);
+    let wide = score_of([
+        (1, Decimal::from(98), Decimal::from(1)),
+        (2, Decimal::from(102), Decimal::from(1)),
+    ]);
+    // (995 + 1005) * 0.9 and (98 + 102) * 0.6
+    assert_eq!(tight, Decimal::from(1800));
+    assert_eq!(wide, Decimal::from(120));
+    assert!(tight > wide);
+
+    let empty = funded_orderbook(&[alice.clone()]);
+    assert_eq!(
+        empty.liquidity_score(&pair, Decimal::from(5)),
+        Decimal::ZERO
+    );
+}
+
+#[test]
+pub fn test_orders_by_distance_from_mid() {
+    let alice = account(1);
+    let pair = test_pair();
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    assert!(orderbook.orders_by_distance_from_mid(&pair).is_empty());
+    let orders = [
+        (OrderSide::Bid, 9, 1),
+        (OrderSide::Bid, 8, 2),
+        (OrderSide::Ask, 11, 3),
+        (OrderSide::Ask, 15, 4),
+    ];
+    for (side, price, ts) in orders {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(1), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    // Mid price is 10
+    let sorted = orderbook.orders_by_distance_from_mid(&pair);
+    let distances: Vec<Decimal> = sorted.iter().map(|(_, distance)| *distance).collect();
+    assert_eq!(distances, [1, 1, 2, 5].map(Decimal::from).to_vec());
+    assert_eq!(sorted[2].0.price, Decimal::from(8));
+    assert_eq!(sorted[3].0.price, Decimal::from(15));
+}
+
+#[test]
+pub fn test_fill_curve() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    for (ts, price, qty) in [(1, 10, 2), (2, 11, 2), (3, 12, 4)] {
+        let order = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    let curve = orderbook.fill_curve(&pair, OrderSide::Bid, Decimal::from(8), 4);
+    assert_eq!(
+        curve,
+        vec![
+            (Decimal::from(2), Decimal::from(10)),
+            (Decimal::from(4), Decimal::new(105, 1)),
+            (Decimal::from(6), Decimal::from(11)),
+            (Decimal::from(8), Decimal::new(1125, 2)),
+        ]
+    );
+    // Sweeping deeper never gets a better average
+    assert!(curve.windows(2).all(|points| points[0].1 <= points[1].1));
+
+    // The curve stops where the book runs out
+    let curve = orderbook.fill_curve(&pair, OrderSide::Bid, Decimal::from(12), 3);
+    assert_eq!(curve.len(), 2);
+    assert!(orderbook
+        .fill_curve(&pair, OrderSide::Ask, Decimal::from(1), 1)
+        .is_empty());
+}
+
+#[test]
+pub fn test_shock_impact() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    let levels = [
+        (1, OrderSide::Bid, 6, 1),
+        (2, OrderSide::Ask, 10, 2),
+        (3, OrderSide::Ask, 14, 2),
+    ];
+    for (ts, side, price, qty) in levels {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(qty), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    assert_eq!(orderbook.mid_price(&pair), Some(Decimal::from(8)));
+    let impact = |side, qty: Decimal| orderbook.shock_impact(&pair, side, qty);
+
+    // Buying within the best ask leaves the mid where it is
+    assert_eq!(
+        impact(OrderSide::Bid, Decimal::from(1)),
+        Some(Decimal::ZERO)
+    );
+    // Taking the best ask moves the mid from 8 to 10
+    assert_eq!(
+        impact(OrderSide::Bid, Decimal::from(2)),
+        Some(Decimal::from(25))
+    );
+    assert_eq!(impact(OrderSide::Bid, Decimal::from(4)), None);
+    assert_eq!(
+        impact(OrderSide::Ask, Decimal::new(5, 1)),
+        Some(Decimal::ZERO)
+    );
+    assert_eq!(impact(OrderSide::Ask, Decimal::from(1)), None);
+}
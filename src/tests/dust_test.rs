@@ -0,0 +1,64 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_sweep_dust_only_sweeps_free_dust() {
+    let (alice, dave) = (account(1), account(4));
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    orderbook.balances.insert(
+        (dave.clone(), pair.quote),
+        (Decimal::new(205, 1), Decimal::from(0)),
+    );
+    orderbook.update_fee_structure(&dave, Decimal::new(1, 3), Decimal::new(1, 3));
+
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(1),
+        1,
+    );
+    let taker = limit_order(
+        &dave,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(1),
+        2,
+    );
+    let resting = limit_order(&dave, OrderSide::Bid, Decimal::from(5), Decimal::from(2), 3);
+    orderbook.process_order(maker, 1).unwrap();
+    orderbook.process_order(taker, 2).unwrap();
+    orderbook.process_order(resting, 3).unwrap();
+    // Dave is left with 0.5 free and 10 reserved quote and 0.999 base after fees
+    assert_eq!(
+        orderbook.balances[&(dave.clone(), pair.quote)],
+        (Decimal::new(5, 1), Decimal::from(10))
+    );
+    assert_eq!(
+        orderbook.balances[&(dave.clone(), pair.base)],
+        (Decimal::new(999, 3), Decimal::from(0))
+    );
+
+    let pot = orderbook.fees_collector.pot.clone();
+    let pot_quote = orderbook
+        .balances
+        .get(&(pot.clone(), pair.quote))
+        .map(|(free, _)| *free)
+        .unwrap_or_default();
+    let changes = orderbook.sweep_dust(&dave, Decimal::new(6, 1)).unwrap();
+    assert_eq!(changes.balances.len(), 2);
+    assert_eq!(
+        orderbook.balances[&(dave.clone(), pair.quote)],
+        (Decimal::from(0), Decimal::from(10))
+    );
+    assert_eq!(
+        orderbook.balances[&(dave, pair.base)],
+        (Decimal::new(999, 3), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(pot, pair.quote)].0,
+        pot_quote + Decimal::new(5, 1)
+    );
+}
@@ -0,0 +1,59 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_taker_consuming_whole_side_rests_remainder() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::ZERO, Decimal::ZERO);
+    }
+    for (ts, price, qty) in [(1, 10, 1), (2, 11, 2)] {
+        let ask = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        let result = orderbook.process_order(ask, ts as u64).unwrap();
+        assert!(result.emptied_sides().is_empty());
+    }
+
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(12), Decimal::from(5), 3);
+    let result = orderbook.process_order(bid.clone(), 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    assert!(result.emptied_sides().contains(&(pair, OrderSide::Ask)));
+    assert_eq!(result.emptied_sides().len(), 1);
+
+    // The consumed side has no levels left and both are published as removed
+    assert!(orderbook.book_levels(&pair, OrderSide::Ask).is_empty());
+    for price in [10, 11] {
+        assert_eq!(
+            result.pricelevels[&(pair, OrderSide::Ask, Decimal::from(price))],
+            Decimal::ZERO
+        );
+    }
+    // The remainder rests and is published
+    assert_eq!(result.modified_orders[&bid.id].status, OrderStatus::OPEN);
+    assert_eq!(
+        result.pricelevels[&(pair, OrderSide::Bid, Decimal::from(12))],
+        Decimal::from(2)
+    );
+    assert_eq!(
+        orderbook.book_levels(&pair, OrderSide::Bid),
+        vec![(Decimal::from(12), Decimal::from(2))]
+    );
+    // 32 quote was spent and 12 * 2 stays reserved for the remainder
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(944), Decimal::from(24))
+    );
+
+    // An order on the empty side does not flag it again
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(9), Decimal::from(1), 4);
+    let result = orderbook.process_order(bid, 4).unwrap();
+    assert!(result.emptied_sides().is_empty());
+}
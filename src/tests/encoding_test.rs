@@ -0,0 +1,38 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use crate::OrderExecutionResult;
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_compact_encoding_round_trip() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(10),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(3), Decimal::from(4), 2);
+    orderbook.process_order(maker, 1).unwrap();
+    let mut result = orderbook.process_order(taker, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert!(!result.balances.is_empty());
+    assert!(!result.pricelevels.is_empty());
+
+    // Modified orders, fee receipts, the taker and emptied sides are not part of the compact
+    // encoding
+    result.modified_orders.clear();
+    result.fees.clear();
+    result.taker = None;
+    result.emptied_sides.clear();
+    let encoded = result.encode_compact();
+    let decoded = OrderExecutionResult::decode_compact(&encoded).unwrap();
+    assert_eq!(decoded, result);
+
+    assert!(OrderExecutionResult::decode_compact(&encoded[..encoded.len() - 1]).is_err());
+    let mut trailing = encoded.clone();
+    trailing.push(0);
+    assert!(OrderExecutionResult::decode_compact(&trailing).is_err());
+}
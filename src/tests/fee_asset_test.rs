@@ -0,0 +1,195 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::{FeeAsset, Orderbook};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+fn trade_with_fee_assets(maker_fee_asset: FeeAsset, taker_fee_asset: FeeAsset) -> Orderbook {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_fee_assets(maker_fee_asset, taker_fee_asset);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(2, 3));
+    }
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(2), 2);
+    orderbook.process_order(maker, 1).unwrap();
+    let result = orderbook.process_order(taker, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    orderbook
+}
+
+#[test]
+pub fn test_maker_pays_in_given_away_asset() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let orderbook = trade_with_fee_assets(FeeAsset::GivenAway, FeeAsset::Received);
+    // The maker gives away base and pays 0.002 base on top
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::new(997998, 3), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.quote)],
+        (Decimal::from(1020), Decimal::from(0))
+    );
+    // The taker receives base and pays 0.004 base out of it
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.base)],
+        (Decimal::new(1001996, 3), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.quote)],
+        (Decimal::from(980), Decimal::from(0))
+    );
+    let pot = orderbook.fees_collector.pot.clone();
+    assert_eq!(orderbook.balances[&(pot, pair.base)].0, Decimal::new(6, 3));
+}
+
+#[test]
+pub fn test_taker_pays_in_given_away_asset() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let orderbook = trade_with_fee_assets(FeeAsset::Received, FeeAsset::GivenAway);
+    // The maker receives quote and pays 0.02 quote out of it
+    assert_eq!(
+        orderbook.balances[&(alice, pair.quote)],
+        (Decimal::new(101998, 2), Decimal::from(0))
+    );
+    // The taker gives away quote and pays 0.04 quote on top
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::new(97996, 2), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::from(1002), Decimal::from(0))
+    );
+    let pot = orderbook.fees_collector.pot.clone();
+    assert_eq!(orderbook.balances[&(pot, pair.quote)].0, Decimal::new(6, 2));
+}
+
+#[test]
+pub fn test_given_away_fee_must_be_covered_at_entry() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    orderbook.set_fee_assets(FeeAsset::Received, FeeAsset::GivenAway);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(2, 3));
+    }
+    orderbook.balances.insert(
+        (bob.clone(), pair.quote),
+        (Decimal::from(20), Decimal::from(0)),
+    );
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    // 20 quote can not cover the 0.04 quote taker fee on top
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(2), 2);
+    let err = orderbook.process_order(taker.clone(), 2).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InsufficientBalance)
+    ));
+
+    orderbook.balances.insert(
+        (bob.clone(), pair.quote),
+        (Decimal::new(2004, 2), Decimal::from(0)),
+    );
+    orderbook.process_order(taker, 3).unwrap();
+    // Bob receives the full base and pays the fee in quote
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.base)],
+        (Decimal::from(2), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.quote)],
+        (Decimal::from(0), Decimal::from(0))
+    );
+}
+
+#[test]
+pub fn test_notional_fees_are_paid_in_quote_by_both_sides() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let orderbook = trade_with_fee_assets(FeeAsset::QuoteNotional, FeeAsset::QuoteNotional);
+    // The maker sells for a notional of 20 quote and pays 0.1% of it out of the received quote
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::new(101998, 2), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(998), Decimal::from(0))
+    );
+    // The taker buys for the same notional and pays 0.2% of it on top
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::new(97996, 2), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::from(1002), Decimal::from(0))
+    );
+    let pot = orderbook.fees_collector.pot.clone();
+    assert_eq!(
+        orderbook.balances[&(pot.clone(), pair.quote)].0,
+        Decimal::new(6, 2)
+    );
+    assert!(!orderbook.balances.contains_key(&(pot, pair.base)));
+}
+
+#[test]
+pub fn test_given_away_fee_is_reserved_with_the_order() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_fee_assets(FeeAsset::GivenAway, FeeAsset::Received);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(2, 3));
+    }
+    // Reserves 2 base and the 0.002 base maker fee on top
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    // A second ask leaves only 0.001 base free
+    let resting = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(100),
+        Decimal::from(997),
+        2,
+    );
+    orderbook.process_order(resting, 2).unwrap();
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::new(1, 3), Decimal::new(999999, 3))
+    );
+
+    // The maker still pays its full fee out of its reservation
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(2), 3);
+    orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::new(1, 3), Decimal::new(997997, 3))
+    );
+    let pot = orderbook.fees_collector.pot.clone();
+    assert_eq!(orderbook.balances[&(pot, pair.base)].0, Decimal::new(6, 3));
+}
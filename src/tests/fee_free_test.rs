@@ -0,0 +1,113 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::{AccountFee, Orderbook};
+use orderbook_primitives::types::OrderSide;
+use polkadex_primitives::AccountId;
+use rust_decimal::Decimal;
+
+// Matches a bid of the taker against a new ask of the maker, returns their fees
+fn trade(
+    orderbook: &mut Orderbook,
+    maker: &AccountId,
+    taker: &AccountId,
+    ts: i64,
+) -> (Decimal, Decimal) {
+    let ask = limit_order(
+        maker,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(1),
+        ts,
+    );
+    orderbook.process_order(ask, ts as u64).unwrap();
+    let bid = limit_order(
+        taker,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(1),
+        ts + 1,
+    );
+    let result = orderbook.process_order(bid, ts as u64 + 1).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    let fee_of = |main: &AccountId| {
+        result
+            .fees
+            .iter()
+            .find(|receipt| receipt.user == *main)
+            .map(|receipt| receipt.amt)
+            .unwrap()
+    };
+    (fee_of(maker), fee_of(taker))
+}
+
+#[test]
+pub fn test_fees_resume_after_fee_free_period() {
+    let (alice, carol) = (account(1), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), carol.clone()]);
+    let pair = test_pair();
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(1, 3));
+    orderbook.update_fee_structure(&carol, Decimal::new(2, 3), Decimal::new(2, 3));
+    orderbook.set_fee_free_until(&carol, 5);
+
+    // Before the deadline only the maker pays
+    let (maker_fee, taker_fee) = trade(&mut orderbook, &alice, &carol, 1);
+    assert_eq!(maker_fee, Decimal::new(1, 2));
+    assert_eq!(taker_fee, Decimal::ZERO);
+    assert_eq!(
+        orderbook.balances[&(carol.clone(), pair.base)].0,
+        Decimal::from(1001)
+    );
+
+    // From the deadline on the taker pays its fee on the received base again
+    let (maker_fee, taker_fee) = trade(&mut orderbook, &alice, &carol, 5);
+    assert_eq!(maker_fee, Decimal::new(1, 2));
+    assert_eq!(taker_fee, Decimal::new(2, 3));
+    assert_eq!(
+        orderbook.balances[&(carol, pair.base)].0,
+        Decimal::new(1001998, 3)
+    );
+}
+
+#[test]
+pub fn test_default_fee_applies_to_accounts_without_entry() {
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    let (maker_fee, taker_fee) = trade(&mut orderbook, &alice, &bob, 1);
+    assert!(maker_fee > Decimal::ZERO && taker_fee > Decimal::ZERO);
+
+    orderbook.set_default_fee(AccountFee {
+        maker_fraction: Decimal::ZERO,
+        taker_fraction: Decimal::ZERO,
+        priority_boost: 0,
+        flat_fee: Decimal::ZERO,
+    });
+    assert!(!orderbook.fees_collector.fee_structure.contains_key(&alice));
+    assert_eq!(
+        trade(&mut orderbook, &alice, &bob, 3),
+        (Decimal::ZERO, Decimal::ZERO)
+    );
+    // Accounts with their own entry keep paying it
+    orderbook.update_fee_structure(&carol, Decimal::new(1, 3), Decimal::new(2, 3));
+    let (maker_fee, taker_fee) = trade(&mut orderbook, &alice, &carol, 5);
+    assert_eq!(maker_fee, Decimal::ZERO);
+    assert_eq!(taker_fee, Decimal::new(2, 3));
+}
+
+#[test]
+pub fn test_default_fee_applies_to_settings_an_account_did_not_override() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    // Entries created for other settings do not pin the fractions of the defaults of the time
+    orderbook.update_priority_boost(&alice, 1);
+    orderbook.update_flat_fee(&bob, Decimal::ZERO);
+    orderbook.set_default_fee(AccountFee {
+        maker_fraction: Decimal::ZERO,
+        taker_fraction: Decimal::ZERO,
+        priority_boost: 0,
+        flat_fee: Decimal::ZERO,
+    });
+    assert_eq!(
+        trade(&mut orderbook, &alice, &bob, 1),
+        (Decimal::ZERO, Decimal::ZERO)
+    );
+    assert_eq!(orderbook.fees_collector.priority_boost(&alice), 1);
+}
@@ -0,0 +1,393 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::{OrderExecutionResult, Orderbook};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_fees_are_applied_once_per_order() {
+    let (alice, carol) = (account(1), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), carol.clone()]);
+    let pair = test_pair();
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(1, 3));
+    orderbook.update_fee_structure(&carol, Decimal::new(2, 3), Decimal::new(2, 3));
+
+    let makers: Vec<_> = (1..=3)
+        .map(|ts| {
+            limit_order(
+                &alice,
+                OrderSide::Ask,
+                Decimal::from(10),
+                Decimal::from(2),
+                ts,
+            )
+        })
+        .collect();
+    for maker in &makers {
+        orderbook.process_order(maker.clone(), 1).unwrap();
+    }
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(5),
+        4,
+    );
+    let result = orderbook.process_order(taker.clone(), 2).unwrap();
+    assert_eq!(result.trades.len(), 3);
+    assert_eq!(result.fees.len(), 6);
+
+    let fees_of = |id| -> Decimal {
+        result
+            .fees
+            .iter()
+            .filter(|receipt| receipt.order_id == id)
+            .map(|receipt| receipt.amt)
+            .sum()
+    };
+    // The taker fee is the sum over all of its trades
+    let taker_fee = fees_of(taker.id);
+    assert!(taker_fee > Decimal::from(0));
+    assert_eq!(result.modified_orders[&taker.id].fee, taker_fee);
+    assert_eq!(
+        result.modified_orders[&taker.id].status,
+        OrderStatus::CLOSED
+    );
+    for maker in &makers {
+        assert_eq!(result.modified_orders[&maker.id].fee, fees_of(maker.id));
+    }
+
+    // Only the partially filled maker is still resting, with its fee applied
+    let resting = orderbook.ask_books[&pair].peek().unwrap().clone();
+    assert_eq!(resting.id, makers[2].id);
+    assert_eq!(resting.fee, fees_of(makers[2].id));
+
+    // Several receipts for the same resting order update it once
+    let receipt = result
+        .fees
+        .iter()
+        .find(|receipt| receipt.order_id == resting.id)
+        .unwrap()
+        .clone();
+    let mut changes = OrderExecutionResult::new(3);
+    changes.modified_orders.insert(resting.id, resting.clone());
+    changes.fees = vec![receipt.clone(), receipt.clone(), receipt.clone()];
+    assert_eq!(orderbook.apply_fee_receipts(&mut changes), 1);
+    let expected = resting.fee + receipt.amt * Decimal::from(3);
+    assert_eq!(changes.modified_orders[&resting.id].fee, expected);
+    assert_eq!(orderbook.ask_books[&pair].peek().unwrap().fee, expected);
+}
+
+#[test]
+pub fn test_pending_fees_equal_pot_increase() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let pair = test_pair();
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(1, 3));
+    orderbook.update_fee_structure(&bob, Decimal::new(2, 3), Decimal::new(2, 3));
+    for (ts, price) in [(1, 1), (2, 2)] {
+        let maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(2),
+            ts,
+        );
+        orderbook.process_order(maker, ts as u64).unwrap();
+    }
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(2), Decimal::from(4), 3);
+    let result = orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+
+    let pending = orderbook.pending_fees(&result);
+    // Maker fees are paid in quote and taker fees in base
+    assert_eq!(pending.len(), 2);
+    let pot = orderbook.fees_collector.pot.clone();
+    for asset in [pair.base, pair.quote] {
+        let pot_free = orderbook
+            .balances
+            .get(&(pot.clone(), asset))
+            .map(|(free, _)| *free)
+            .unwrap_or_default();
+        assert_eq!(pending[&asset], pot_free);
+    }
+    assert_eq!(pending[&pair.base], Decimal::new(8, 3));
+}
+
+#[test]
+pub fn test_fee_revenue_since_stid() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(1, 3));
+    }
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(10),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    for stid in 2..=4 {
+        let taker = limit_order(
+            &bob,
+            OrderSide::Bid,
+            Decimal::from(1),
+            Decimal::from(1),
+            stid as i64,
+        );
+        orderbook.process_order(taker, stid).unwrap();
+    }
+    // Every trade accrues 0.001 base from bob and 0.001 quote from alice
+    let revenue = orderbook.fee_revenue(3);
+    assert_eq!(revenue[&pair.base], Decimal::new(2, 3));
+    assert_eq!(revenue[&pair.quote], Decimal::new(2, 3));
+    let revenue = orderbook.fee_revenue(0);
+    assert_eq!(revenue[&pair.base], Decimal::new(3, 3));
+    assert!(orderbook.fee_revenue(5).is_empty());
+}
+
+#[test]
+pub fn test_fee_updates_never_reorder_the_book() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    // Two levels with several orders queued at each
+    let asks: Vec<_> = [(1, 11), (2, 10), (3, 11), (4, 10), (5, 10)]
+        .into_iter()
+        .map(|(ts, price)| {
+            limit_order(
+                &alice,
+                OrderSide::Ask,
+                Decimal::from(price),
+                Decimal::from(1),
+                ts,
+            )
+        })
+        .collect();
+    for ask in &asks {
+        orderbook.process_order(ask.clone(), 1).unwrap();
+    }
+    let pop_order = |orderbook: &Orderbook| {
+        let mut book = orderbook.ask_books[&pair].clone();
+        std::iter::from_fn(move || book.pop())
+            .map(|order| order.id)
+            .collect::<Vec<_>>()
+    };
+    let before = pop_order(&orderbook);
+
+    for (index, ask) in asks.iter().enumerate() {
+        let mut updated = ask.clone();
+        updated.fee = Decimal::from(index as u64 + 1);
+        orderbook.update_in_memory_order_state_with_fee(&updated);
+        assert_eq!(pop_order(&orderbook), before);
+    }
+    assert!(orderbook.ask_books[&pair]
+        .iter()
+        .all(|order| order.fee > Decimal::from(0)));
+}
+
+#[test]
+pub fn test_fee_receipts_reconcile_with_pot_and_orders() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(2, 3));
+    orderbook.update_fee_structure(&bob, Decimal::new(1, 3), Decimal::new(2, 3));
+    for (ts, price) in [(1, 10), (2, 11)] {
+        let ask = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::ONE,
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+    let pot = orderbook.fees_collector.pot.clone();
+    let pot_balance = |orderbook: &Orderbook, asset| {
+        orderbook
+            .balances
+            .get(&(pot.clone(), asset))
+            .map(|(free, _)| *free)
+            .unwrap_or_default()
+    };
+    let before = [
+        pot_balance(&orderbook, pair.base),
+        pot_balance(&orderbook, pair.quote),
+    ];
+
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::from(2), 3);
+    let result = orderbook.process_order(bid.clone(), 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    // A maker and a taker receipt per trade
+    let receipts = result.fee_receipts();
+    assert_eq!(receipts.len(), 4);
+    for trade in &result.trades {
+        let roles: Vec<_> = receipts
+            .iter()
+            .filter(|receipt| receipt.trade_id == trade.trade_id())
+            .map(|receipt| (receipt.user.clone(), receipt.is_maker))
+            .collect();
+        assert_eq!(roles.len(), 2);
+        assert!(roles.contains(&(alice.clone(), true)));
+        assert!(roles.contains(&(bob.clone(), false)));
+    }
+    for (asset, before) in [pair.base, pair.quote].into_iter().zip(before) {
+        let collected: Decimal = receipts
+            .iter()
+            .filter(|receipt| receipt.asset == asset)
+            .map(|receipt| receipt.amt)
+            .sum();
+        assert!(collected > Decimal::ZERO);
+        assert_eq!(pot_balance(&orderbook, asset) - before, collected);
+    }
+    for (order_id, order) in &result.modified_orders {
+        let paid: Decimal = receipts
+            .iter()
+            .filter(|receipt| receipt.order_id == *order_id)
+            .map(|receipt| receipt.amt)
+            .sum();
+        assert_eq!(order.fee, paid);
+    }
+    // Bob pays 0.2% of the base he receives
+    let taker = &result.modified_orders[&bid.id];
+    assert_eq!(taker.fee, Decimal::new(4, 3));
+    assert_eq!(taker.status, OrderStatus::CLOSED);
+}
+
+#[test]
+pub fn test_fees_paid_by_account() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(2, 3));
+    }
+    assert!(orderbook.fees_paid_by(&alice).is_empty());
+    // Alice sells 1 at 10 as maker, then buys 2 at 20 as taker
+    let fills = [
+        (&alice, &bob, OrderSide::Ask, 10, 1),
+        (&bob, &alice, OrderSide::Ask, 20, 2),
+    ];
+    for (index, (maker, taker, side, price, qty)) in fills.into_iter().enumerate() {
+        let ts = 2 * index as i64 + 1;
+        let price = Decimal::from(price);
+        let qty = Decimal::from(qty);
+        let resting = limit_order(maker, side, price, qty, ts);
+        orderbook.process_order(resting, ts as u64).unwrap();
+        let taking = limit_order(taker, OrderSide::Bid, price, qty, ts + 1);
+        let result = orderbook.process_order(taking, ts as u64 + 1).unwrap();
+        assert_eq!(result.trades.len(), 1);
+    }
+
+    // Fees are paid in the received asset
+    assert_eq!(
+        orderbook.fees_paid_by(&alice),
+        [
+            (pair.base, Decimal::new(4, 3)),
+            (pair.quote, Decimal::new(1, 2))
+        ]
+        .into_iter()
+        .collect()
+    );
+    assert_eq!(
+        orderbook.fees_paid_by(&bob),
+        [
+            (pair.base, Decimal::new(2, 3)),
+            (pair.quote, Decimal::new(4, 2))
+        ]
+        .into_iter()
+        .collect()
+    );
+    assert!(orderbook.fees_paid_by(&account(3)).is_empty());
+}
+
+#[test]
+pub fn test_deferred_maker_fee_is_applied_once_at_close() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(2, 3));
+    orderbook.pair_settings_mut(pair).defer_maker_fees = true;
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(10),
+        1,
+    );
+    orderbook.process_order(maker.clone(), 1).unwrap();
+
+    let mut receipts_total = Decimal::ZERO;
+    for ts in 2..=11 {
+        let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, ts);
+        let result = orderbook.process_order(taker, ts as u64).unwrap();
+        assert_eq!(result.trades.len(), 1);
+        receipts_total += result
+            .fee_receipts()
+            .iter()
+            .filter(|receipt| receipt.order_id == maker.id)
+            .map(|receipt| receipt.amt)
+            .sum::<Decimal>();
+        let modified = &result.modified_orders[&maker.id];
+        if ts < 11 {
+            // The resting maker and its published state are left untouched
+            assert_eq!(modified.fee, Decimal::ZERO);
+            let resting = orderbook.ask_books[&pair].peek().unwrap().clone();
+            assert_eq!(resting.fee, Decimal::ZERO);
+            assert_eq!(resting.filled_quantity, Decimal::from(ts - 1));
+
+            // Replaying the receipts of the fill updates no resting order
+            let deferred = orderbook.deferred_maker_fees.clone();
+            let mut changes = OrderExecutionResult::new(ts as u64);
+            changes.modified_orders.insert(maker.id, resting.clone());
+            changes.fees = result.fee_receipts().to_vec();
+            changes.fees.retain(|receipt| receipt.order_id == maker.id);
+            assert_eq!(orderbook.apply_fee_receipts(&mut changes), 0);
+            assert_eq!(changes.modified_orders[&maker.id].fee, Decimal::ZERO);
+            let after = orderbook.ask_books[&pair].peek().unwrap();
+            assert_eq!(after.id, resting.id);
+            assert_eq!(after.fee, resting.fee);
+            assert_eq!(after.filled_quantity, resting.filled_quantity);
+            assert_eq!(after.status, OrderStatus::OPEN);
+            // Drop the fee accrued by the replay
+            orderbook.deferred_maker_fees = deferred;
+        } else {
+            assert_eq!(modified.status, OrderStatus::CLOSED);
+            assert_eq!(modified.fee, receipts_total);
+        }
+    }
+    // 0.1% of 10 quote on each of the ten fills
+    assert_eq!(receipts_total, Decimal::new(1, 1));
+    assert!(orderbook.deferred_maker_fees.is_empty());
+}
+
+#[test]
+pub fn test_deferred_maker_fee_is_applied_on_cancel() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(2, 3));
+    orderbook.pair_settings_mut(pair).defer_maker_fees = true;
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(10),
+        1,
+    );
+    orderbook.process_order(maker.clone(), 1).unwrap();
+    for ts in 2..=4 {
+        let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, ts);
+        orderbook.process_order(taker, ts as u64).unwrap();
+    }
+
+    let result = orderbook
+        .cancel_order(pair, OrderSide::Ask, &maker.id, 5, 5)
+        .unwrap();
+    let cancelled = &result.modified_orders[&maker.id];
+    assert_eq!(cancelled.status, OrderStatus::CANCELLED);
+    assert_eq!(cancelled.fee, Decimal::new(3, 2));
+}
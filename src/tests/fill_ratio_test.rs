@@ -0,0 +1,66 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use orderbook_primitives::types::{OrderSide, OrderType};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_fill_ratio_of_unfilled_order() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let order = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(1),
+        Decimal::from(4),
+        1,
+    );
+    let result = orderbook.process_order(order, 1).unwrap();
+    assert_eq!(result.fill_ratio(), Decimal::from(0));
+}
+
+#[test]
+pub fn test_fill_ratio_of_partially_and_fully_filled_orders() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(4), 2);
+    let result = orderbook.process_order(taker, 2).unwrap();
+    assert_eq!(result.fill_ratio(), Decimal::new(5, 1));
+
+    // The rest of the taker now rests as a maker for a full fill
+    let taker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(2),
+        3,
+    );
+    let result = orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(result.fill_ratio(), Decimal::from(1));
+}
+
+#[test]
+pub fn test_fill_ratio_of_quote_budget_market_buy() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(5),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+    let mut taker = limit_order(&bob, OrderSide::Bid, Decimal::from(0), Decimal::from(0), 2);
+    taker.order_type = OrderType::MARKET;
+    taker.quote_order_qty = Decimal::from(10);
+    let result = orderbook.process_order(taker, 2).unwrap();
+    // Half of the quote budget is spent on the only maker
+    assert_eq!(result.fill_ratio(), Decimal::new(5, 1));
+}
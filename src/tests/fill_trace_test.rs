@@ -0,0 +1,53 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_fill_traces_reconstruct_sweep() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let makers: Vec<_> = (1..=3)
+        .map(|ts| {
+            limit_order(
+                &alice,
+                OrderSide::Ask,
+                Decimal::from(10),
+                Decimal::from(2),
+                ts,
+            )
+        })
+        .collect();
+    for maker in &makers {
+        orderbook
+            .process_order(maker.clone(), maker.timestamp as u64)
+            .unwrap();
+    }
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(5), 4);
+    let result = orderbook.process_order(taker.clone(), 4).unwrap();
+
+    let traces = result.fill_traces();
+    assert_eq!(traces.len(), 3);
+    let expected_makers: Vec<_> = makers.iter().map(|maker| maker.id).collect();
+    let traced_makers: Vec<_> = traces.iter().map(|trace| trace.maker_id).collect();
+    assert_eq!(traced_makers, expected_makers);
+    // Each maker is filled from scratch, the last one only partially
+    let maker_fills: Vec<_> = traces
+        .iter()
+        .map(|trace| (trace.maker_filled_before, trace.maker_filled_after))
+        .collect();
+    assert_eq!(
+        maker_fills,
+        vec![
+            (Decimal::from(0), Decimal::from(2)),
+            (Decimal::from(0), Decimal::from(2)),
+            (Decimal::from(0), Decimal::from(1)),
+        ]
+    );
+    // The taker fills continue where the previous trade left off
+    assert!(traces.iter().all(|trace| trace.taker_id == taker.id));
+    assert_eq!(traces[0].taker_filled_before, Decimal::from(0));
+    for pair in traces.windows(2) {
+        assert_eq!(pair[0].taker_filled_after, pair[1].taker_filled_before);
+    }
+    assert_eq!(traces[2].taker_filled_after, Decimal::from(5));
+}
@@ -0,0 +1,48 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_flat_fee_is_charged_once_per_order() {
+    let (alice, carol) = (account(1), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), carol.clone()]);
+    let pair = test_pair();
+    for main in [&alice, &carol] {
+        orderbook.update_fee_structure(main, Decimal::from(0), Decimal::from(0));
+    }
+    orderbook.update_flat_fee(&carol, Decimal::from(1));
+
+    for ts in 1..=3 {
+        let maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(10),
+            Decimal::from(2),
+            ts,
+        );
+        orderbook.process_order(maker, ts as u64).unwrap();
+    }
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(6),
+        4,
+    );
+    let result = orderbook.process_order(taker, 4).unwrap();
+    assert_eq!(result.trades.len(), 3);
+
+    // Carol receives 6 base and pays the flat fee of 1 base once
+    assert_eq!(
+        orderbook.balances.get(&(carol, pair.base)).unwrap().0,
+        Decimal::from(1005)
+    );
+    assert_eq!(
+        orderbook
+            .balances
+            .get(&(orderbook.fees_collector.pot.clone(), pair.base))
+            .unwrap()
+            .0,
+        Decimal::from(1)
+    );
+}
@@ -0,0 +1,274 @@
+use crate::tests::{account, limit_order};
+use crate::Orderbook;
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::{OrderSide, OrderStatus, TradingPair};
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+use std::collections::BTreeSet;
+
+#[test]
+pub fn test_implied_pair_generates_linked_leg_trades() {
+    let (a, b, c) = (AssetId::Asset(1), AssetId::Asset(2), AssetId::Polkadex);
+    let first_leg = TradingPair::from(b, a);
+    let second_leg = TradingPair::from(c, b);
+    let implied = TradingPair::from(c, a);
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+
+    let mut orderbook = Orderbook::new();
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(a, b))
+        .unwrap();
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(b, c))
+        .unwrap();
+    orderbook
+        .balances
+        .insert((alice.clone(), a), (Decimal::from(1000), Decimal::from(0)));
+    orderbook
+        .balances
+        .insert((bob.clone(), b), (Decimal::from(1000), Decimal::from(0)));
+    orderbook
+        .balances
+        .insert((carol.clone(), c), (Decimal::from(1000), Decimal::from(0)));
+    orderbook.update_fee_structure(&carol, Decimal::from(0), Decimal::from(0));
+
+    let mut first_maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(10),
+        1,
+    );
+    first_maker.pair = first_leg;
+    let mut second_maker = limit_order(
+        &bob,
+        OrderSide::Ask,
+        Decimal::from(3),
+        Decimal::from(100),
+        2,
+    );
+    second_maker.pair = second_leg;
+    orderbook.process_order(first_maker, 1).unwrap();
+    orderbook.process_order(second_maker, 2).unwrap();
+
+    // Unregistered implied pairs are rejected
+    let mut taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(7),
+        Decimal::from(5),
+        3,
+    );
+    taker.pair = implied;
+    assert!(orderbook.process_implied_order(taker.clone(), 3).is_err());
+    assert!(orderbook
+        .register_implied_pair(implied, second_leg, first_leg)
+        .is_err());
+    orderbook
+        .register_implied_pair(implied, first_leg, second_leg)
+        .unwrap();
+
+    // Implied price is 2 * 3 = 6
+    let level = orderbook
+        .best_implied_level(&implied, OrderSide::Bid, Decimal::from(0))
+        .unwrap();
+    assert_eq!(level.price(), Decimal::from(6));
+
+    let result = orderbook.process_implied_order(taker.clone(), 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    let (second_trade, first_trade) = (&result.trades[0], &result.trades[1]);
+    assert_eq!(second_trade.maker.pair, second_leg);
+    assert_eq!(second_trade.price, Decimal::from(3));
+    assert_eq!(second_trade.amount, Decimal::from(10));
+    assert_eq!(first_trade.maker.pair, first_leg);
+    assert_eq!(first_trade.price, Decimal::from(2));
+    assert_eq!(first_trade.amount, Decimal::from(5));
+    // The intermediate asset bought on the second leg is spent on the first leg
+    assert_eq!(first_trade.amount * first_trade.price, second_trade.amount);
+    assert_eq!(first_trade.taker.main_account, carol);
+    assert_eq!(second_trade.taker.main_account, carol);
+
+    let implied_order = result.modified_orders.get(&taker.id).unwrap();
+    assert_eq!(implied_order.filled_quantity, Decimal::from(5));
+    assert_eq!(implied_order.status, OrderStatus::CLOSED);
+
+    assert_eq!(
+        orderbook.balances.get(&(carol.clone(), a)).unwrap(),
+        &(Decimal::from(5), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances.get(&(carol.clone(), b)).unwrap(),
+        &(Decimal::from(0), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances.get(&(carol, c)).unwrap(),
+        &(Decimal::from(970), Decimal::from(0))
+    );
+}
+
+#[test]
+pub fn test_implied_legs_are_rolled_back_when_the_closing_leg_fails() {
+    let (a, b, c) = (AssetId::Asset(1), AssetId::Asset(2), AssetId::Polkadex);
+    let first_leg = TradingPair::from(b, a);
+    let second_leg = TradingPair::from(c, b);
+    let implied = TradingPair::from(c, a);
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+
+    let mut orderbook = Orderbook::new();
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(a, b))
+        .unwrap();
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(b, c))
+        .unwrap();
+    orderbook
+        .balances
+        .insert((alice.clone(), a), (Decimal::from(1000), Decimal::from(0)));
+    orderbook
+        .balances
+        .insert((bob.clone(), b), (Decimal::from(1000), Decimal::from(0)));
+    orderbook
+        .balances
+        .insert((carol.clone(), c), (Decimal::from(1000), Decimal::from(0)));
+    orderbook.update_fee_structure(&carol, Decimal::from(0), Decimal::from(0));
+
+    let mut first_maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(10),
+        1,
+    );
+    first_maker.pair = first_leg;
+    let mut second_maker = limit_order(
+        &bob,
+        OrderSide::Ask,
+        Decimal::from(3),
+        Decimal::from(100),
+        2,
+    );
+    second_maker.pair = second_leg;
+    orderbook.process_order(first_maker, 1).unwrap();
+    orderbook.process_order(second_maker.clone(), 2).unwrap();
+    orderbook
+        .register_implied_pair(implied, first_leg, second_leg)
+        .unwrap();
+    // The closing leg on the first leg buys 5, below the minimum qty of its pair
+    orderbook.pair_settings_mut(first_leg).min_qty = Some(Decimal::from(6));
+
+    let mut taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(7),
+        Decimal::from(5),
+        3,
+    );
+    taker.pair = implied;
+    let result = orderbook.process_implied_order(taker.clone(), 3).unwrap();
+    assert!(result.trades.is_empty());
+    assert_eq!(
+        result
+            .modified_orders
+            .get(&taker.id)
+            .unwrap()
+            .filled_quantity,
+        Decimal::from(0)
+    );
+
+    // The opening leg on the second leg was rolled back
+    assert_eq!(
+        orderbook.balances.get(&(carol.clone(), c)).unwrap(),
+        &(Decimal::from(1000), Decimal::from(0))
+    );
+    assert!(orderbook
+        .balances
+        .get(&(carol.clone(), b))
+        .is_none_or(|balance| balance == &(Decimal::from(0), Decimal::from(0))));
+    assert_eq!(
+        orderbook.balances.get(&(bob, b)).unwrap(),
+        &(Decimal::from(900), Decimal::from(100))
+    );
+    let resting = orderbook.ask_books[&second_leg].peek().unwrap();
+    assert_eq!(resting.id, second_maker.id);
+    assert_eq!(resting.filled_quantity, Decimal::from(0));
+    assert!(orderbook.prepared.is_empty());
+}
+
+#[test]
+pub fn test_implied_legs_of_every_level_get_their_own_ids() {
+    let (a, b, c) = (AssetId::Asset(1), AssetId::Asset(2), AssetId::Polkadex);
+    let first_leg = TradingPair::from(b, a);
+    let second_leg = TradingPair::from(c, b);
+    let implied = TradingPair::from(c, a);
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+
+    let mut orderbook = Orderbook::new();
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(a, b))
+        .unwrap();
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(b, c))
+        .unwrap();
+    orderbook
+        .balances
+        .insert((alice.clone(), a), (Decimal::from(1000), Decimal::from(0)));
+    orderbook
+        .balances
+        .insert((bob.clone(), b), (Decimal::from(1000), Decimal::from(0)));
+    orderbook
+        .balances
+        .insert((carol.clone(), c), (Decimal::from(1000), Decimal::from(0)));
+    orderbook.update_fee_structure(&carol, Decimal::from(0), Decimal::from(0));
+    orderbook
+        .register_implied_pair(implied, first_leg, second_leg)
+        .unwrap();
+
+    // Two levels on the first leg, implied at 2 * 3 = 6 and 3 * 3 = 9
+    for (price, ts) in [(2, 1), (3, 2)] {
+        let mut maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(5),
+            ts,
+        );
+        maker.pair = first_leg;
+        orderbook.process_order(maker, ts).unwrap();
+    }
+    let mut second_maker = limit_order(
+        &bob,
+        OrderSide::Ask,
+        Decimal::from(3),
+        Decimal::from(100),
+        3,
+    );
+    second_maker.pair = second_leg;
+    orderbook.process_order(second_maker, 3).unwrap();
+
+    let mut taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(10),
+        4,
+    );
+    taker.pair = implied;
+    let result = orderbook.process_implied_order(taker.clone(), 4).unwrap();
+    assert_eq!(result.trades.len(), 4);
+    let legs: BTreeSet<_> = result.trades.iter().map(|trade| trade.taker.id).collect();
+    assert_eq!(legs.len(), 4);
+    // Every leg of every level is kept in the result
+    for leg in legs {
+        assert_eq!(result.modified_orders[&leg].status, OrderStatus::CLOSED);
+    }
+    let implied_order = result.modified_orders.get(&taker.id).unwrap();
+    assert_eq!(implied_order.filled_quantity, Decimal::from(10));
+    assert_eq!(
+        orderbook.balances.get(&(carol.clone(), a)).unwrap(),
+        &(Decimal::from(10), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances.get(&(carol, c)).unwrap(),
+        &(Decimal::from(925), Decimal::from(0))
+    );
+}
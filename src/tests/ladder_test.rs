@@ -0,0 +1,115 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_filled_rung_posts_next_rung() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let pair = test_pair();
+
+    let base_order = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    assert!(orderbook
+        .register_ladder(base_order.clone(), 0, Decimal::from(1))
+        .is_err());
+    let result = orderbook
+        .register_ladder(base_order, 3, Decimal::from(1))
+        .unwrap();
+    assert!(result.trades.is_empty());
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(10))
+    );
+
+    // Consuming the first rung posts the second rung one step away
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::from(2), 2);
+    let result = orderbook.process_order(taker, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert!(result
+        .modified_orders
+        .values()
+        .any(|order| order.main_account == alice && order.price == Decimal::from(11)));
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(11))
+    );
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::from(2), 3);
+    orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(12))
+    );
+
+    // The last rung does not post anything else
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(12), Decimal::from(2), 4);
+    orderbook.process_order(taker, 4).unwrap();
+    assert_eq!(orderbook.best_price(&pair, OrderSide::Ask), None);
+    assert!(orderbook.ladders.is_empty());
+}
+
+#[test]
+pub fn test_rung_that_can_not_be_posted_is_reported() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let pair = test_pair();
+
+    // Alice can only back one rung of 600
+    let base_order = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(600),
+        1,
+    );
+    orderbook
+        .register_ladder(base_order, 2, Decimal::from(1))
+        .unwrap();
+    let taker = limit_order(
+        &bob,
+        OrderSide::Bid,
+        Decimal::from(1),
+        Decimal::from(600),
+        2,
+    );
+    let result = orderbook.process_order(taker, 2).unwrap();
+
+    // The fill is settled and the ladder ends
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.failed_rungs().len(), 1);
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::from(1600), Decimal::from(0))
+    );
+    assert_eq!(orderbook.best_price(&pair, OrderSide::Ask), None);
+    assert!(orderbook.ladders.is_empty());
+}
+
+#[test]
+pub fn test_cancelled_rung_ends_its_ladder() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    let base_order = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    orderbook
+        .register_ladder(base_order.clone(), 3, Decimal::from(1))
+        .unwrap();
+    assert_eq!(orderbook.ladders.len(), 1);
+
+    orderbook
+        .cancel_order(pair, OrderSide::Ask, &base_order.id, 0, 2)
+        .unwrap();
+    assert!(orderbook.ladders.is_empty());
+}
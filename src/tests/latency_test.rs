@@ -0,0 +1,38 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+use std::time::Duration;
+
+#[test]
+pub fn test_latency_percentiles() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    assert_eq!(orderbook.latency_percentiles(), Default::default());
+
+    for ts in 1..=5 {
+        let order = limit_order(
+            &alice,
+            OrderSide::Bid,
+            Decimal::from(ts),
+            Decimal::from(1),
+            ts,
+        );
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    // Every processed order is sampled
+    assert_eq!(orderbook.latencies.len(), 5);
+    let (p50, p95, p99) = orderbook.latency_percentiles();
+    assert!(p50 <= p95 && p95 <= p99);
+    assert_eq!(p99, *orderbook.latencies.iter().max().unwrap());
+
+    // 1ms to 100ms in reverse order
+    orderbook.latencies = (1..=100).rev().map(Duration::from_millis).collect();
+    assert_eq!(
+        orderbook.latency_percentiles(),
+        (
+            Duration::from_millis(50),
+            Duration::from_millis(95),
+            Duration::from_millis(99)
+        )
+    );
+}
@@ -0,0 +1,360 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::NoReferencePolicy;
+use orderbook_primitives::types::{Order, OrderSide, OrderStatus, OrderType};
+use rust_decimal::Decimal;
+
+fn market_order(side: OrderSide, qty: Decimal, quote_order_qty: Decimal, timestamp: i64) -> Order {
+    let mut order = limit_order(&account(2), side, Decimal::from(0), qty, timestamp);
+    order.order_type = OrderType::MARKET;
+    order.quote_order_qty = quote_order_qty;
+    order
+}
+
+#[test]
+pub fn test_market_order_without_qty_or_quote_is_rejected() {
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    let order = market_order(OrderSide::Bid, Decimal::from(0), Decimal::from(0), 1);
+    let err = orderbook.process_order(order, 1).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InvalidMarketOrder)
+    ));
+    // Nothing was reserved
+    assert_eq!(
+        orderbook.balances[&(account(2), test_pair().quote)],
+        (Decimal::from(1000), Decimal::from(0))
+    );
+}
+
+#[test]
+pub fn test_market_orders_with_qty_or_quote_are_accepted() {
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    let ask = limit_order(
+        &account(1),
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(10),
+        1,
+    );
+    let bid = limit_order(
+        &account(1),
+        OrderSide::Bid,
+        Decimal::from(1),
+        Decimal::from(10),
+        2,
+    );
+    orderbook.process_order(ask, 1).unwrap();
+    orderbook.process_order(bid, 2).unwrap();
+
+    let by_quote = market_order(OrderSide::Bid, Decimal::from(0), Decimal::from(2), 3);
+    let result = orderbook.process_order(by_quote, 3).unwrap();
+    assert_eq!(result.trades.len(), 1);
+
+    let by_qty = market_order(OrderSide::Ask, Decimal::from(2), Decimal::from(0), 4);
+    let result = orderbook.process_order(by_qty, 4).unwrap();
+    assert_eq!(result.trades.len(), 1);
+}
+
+#[test]
+pub fn test_market_order_stops_at_protection_price() {
+    let pair = test_pair();
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    orderbook.update_fee_structure(&account(2), Decimal::from(0), Decimal::from(0));
+    for (ts, price) in [(1, 10), (2, 11), (3, 12)] {
+        let ask = limit_order(
+            &account(1),
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+    // Buys with a budget of 100 but no worse than 11
+    let mut order = market_order(OrderSide::Bid, Decimal::from(0), Decimal::from(100), 4);
+    order.price = Decimal::from(11);
+    let result = orderbook.process_order(order, 4).unwrap();
+
+    let prices: Vec<_> = result.trades.iter().map(|trade| trade.price).collect();
+    assert_eq!(prices, vec![Decimal::from(10), Decimal::from(11)]);
+    // The rest of the budget is unreserved and the ask beyond the protection keeps resting
+    assert_eq!(
+        orderbook.balances[&(account(2), pair.quote)],
+        (Decimal::from(979), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(12))
+    );
+}
+
+#[test]
+pub fn test_protected_order_needs_fresh_reference_price() {
+    let pair = test_pair();
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    orderbook.pair_settings_mut(pair).reference_price_ttl = Some(1_000);
+    orderbook.set_reference_price(pair, Decimal::from(2), 10_000);
+    let bid = limit_order(
+        &account(1),
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(10),
+        1,
+    );
+    orderbook.process_order(bid, 1).unwrap();
+
+    // Protected at 1 and processed within the ttl of the reference price
+    let mut fresh = market_order(OrderSide::Ask, Decimal::from(1), Decimal::from(0), 2);
+    fresh.price = Decimal::from(1);
+    let result = orderbook.process_order_at(fresh, 2, 10_500).unwrap();
+    assert_eq!(result.trades.len(), 1);
+
+    let mut stale = market_order(OrderSide::Ask, Decimal::from(1), Decimal::from(0), 3);
+    stale.price = Decimal::from(1);
+    let err = orderbook
+        .process_order_at(stale.clone(), 3, 11_001)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::StaleReferencePrice)
+    ));
+    // The timestamp of the order is set by the client and does not refresh the reference
+    let mut forged = market_order(OrderSide::Ask, Decimal::from(1), Decimal::from(0), 5);
+    forged.price = Decimal::from(1);
+    forged.timestamp = 10_500;
+    let err = orderbook.process_order(forged, 5).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::StaleReferencePrice)
+    ));
+    // Unprotected orders do not rely on the reference price
+    stale.price = Decimal::from(0);
+    orderbook.process_order_at(stale, 4, 11_001).unwrap();
+}
+
+#[test]
+pub fn test_every_trade_of_market_order_carries_its_final_status() {
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    for (ts, price) in [(1, 12), (2, 11), (3, 10)] {
+        let bid = limit_order(
+            &account(1),
+            OrderSide::Bid,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        orderbook.process_order(bid, ts as u64).unwrap();
+    }
+    let order = market_order(OrderSide::Ask, Decimal::from(3), Decimal::from(0), 4);
+    let mut result = orderbook.process_order(order.clone(), 4).unwrap();
+    assert_eq!(result.trades.len(), 3);
+    assert!(result
+        .trades
+        .iter()
+        .all(|trade| trade.taker.status == OrderStatus::CLOSED));
+
+    // Reordered trades get the status applied all the same
+    result.trades.reverse();
+    for trade in &mut result.trades {
+        trade.taker.status = OrderStatus::OPEN;
+    }
+    let taker = &result.modified_orders[&order.id];
+    orderbook.change_status_of_order_in_trade(taker, &mut result.trades);
+    assert!(result
+        .trades
+        .iter()
+        .all(|trade| trade.taker.status == OrderStatus::CLOSED));
+}
+
+#[test]
+pub fn test_protected_first_trade_without_reference_price() {
+    let pair = test_pair();
+    for policy in [
+        NoReferencePolicy::Reject,
+        NoReferencePolicy::AnchorToFirstFill,
+    ] {
+        let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+        let settings = orderbook.pair_settings_mut(pair);
+        settings.reference_price_ttl = Some(1_000);
+        settings.no_reference_policy = policy;
+        for (ts, price) in [(1, 3), (2, 2)] {
+            let bid = limit_order(
+                &account(1),
+                OrderSide::Bid,
+                Decimal::from(price),
+                Decimal::from(1),
+                ts,
+            );
+            orderbook.process_order(bid, ts as u64).unwrap();
+        }
+
+        // Sells 2, no lower than 1, as the first trade of the pair
+        let mut order = market_order(OrderSide::Ask, Decimal::from(2), Decimal::from(0), 3);
+        order.price = Decimal::from(1);
+        let result = orderbook.process_order_at(order, 3, 5_000);
+        match policy {
+            NoReferencePolicy::Reject => {
+                let err = result.unwrap_err();
+                assert!(matches!(
+                    err.downcast_ref::<Error>(),
+                    Some(Error::StaleReferencePrice)
+                ));
+                assert_eq!(orderbook.reference_price(&pair), None);
+            }
+            NoReferencePolicy::AnchorToFirstFill => {
+                assert_eq!(result.unwrap().trades.len(), 2);
+                // The best bid filled first and anchors the reference price
+                assert_eq!(
+                    orderbook.reference_price(&pair),
+                    Some((Decimal::from(3), 5_000))
+                );
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_market_buy_by_qty_reserves_quote() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::from(0), Decimal::from(0));
+    }
+    orderbook.pair_settings_mut(pair).market_buy_reserve_buffer = Decimal::from(10);
+    for (ts, price) in [(1, 10), (2, 11)] {
+        let ask = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+
+    // Taking 1.5 costs 10 + 5.5, the reserve adds the 10% buffer on top
+    let order = market_order(OrderSide::Bid, Decimal::new(15, 1), Decimal::from(0), 3);
+    let accepted = orderbook.accept_order(order, 3).unwrap();
+    let reserve = Decimal::new(1705, 2);
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(1000) - reserve, reserve)
+    );
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.base)],
+        (Decimal::from(1000), Decimal::from(0))
+    );
+
+    let result = orderbook.match_accepted(accepted).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    // The unspent part of the estimate is refunded
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::new(9845, 1), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::new(10015, 1), Decimal::from(0))
+    );
+}
+
+#[test]
+pub fn test_quote_budget_residual_is_less_than_a_step_at_the_last_level() {
+    let pair = test_pair();
+    let step = Decimal::new(1, 1);
+    let budget = Decimal::from(100);
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    for main in [account(1), account(2)] {
+        orderbook.update_fee_structure(&main, Decimal::from(0), Decimal::from(0));
+    }
+    orderbook
+        .trading_pairs
+        .get_mut(&pair)
+        .unwrap()
+        .qty_step_size = step;
+    for (ts, price, qty) in [(1, 7, 1), (2, 8, 20)] {
+        let ask = limit_order(
+            &account(1),
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+    let order = market_order(OrderSide::Bid, Decimal::from(0), budget, 3);
+    let result = orderbook.process_order(order, 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    // 7 is spent on the first level, the 93 left buys 11.6 at 8 and 0.2 is left over
+    assert_eq!(result.trades[1].amount, Decimal::new(116, 1));
+    let spent: Decimal = result
+        .trades
+        .iter()
+        .map(|trade| trade.price * trade.amount)
+        .sum();
+    let residual = budget - spent;
+    assert_eq!(residual, Decimal::new(2, 1));
+    assert!(residual < result.trades[1].price * step);
+}
+
+#[test]
+pub fn test_quote_budget_buy_is_rounded_down_to_whole_steps() {
+    let pair = test_pair();
+    let step = Decimal::new(1, 2);
+    let price = Decimal::from(3);
+    let mut orderbook = funded_orderbook(&[account(1), account(2)]);
+    for main in [account(1), account(2)] {
+        orderbook.update_fee_structure(&main, Decimal::from(0), Decimal::from(0));
+    }
+    orderbook
+        .trading_pairs
+        .get_mut(&pair)
+        .unwrap()
+        .qty_step_size = step;
+    let ask = limit_order(&account(1), OrderSide::Ask, price, Decimal::from(10), 1);
+    orderbook.process_order(ask, 1).unwrap();
+    let order = market_order(OrderSide::Bid, Decimal::from(0), Decimal::from(10), 2);
+    let result = orderbook.process_order(order, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    // 10 / 3 buys 3.33, not the 3.33333333 that is not a multiple of the step
+    assert_eq!(result.trades[0].amount, Decimal::new(333, 2));
+}
+
+#[test]
+pub fn test_market_buy_by_qty_never_spends_beyond_its_reserve() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::from(0), Decimal::from(0));
+    }
+    orderbook.pair_settings_mut(pair).cancel_on_self_trade = true;
+    // The estimate counts bob's own ask at 10, which is cancelled instead of matched
+    for (ts, main, price) in [(1, &bob, 10), (2, &alice, 20)] {
+        let ask = limit_order(
+            main,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+
+    // Reserves 10 and buys only the 0.5 at 20 they afford
+    let order = market_order(OrderSide::Bid, Decimal::from(1), Decimal::from(0), 3);
+    let result = orderbook.process_order(order, 3).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].amount, Decimal::new(5, 1));
+    assert!(result.trades[0].taker.quote_order_qty.is_zero());
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(990), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::new(10005, 1), Decimal::from(0))
+    );
+}
@@ -0,0 +1,73 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::{MatchDecision, SkipReason};
+use orderbook_primitives::types::{OrderSide, OrderStatus, OrderType};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_can_match_decisions() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let maker = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+
+    let crossing = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, 2);
+    assert_eq!(
+        orderbook.can_match(&crossing, &maker, 2),
+        MatchDecision::Yes
+    );
+    let below = limit_order(&bob, OrderSide::Bid, Decimal::from(9), Decimal::ONE, 2);
+    assert_eq!(orderbook.can_match(&below, &maker, 2), MatchDecision::No);
+    // Market takers match unless their protection price is crossed
+    let mut market = limit_order(&bob, OrderSide::Bid, Decimal::ZERO, Decimal::ONE, 2);
+    market.order_type = OrderType::MARKET;
+    assert_eq!(orderbook.can_match(&market, &maker, 2), MatchDecision::Yes);
+    market.price = Decimal::from(9);
+    assert_eq!(orderbook.can_match(&market, &maker, 2), MatchDecision::No);
+
+    // Own makers are only skipped if the pair cancels self trades
+    let own = limit_order(&alice, OrderSide::Bid, Decimal::from(10), Decimal::ONE, 2);
+    assert_eq!(orderbook.can_match(&own, &maker, 2), MatchDecision::Yes);
+    orderbook.pair_settings_mut(pair).cancel_on_self_trade = true;
+    assert_eq!(
+        orderbook.can_match(&own, &maker, 2),
+        MatchDecision::SkipMaker(SkipReason::SelfTrade)
+    );
+    let own_below = limit_order(&alice, OrderSide::Bid, Decimal::from(9), Decimal::ONE, 2);
+    assert_eq!(
+        orderbook.can_match(&own_below, &maker, 2),
+        MatchDecision::No
+    );
+
+    // Expired makers are skipped whatever the price
+    orderbook.pair_settings_mut(pair).order_ttl = Some(100);
+    assert_eq!(
+        orderbook.can_match(&crossing, &maker, 101),
+        MatchDecision::Yes
+    );
+    assert_eq!(
+        orderbook.can_match(&below, &maker, 102),
+        MatchDecision::SkipMaker(SkipReason::Expired)
+    );
+}
+
+#[test]
+pub fn test_matching_cancels_skipped_makers() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).cancel_on_self_trade = true;
+    let own = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+    let other = limit_order(&bob, OrderSide::Ask, Decimal::from(11), Decimal::ONE, 2);
+    orderbook.process_order(own.clone(), 1).unwrap();
+    orderbook.process_order(other.clone(), 2).unwrap();
+
+    let taker = limit_order(&alice, OrderSide::Bid, Decimal::from(11), Decimal::ONE, 3);
+    assert!(orderbook.will_match(&taker));
+    let result = orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].maker.id, other.id);
+    assert_eq!(
+        result.modified_orders[&own.id].status,
+        OrderStatus::CANCELLED
+    );
+}
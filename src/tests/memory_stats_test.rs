@@ -0,0 +1,31 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_memory_stats_counts_entries() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(1, 3));
+    let empty = orderbook.memory_stats();
+    assert_eq!(empty.resting_orders, 0);
+    assert_eq!(empty.price_levels, 0);
+    assert_eq!(empty.balance_entries, 4);
+    assert_eq!(empty.fee_structure_entries, 1);
+
+    // Two asks at one level and a bid at another
+    for (ts, side, price) in [
+        (1, OrderSide::Ask, 10),
+        (2, OrderSide::Ask, 10),
+        (3, OrderSide::Bid, 9),
+    ] {
+        let order = limit_order(&alice, side, Decimal::from(price), Decimal::from(1), ts);
+        orderbook.process_order(order, ts as u64).unwrap();
+    }
+    let stats = orderbook.memory_stats();
+    assert_eq!(stats.resting_orders, 3);
+    assert_eq!(stats.price_levels, 2);
+    assert_eq!(stats.balance_entries, 4);
+    assert_eq!(stats.fee_structure_entries, 1);
+    assert!(stats.estimated_bytes > empty.estimated_bytes);
+}
@@ -0,0 +1,35 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_merged_result_equals_sequential_application() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let mut balances = orderbook.balances.clone();
+    let mut pricelevels = orderbook.pricelevels.clone();
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(10),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(2), Decimal::from(4), 2);
+    let mut merged = orderbook.process_order(maker.clone(), 1).unwrap();
+    let second = orderbook.process_order(taker, 2).unwrap();
+    merged.merge(second);
+
+    assert_eq!(merged.stid, 2);
+    assert_eq!(merged.trades.len(), 1);
+    // The later state of the maker wins
+    assert_eq!(merged.modified_orders[&maker.id].status, OrderStatus::OPEN);
+    assert_eq!(
+        merged.modified_orders[&maker.id].filled_quantity,
+        Decimal::from(4)
+    );
+    balances.extend(merged.balances.clone());
+    pricelevels.extend(merged.pricelevels.clone());
+    assert_eq!(balances, orderbook.balances);
+    assert_eq!(pricelevels, orderbook.pricelevels);
+}
@@ -0,0 +1,102 @@
+use crate::tests::{account, funded_orderbook_with, limit_order, test_pair};
+use crate::{Orderbook, PriceMode};
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::prelude::Zero;
+use rust_decimal::Decimal;
+
+fn midpoint_orderbook() -> Orderbook {
+    let pair = test_pair();
+    let mut config = TradingPairConfig::default(pair.base, pair.quote);
+    config.price_tick_size = Decimal::from(1);
+    let mut orderbook = funded_orderbook_with(config, &[account(1), account(2)]);
+    orderbook.pair_settings_mut(pair).price_mode = PriceMode::Midpoint;
+    for main in [account(1), account(2)] {
+        orderbook.update_fee_structure(&main, Decimal::from(0), Decimal::from(0));
+    }
+    orderbook
+}
+
+#[test]
+pub fn test_midpoint_price_is_rounded_down_for_ask_maker() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = midpoint_orderbook();
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(13), Decimal::from(2), 2);
+    orderbook.process_order(maker, 1).unwrap();
+    let result = orderbook.process_order(taker, 2).unwrap();
+
+    // Midpoint 11.5 is rounded towards the maker price
+    assert_eq!(result.trades.len(), 1);
+    let trade = &result.trades[0];
+    assert_eq!(trade.price, Decimal::from(11));
+    assert!((trade.price % Decimal::from(1)).is_zero());
+    assert_eq!(orderbook.verify_trade(trade), Ok(()));
+
+    // Taker reserved 26 at its limit price and gets everything above 22 back
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(978), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::from(1002), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(1022), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(998), Decimal::from(0))
+    );
+    // The maker's level is reduced at the maker price, not the trade price
+    assert!(orderbook
+        .pricelevels
+        .get(&(pair, OrderSide::Ask, Decimal::from(10)))
+        .is_none_or(|qty| qty.is_zero()));
+}
+
+#[test]
+pub fn test_midpoint_price_is_rounded_up_for_bid_maker() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = midpoint_orderbook();
+    let maker = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(1),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Ask, Decimal::from(7), Decimal::from(1), 2);
+    orderbook.process_order(maker, 1).unwrap();
+    let result = orderbook.process_order(taker, 2).unwrap();
+
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].price, Decimal::from(9));
+    // The bid maker reserved 10 and is refunded the difference to the trade price
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(991), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(1001), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(1009), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::from(999), Decimal::from(0))
+    );
+}
@@ -0,0 +1,157 @@
+use crate::tests::{account, funded_orderbook_with, limit_order, test_pair};
+use crate::{MinVolumePolicy, Orderbook};
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+// Orderbook with a min volume of 5 and fee free accounts
+fn min_volume_orderbook() -> Orderbook {
+    let pair = test_pair();
+    let mut config = TradingPairConfig::default(pair.base, pair.quote);
+    config.min_volume = Decimal::from(5);
+    let mut orderbook = funded_orderbook_with(config, &[account(1), account(2)]);
+    for main in [account(1), account(2)] {
+        orderbook.update_fee_structure(&main, Decimal::from(0), Decimal::from(0));
+    }
+    orderbook
+}
+
+#[test]
+pub fn test_order_at_exactly_min_volume_rests() {
+    let pair = test_pair();
+    let mut orderbook = min_volume_orderbook();
+    let order = limit_order(
+        &account(1),
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(5),
+        1,
+    );
+    let result = orderbook.process_order(order.clone(), 1).unwrap();
+    assert_eq!(result.modified_orders[&order.id].status, OrderStatus::OPEN);
+    assert_eq!(orderbook.ask_books[&pair].len(), 1);
+    assert_eq!(
+        orderbook
+            .pricelevels
+            .get(&(pair, OrderSide::Ask, Decimal::from(1))),
+        Some(&Decimal::from(5))
+    );
+}
+
+#[test]
+pub fn test_residual_at_exactly_min_volume_keeps_resting() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = min_volume_orderbook();
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(10),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(5), 2);
+    orderbook.process_order(maker.clone(), 1).unwrap();
+    let result = orderbook.process_order(taker, 2).unwrap();
+
+    assert_eq!(result.trades.len(), 1);
+    // The maker keeps a residual of exactly the min volume, its reservation and its level
+    assert_eq!(result.modified_orders[&maker.id].status, OrderStatus::OPEN);
+    assert_eq!(orderbook.ask_books[&pair].len(), 1);
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(990), Decimal::from(5))
+    );
+    assert_eq!(
+        orderbook
+            .pricelevels
+            .get(&(pair, OrderSide::Ask, Decimal::from(1))),
+        Some(&Decimal::from(5))
+    );
+}
+
+#[test]
+pub fn test_keep_whole_policy_skips_makers_left_below_min_volume() {
+    let pair = test_pair();
+    let mut orderbook = min_volume_orderbook();
+    orderbook.pair_settings_mut(pair).min_volume_policy = MinVolumePolicy::KeepMakerWhole;
+    let first = limit_order(
+        &account(1),
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(4),
+        1,
+    );
+    let second = limit_order(
+        &account(1),
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(10),
+        2,
+    );
+    orderbook.process_order(first.clone(), 1).unwrap();
+    orderbook.process_order(second.clone(), 2).unwrap();
+
+    // Filling the first maker would leave 1 at a volume of 2
+    let taker = limit_order(
+        &account(2),
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(3),
+        3,
+    );
+    let result = orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].maker.id, second.id);
+    assert_eq!(result.trades[0].amount, Decimal::from(3));
+    let first = orderbook.ask_books[&pair]
+        .iter()
+        .find(|order| order.id == first.id)
+        .unwrap();
+    assert_eq!(first.filled_quantity, Decimal::from(0));
+    assert_eq!(first.status, OrderStatus::OPEN);
+    assert_eq!(orderbook.ask_books[&pair].len(), 2);
+}
+
+#[test]
+pub fn test_keep_whole_policy_cancels_the_crossed_remainder() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = min_volume_orderbook();
+    let settings = orderbook.pair_settings_mut(pair);
+    settings.min_volume_policy = MinVolumePolicy::KeepMakerWhole;
+    settings.max_sweep_levels = Some(1);
+    let kept = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(8),
+        1,
+    );
+    let filled = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(3),
+        2,
+    );
+    orderbook.process_order(kept.clone(), 1).unwrap();
+    orderbook.process_order(filled.clone(), 2).unwrap();
+
+    // Filling the maker at 1 would leave 2 at a volume of 2, the kept maker is no swept level
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(2), Decimal::from(6), 3);
+    let result = orderbook.process_order(taker.clone(), 3).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].maker.id, filled.id);
+    // The remainder of 3 would rest crossing the kept maker, so it is cancelled
+    assert_eq!(
+        result.modified_orders[&taker.id].status,
+        OrderStatus::CANCELLED
+    );
+    assert!(orderbook.bid_books[&pair].is_empty());
+    assert_eq!(orderbook.ask_books[&pair].len(), 1);
+    assert_eq!(
+        orderbook.balances[&(bob, pair.quote)],
+        (Decimal::from(994), Decimal::from(0))
+    );
+}
@@ -1 +1,113 @@
+mod accept_test;
+mod account_trades_test;
+mod auction_test;
+mod audit_test;
+mod avg_price_test;
+mod batch_test;
+mod bbo_test;
+mod bounds_test;
+mod cancel_test;
+mod candles_test;
+mod commit_test;
+mod conversion_test;
+mod depth_test;
+mod dust_test;
+mod emptied_side_test;
+mod encoding_test;
+mod fee_asset_test;
+mod fee_free_test;
+mod fee_receipts_test;
+mod fill_ratio_test;
+mod fill_trace_test;
+mod flat_fee_test;
+mod implied_test;
+mod ladder_test;
+mod latency_test;
+mod market_order_test;
+mod match_decision_test;
+mod memory_stats_test;
+mod merge_test;
+mod midpoint_test;
+mod min_volume_test;
+mod netting_test;
+mod open_orders_test;
+mod pair_config_test;
+mod pot_test;
+mod preview_test;
+mod price_level_cap_test;
+mod priority_boost_test;
+mod published_levels_test;
+mod reclaim_test;
+mod reconcile_test;
+mod replay_test;
+mod reservation_log_test;
+mod reserve_test;
+mod residual_test;
+mod self_trade_test;
+mod settlement_fee_test;
+mod settlement_test;
+mod size_discount_test;
+mod sub_step_test;
+mod sweep_test;
+mod tape_test;
 mod trade_price_test;
+mod ttl_test;
+mod twap_test;
+
+use crate::Orderbook;
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::{Order, OrderSide, OrderType, TradingPair};
+use polkadex_primitives::{AccountId, AssetId};
+use rust_decimal::Decimal;
+use sp_core::H256;
+
+pub fn test_pair() -> TradingPair {
+    TradingPair::from(AssetId::Asset(1), AssetId::Polkadex)
+}
+
+pub fn account(seed: u8) -> AccountId {
+    AccountId::new([seed; 32])
+}
+
+/// Creates a limit order, the timestamp is also used to derive a unique order id.
+pub fn limit_order(
+    main: &AccountId,
+    side: OrderSide,
+    price: Decimal,
+    qty: Decimal,
+    timestamp: i64,
+) -> Order {
+    let mut order = Order::random_order_for_testing(test_pair(), side, OrderType::LIMIT);
+    order.id = H256::repeat_byte(timestamp as u8);
+    order.user = main.clone();
+    order.main_account = main.clone();
+    order.price = price;
+    order.qty = qty;
+    order.quote_order_qty = Decimal::from(0);
+    order.timestamp = timestamp;
+    order
+}
+
+/// Creates an orderbook with the test pair registered and each account funded
+/// with 1000 units of both base and quote asset.
+pub fn funded_orderbook(accounts: &[AccountId]) -> Orderbook {
+    let pair = test_pair();
+    funded_orderbook_with(TradingPairConfig::default(pair.base, pair.quote), accounts)
+}
+
+/// Creates an orderbook with the test pair registered under the given config and each account
+/// funded with 1000 units of both base and quote asset.
+pub fn funded_orderbook_with(config: TradingPairConfig, accounts: &[AccountId]) -> Orderbook {
+    let pair = test_pair();
+    let mut orderbook = Orderbook::new();
+    orderbook.add_trading_pair(config).unwrap();
+    for main in accounts {
+        for asset in [pair.base, pair.quote] {
+            orderbook.balances.insert(
+                (main.clone(), asset),
+                (Decimal::from(1000), Decimal::from(0)),
+            );
+        }
+    }
+    orderbook
+}
@@ -0,0 +1,59 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::{OrderExecutionResult, Orderbook, ReservationReason};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+fn sweep_three_makers(netting: bool) -> (Orderbook, OrderExecutionResult) {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_balance_netting(netting);
+    orderbook.set_reservation_logging(true);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(2, 3));
+    }
+    for ts in 1..=3 {
+        let price = Decimal::from(10 + ts);
+        let maker = limit_order(&alice, OrderSide::Ask, price, Decimal::from(1), ts);
+        orderbook.process_order(maker, ts as u64).unwrap();
+    }
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(13), Decimal::from(3), 4);
+    let result = orderbook.process_order(taker, 4).unwrap();
+    (orderbook, result)
+}
+
+#[test]
+pub fn test_netted_balances_match_per_leg_settlement() {
+    let pair = test_pair();
+    let (netted, netted_result) = sweep_three_makers(true);
+    let (per_leg, per_leg_result) = sweep_three_makers(false);
+    assert_eq!(netted_result.trades.len(), 3);
+    assert_eq!(netted.balances, per_leg.balances);
+    assert_eq!(netted_result.balances, per_leg_result.balances);
+
+    // One net change per account and asset: alice, bob and the pot in base and quote
+    let pot = netted.fees_collector.pot.clone();
+    assert_eq!(netted_result.balances.len(), 6);
+    for main in [account(1), account(2), pot] {
+        for asset in [pair.base, pair.quote] {
+            assert_eq!(
+                netted_result.balances[&(main.clone(), asset)],
+                netted.balances[&(main.clone(), asset)]
+            );
+        }
+    }
+
+    // The reserved balance of bob is written once instead of once per refund and trade
+    let settled = |orderbook: &Orderbook, main| {
+        orderbook
+            .reservation_log()
+            .iter()
+            .filter(|event| {
+                event.account == main
+                    && event.asset == pair.quote
+                    && event.reason == ReservationReason::TradeSettle
+            })
+            .count()
+    };
+    assert_eq!(settled(&netted, account(2)), 1);
+    assert_eq!(settled(&per_leg, account(2)), 5);
+}
@@ -0,0 +1,110 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+use sp_core::H256;
+
+#[test]
+pub fn test_open_orders_are_capped_per_account() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_max_open_orders(Some(2));
+    let ask = |price: i64, ts: i64| {
+        limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        )
+    };
+    orderbook.process_order(ask(10, 1), 1).unwrap();
+    orderbook.process_order(ask(11, 2), 2).unwrap();
+    assert_eq!(orderbook.open_orders_of(&alice), 2);
+    let err = orderbook.process_order(ask(12, 3), 3).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::TooManyOpenOrders)
+    ));
+
+    // Cancelling frees a slot
+    orderbook
+        .cancel_order(pair, OrderSide::Ask, &ask(10, 1).id, 10, 4)
+        .unwrap();
+    assert_eq!(orderbook.open_orders_of(&alice), 1);
+    orderbook.process_order(ask(12, 3), 5).unwrap();
+    assert_eq!(orderbook.open_orders_of(&alice), 2);
+
+    // So does a filled order
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::from(1), 6);
+    orderbook.process_order(taker, 6).unwrap();
+    assert_eq!(orderbook.open_orders_of(&alice), 1);
+    assert_eq!(orderbook.open_orders_of(&bob), 0);
+    orderbook.process_order(ask(13, 7), 7).unwrap();
+}
+
+#[test]
+pub fn test_iter_orders_borrows_the_book() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    assert_eq!(orderbook.iter_orders(&pair, OrderSide::Ask).count(), 0);
+    let orders = [(&alice, 10, 1), (&bob, 11, 2), (&alice, 12, 3)];
+    for (main, price, ts) in orders {
+        let ask = limit_order(main, OrderSide::Ask, Decimal::from(price), Decimal::ONE, ts);
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+
+    let mut prices: Vec<Decimal> = orderbook
+        .iter_orders(&pair, OrderSide::Ask)
+        .filter(|order| order.main_account == alice)
+        .map(|order| order.price)
+        .collect();
+    prices.sort();
+    assert_eq!(prices, vec![Decimal::from(10), Decimal::from(12)]);
+    let resting: Decimal = orderbook
+        .iter_orders(&pair, OrderSide::Ask)
+        .map(|order| order.qty)
+        .sum();
+    assert_eq!(resting, Decimal::from(3));
+    assert_eq!(orderbook.iter_orders(&pair, OrderSide::Bid).count(), 0);
+    // Iterating leaves the book untouched
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(10))
+    );
+}
+
+#[test]
+pub fn test_age_profile_lists_the_oldest_resting_orders_first() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    assert!(orderbook.age_profile(&pair, 10).is_empty());
+    let orders = [
+        (&alice, OrderSide::Ask, 10, 1),
+        (&bob, OrderSide::Bid, 9, 2),
+        (&alice, OrderSide::Ask, 11, 4),
+        (&bob, OrderSide::Bid, 8, 5),
+    ];
+    for (main, side, price, ts) in orders {
+        let mut order = limit_order(main, side, Decimal::from(price), Decimal::ONE, ts);
+        // Ages are measured from the time the engine rested the order, not the client's
+        order.timestamp = 100 - ts;
+        orderbook
+            .process_order_at(order, ts as u64, ts as u64)
+            .unwrap();
+    }
+    // Fills the oldest order, which leaves the book
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, 6);
+    orderbook.process_order_at(taker, 6, 6).unwrap();
+
+    let profile = orderbook.age_profile(&pair, 10);
+    let expected = vec![
+        (H256::repeat_byte(2), 8),
+        (H256::repeat_byte(4), 6),
+        (H256::repeat_byte(5), 5),
+    ];
+    assert_eq!(profile, expected);
+}
@@ -0,0 +1,77 @@
+use crate::error::Error;
+use crate::tests::test_pair;
+use crate::Orderbook;
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::TradingPair;
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_invalid_pair_config_is_rejected() {
+    let pair = test_pair();
+    let mut orderbook = Orderbook::new();
+
+    let mut config = TradingPairConfig::default(pair.base, pair.quote);
+    config.qty_step_size = Decimal::from(0);
+    assert!(matches!(
+        orderbook.add_trading_pair(config),
+        Err(Error::InvalidPairConfig(_))
+    ));
+
+    let mut config = TradingPairConfig::default(pair.base, pair.quote);
+    config.min_volume = config.max_volume + Decimal::from(1);
+    assert!(matches!(
+        orderbook.add_trading_pair(config),
+        Err(Error::InvalidPairConfig(_))
+    ));
+    assert!(orderbook.get_pair_config(&pair).is_none());
+
+    // The qty bounds of the pair must fit at least one qty step
+    let step = TradingPairConfig::default(pair.base, pair.quote).qty_step_size;
+    orderbook.pair_settings_mut(pair).min_qty = Some(step / Decimal::from(2));
+    assert!(matches!(
+        orderbook.add_trading_pair(TradingPairConfig::default(pair.base, pair.quote)),
+        Err(Error::InvalidPairConfig(_))
+    ));
+    orderbook.pair_settings_mut(pair).min_qty = Some(step);
+    orderbook.pair_settings_mut(pair).max_qty = Some(step / Decimal::from(2));
+    assert!(matches!(
+        orderbook.add_trading_pair(TradingPairConfig::default(pair.base, pair.quote)),
+        Err(Error::InvalidPairConfig(_))
+    ));
+    orderbook.pair_settings_mut(pair).max_qty = None;
+    assert!(orderbook.get_pair_config(&pair).is_none());
+
+    let config = TradingPairConfig::default(pair.base, pair.quote);
+    assert!(orderbook.add_trading_pair(config).is_ok());
+    assert!(orderbook.get_pair_config(&pair).is_some());
+}
+
+#[test]
+pub fn test_list_registered_pairs() {
+    let pair = test_pair();
+    let other = TradingPair::from(AssetId::Asset(2), AssetId::Polkadex);
+    let mut orderbook = Orderbook::new();
+    assert!(orderbook.list_pairs().is_empty());
+    assert!(!orderbook.is_registered(&pair));
+
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(pair.base, pair.quote))
+        .unwrap();
+    assert!(orderbook.is_registered(&pair));
+    assert!(!orderbook.is_registered(&other));
+    assert_eq!(orderbook.list_pairs(), vec![pair]);
+
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(other.base, other.quote))
+        .unwrap();
+    let pairs = orderbook.list_pairs();
+    assert_eq!(pairs.len(), 2);
+    assert!(pairs.contains(&pair) && pairs.contains(&other));
+    assert_eq!(
+        orderbook
+            .get_pair_config(&other)
+            .map(|config| config.quote_asset),
+        Some(other.quote)
+    );
+}
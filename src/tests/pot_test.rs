@@ -0,0 +1,47 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_fees_accrue_to_custom_pot() {
+    let (alice, bob, pot) = (account(1), account(2), account(9));
+    let pair = test_pair();
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(1, 3));
+    }
+    let default_pot = orderbook.fees_collector.pot.clone();
+
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(4),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(2), 2);
+    orderbook.process_order(maker, 1).unwrap();
+    orderbook.process_order(taker, 2).unwrap();
+    // Bob paid 0.002 base and alice 0.002 quote to the default pot
+    let accrued = (Decimal::new(2, 3), Decimal::from(0));
+    assert_eq!(
+        orderbook.balances[&(default_pot.clone(), pair.base)],
+        accrued
+    );
+
+    // Accrued fees are migrated to the new pot
+    orderbook.set_pot(pot.clone());
+    assert!(!orderbook
+        .balances
+        .contains_key(&(default_pot.clone(), pair.base)));
+    assert_eq!(orderbook.balances[&(pot.clone(), pair.base)], accrued);
+    assert_eq!(orderbook.balances[&(pot.clone(), pair.quote)], accrued);
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(2), 3);
+    orderbook.process_order(taker, 3).unwrap();
+    assert_eq!(
+        orderbook.balances[&(pot, pair.base)],
+        (Decimal::new(4, 3), Decimal::from(0))
+    );
+    assert!(!orderbook.balances.contains_key(&(default_pot, pair.base)));
+}
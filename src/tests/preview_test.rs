@@ -0,0 +1,195 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::MakerTakerHint;
+use orderbook_primitives::types::{OrderSide, OrderType};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_matching_preview_matches_actual_trades() {
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    let pair = test_pair();
+    let makers = [
+        limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(10),
+            Decimal::from(2),
+            1,
+        ),
+        limit_order(&bob, OrderSide::Ask, Decimal::from(10), Decimal::from(1), 2),
+        limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(11),
+            Decimal::from(3),
+            3,
+        ),
+        limit_order(&bob, OrderSide::Ask, Decimal::from(12), Decimal::from(2), 4),
+    ];
+    for maker in &makers {
+        orderbook.process_order(maker.clone(), 1).unwrap();
+    }
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(11),
+        Decimal::from(5),
+        5,
+    );
+
+    let preview = orderbook.matching_preview(&taker);
+    assert_eq!(
+        preview,
+        vec![
+            (makers[0].id, Decimal::from(10), Decimal::from(2)),
+            (makers[1].id, Decimal::from(10), Decimal::from(1)),
+            (makers[2].id, Decimal::from(11), Decimal::from(2)),
+        ]
+    );
+    // The preview does not touch the book
+    assert_eq!(orderbook.ask_books[&pair].len(), 4);
+
+    let result = orderbook.process_order(taker, 2).unwrap();
+    let trades: Vec<_> = result
+        .trades
+        .iter()
+        .map(|trade| (trade.maker.id, trade.price, trade.amount))
+        .collect();
+    assert_eq!(preview, trades);
+}
+
+#[test]
+pub fn test_matching_preview_selects_makers_as_matching() {
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    let settings = orderbook.pair_settings_mut(test_pair());
+    settings.cancel_on_self_trade = true;
+    settings.max_sweep_levels = Some(1);
+    let own = limit_order(&carol, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+    let other = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 2);
+    let next_level = limit_order(&bob, OrderSide::Ask, Decimal::from(11), Decimal::ONE, 3);
+    for maker in [&own, &other, &next_level] {
+        orderbook.process_order(maker.clone(), 1).unwrap();
+    }
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(11),
+        Decimal::from(3),
+        4,
+    );
+
+    // The own maker is skipped and matching stops after the first level
+    let preview = orderbook.matching_preview(&taker);
+    assert_eq!(preview, vec![(other.id, Decimal::from(10), Decimal::ONE)]);
+
+    let result = orderbook.process_order(taker, 2).unwrap();
+    let trades: Vec<_> = result
+        .trades
+        .iter()
+        .map(|trade| (trade.maker.id, trade.price, trade.amount))
+        .collect();
+    assert_eq!(preview, trades);
+}
+
+#[test]
+pub fn test_maker_classification() {
+    let (alice, carol) = (account(1), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), carol.clone()]);
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(ask, 1).unwrap();
+
+    let maker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(9),
+        Decimal::from(2),
+        2,
+    );
+    assert_eq!(
+        orderbook.maker_classification(&maker),
+        MakerTakerHint::PureMaker
+    );
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(2),
+        3,
+    );
+    assert_eq!(
+        orderbook.maker_classification(&taker),
+        MakerTakerHint::PureTaker
+    );
+    let mixed = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(3),
+        4,
+    );
+    assert_eq!(
+        orderbook.maker_classification(&mixed),
+        MakerTakerHint::Mixed
+    );
+    let mut market = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(0),
+        Decimal::from(0),
+        5,
+    );
+    market.order_type = OrderType::MARKET;
+    market.quote_order_qty = Decimal::from(10);
+    assert_eq!(
+        orderbook.maker_classification(&market),
+        MakerTakerHint::PureTaker
+    );
+}
+
+#[test]
+pub fn test_net_proceeds_match_settled_balances() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::new(1, 3), Decimal::new(25, 4));
+    }
+    let fills = vec![
+        (Decimal::from(10), Decimal::from(1)),
+        (Decimal::from(11), Decimal::from(2)),
+    ];
+    let expected_taker = orderbook.net_proceeds(&bob, OrderSide::Bid, pair, fills.clone(), false);
+    let expected_maker = orderbook.net_proceeds(&alice, OrderSide::Ask, pair, fills, true);
+
+    for (ts, (price, qty)) in [(10, 1), (11, 2)].into_iter().enumerate() {
+        let ts = ts as i64 + 1;
+        let maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook.process_order(maker, ts as u64).unwrap();
+    }
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::from(3), 3);
+    orderbook.process_order(taker, 3).unwrap();
+
+    assert_eq!(expected_taker.0, pair.base);
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)].0,
+        Decimal::from(1000) + expected_taker.1
+    );
+    assert_eq!(expected_maker.0, pair.quote);
+    assert_eq!(
+        orderbook.balances[&(alice, pair.quote)].0,
+        Decimal::from(1000) + expected_maker.1
+    );
+}
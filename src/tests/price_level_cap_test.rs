@@ -0,0 +1,109 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_new_price_level_beyond_cap_is_rejected() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).max_price_levels = Some(2);
+    let ask = |price: i64, ts: i64| {
+        limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        )
+    };
+    orderbook.process_order(ask(10, 1), 1).unwrap();
+    orderbook.process_order(ask(11, 2), 2).unwrap();
+
+    let err = orderbook.process_order(ask(12, 3), 3).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::TooManyPriceLevels)
+    ));
+    // Nothing was reserved for the rejected order
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(998), Decimal::from(2))
+    );
+
+    // Existing levels still grow
+    orderbook.process_order(ask(11, 4), 4).unwrap();
+    assert_eq!(
+        orderbook.book_levels(&pair, OrderSide::Ask),
+        vec![
+            (Decimal::from(10), Decimal::from(1)),
+            (Decimal::from(11), Decimal::from(2))
+        ]
+    );
+
+    // A taker at a new price that fills completely opens no level
+    let bid = limit_order(
+        &bob,
+        OrderSide::Bid,
+        Decimal::new(105, 1),
+        Decimal::from(1),
+        5,
+    );
+    let result = orderbook.process_order(bid, 5).unwrap();
+    assert_eq!(result.trades.len(), 1);
+}
+
+#[test]
+pub fn test_price_level_cap_counts_levels_after_the_trades() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).max_price_levels = Some(2);
+    for (price, ts) in [(10, 1), (11, 2)] {
+        let ask = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::ONE,
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+
+    // Taking the level at 10 frees the level the remainder rests at
+    let bid = limit_order(
+        &bob,
+        OrderSide::Bid,
+        Decimal::new(105, 1),
+        Decimal::from(2),
+        3,
+    );
+    let result = orderbook.process_order(bid.clone(), 3).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.modified_orders[&bid.id].status, OrderStatus::OPEN);
+    assert_eq!(
+        orderbook.book_levels(&pair, OrderSide::Bid),
+        vec![(Decimal::new(105, 1), Decimal::ONE)]
+    );
+}
+
+#[test]
+pub fn test_price_level_cap_applies_to_auction_only_pairs() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let settings = orderbook.pair_settings_mut(pair);
+    settings.max_price_levels = Some(1);
+    settings.auction_only = true;
+    let ask = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+    orderbook.process_order(ask, 1).unwrap();
+
+    // Crossing orders rest until the auction, so they open a level too
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::ONE, 2);
+    let err = orderbook.process_order(bid, 2).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::TooManyPriceLevels)
+    ));
+}
@@ -0,0 +1,55 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_boosted_maker_fills_before_earlier_maker_at_same_price() {
+    let (alice, bob, carol) = (account(1), account(2), account(3));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone(), carol.clone()]);
+    orderbook.update_priority_boost(&bob, 1);
+
+    let early = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(5),
+        1,
+    );
+    let boosted = limit_order(&bob, OrderSide::Ask, Decimal::from(10), Decimal::from(5), 2);
+    let cheaper = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(9),
+        Decimal::from(1),
+        3,
+    );
+    orderbook.process_order(early.clone(), 1).unwrap();
+    orderbook.process_order(boosted.clone(), 2).unwrap();
+    orderbook.process_order(cheaper.clone(), 3).unwrap();
+
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(6),
+        4,
+    );
+    let result = orderbook.process_order(taker, 4).unwrap();
+    // Boost never overrides price priority
+    assert_eq!(result.trades.len(), 2);
+    assert_eq!(result.trades[0].maker.id, cheaper.id);
+    assert_eq!(result.trades[1].maker.id, boosted.id);
+
+    // The earlier non boosted maker is still resting untouched
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(5),
+        5,
+    );
+    let result = orderbook.process_order(taker, 5).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].maker.id, early.id);
+    assert_eq!(result.trades[0].amount, Decimal::from(5));
+}
@@ -0,0 +1,81 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_published_levels_are_capped() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_max_published_levels(Some(2));
+    for ts in 1..=4 {
+        let maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(9 + ts),
+            Decimal::from(1),
+            ts,
+        );
+        let result = orderbook.process_order(maker, ts as u64).unwrap();
+        assert!(!result.resync_required());
+    }
+
+    // Sweeping four levels and resting the remainder touches five levels
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(13), Decimal::from(5), 5);
+    let result = orderbook.process_order(taker, 5).unwrap();
+    assert_eq!(result.trades.len(), 4);
+    assert_eq!(result.pricelevels.len(), 2);
+    assert!(result.resync_required());
+
+    // Without a cap every level is published
+    orderbook.set_max_published_levels(None);
+    let taker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(13),
+        Decimal::from(1),
+        6,
+    );
+    let result = orderbook.process_order(taker, 6).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert!(!result.resync_required());
+}
+
+#[test]
+pub fn test_levels_are_published_at_display_tick() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    orderbook.pair_settings_mut(pair).display_tick_size = Some(Decimal::from(1));
+    let first = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::new(102, 1),
+        Decimal::from(1),
+        1,
+    );
+    let second = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::new(107, 1),
+        Decimal::from(2),
+        2,
+    );
+    orderbook.process_order(first, 1).unwrap();
+    let result = orderbook.process_order(second, 2).unwrap();
+
+    // Both levels fall in the display level at 10
+    assert_eq!(result.pricelevels.len(), 1);
+    assert_eq!(
+        result.pricelevels[&(pair, OrderSide::Bid, Decimal::from(10))],
+        Decimal::from(3)
+    );
+    // Internally the levels keep their prices
+    assert_eq!(
+        orderbook.pricelevels[&(pair, OrderSide::Bid, Decimal::new(102, 1))],
+        Decimal::from(1)
+    );
+    assert_eq!(
+        orderbook.pricelevels[&(pair, OrderSide::Bid, Decimal::new(107, 1))],
+        Decimal::from(2)
+    );
+}
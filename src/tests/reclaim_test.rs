@@ -0,0 +1,92 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+#[test]
+pub fn test_unbacked_reservation_is_reclaimed() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    let resting = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(resting, 1).unwrap();
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(980), Decimal::from(20))
+    );
+
+    // Nothing to reclaim while every reservation is backed
+    let changes = orderbook.reclaim_market_order_reservations(&alice).unwrap();
+    assert!(changes.balances.is_empty());
+
+    // Leave an unbacked reservation behind
+    orderbook.balances.insert(
+        (alice.clone(), pair.quote),
+        (Decimal::from(975), Decimal::from(25)),
+    );
+    orderbook.balances.insert(
+        (alice.clone(), pair.base),
+        (Decimal::from(997), Decimal::from(3)),
+    );
+    let changes = orderbook.reclaim_market_order_reservations(&alice).unwrap();
+    assert_eq!(changes.balances.len(), 2);
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(980), Decimal::from(20))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(1000), Decimal::from(0))
+    );
+}
+
+#[test]
+pub fn test_reserved_settlement_fee_of_resting_order_survives_reclaim() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    let fee_asset = AssetId::Asset(2);
+    let settings = orderbook.pair_settings_mut(pair);
+    settings.settlement_fee = Decimal::from(1);
+    settings.settlement_fee_asset = Some(fee_asset);
+    orderbook.balances.insert(
+        (alice.clone(), fee_asset),
+        (Decimal::from(10), Decimal::from(0)),
+    );
+    let resting = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(resting.clone(), 1).unwrap();
+    assert_eq!(
+        orderbook.backed_reservations(&alice),
+        BTreeMap::from([(pair.quote, Decimal::from(20)), (fee_asset, Decimal::ONE)])
+    );
+
+    // The fee reserved on top of the resting order is backed and kept
+    let changes = orderbook.reclaim_market_order_reservations(&alice).unwrap();
+    assert!(changes.balances.is_empty());
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), fee_asset)],
+        (Decimal::from(9), Decimal::from(1))
+    );
+
+    // Cancelling the order releases the fee it still holds
+    orderbook
+        .cancel_order(pair, OrderSide::Bid, &resting.id, 1, 2)
+        .unwrap();
+    assert_eq!(
+        orderbook.balances[&(alice, fee_asset)],
+        (Decimal::from(10), Decimal::from(0))
+    );
+}
@@ -0,0 +1,65 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+#[test]
+pub fn test_reconcile_cancels_worst_priced_orders() {
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    let pair = test_pair();
+    let bids = [
+        limit_order(
+            &alice,
+            OrderSide::Bid,
+            Decimal::from(10),
+            Decimal::from(30),
+            1,
+        ),
+        limit_order(
+            &alice,
+            OrderSide::Bid,
+            Decimal::from(8),
+            Decimal::from(30),
+            2,
+        ),
+        limit_order(
+            &alice,
+            OrderSide::Bid,
+            Decimal::from(9),
+            Decimal::from(30),
+            3,
+        ),
+    ];
+    for bid in &bids {
+        orderbook.process_order(bid.clone(), 1).unwrap();
+    }
+    // 300 + 240 + 270 quote is reserved
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(190), Decimal::from(810))
+    );
+
+    // Alice was slashed down to 400 quote, the bids at 8 and 9 must go
+    let actual = BTreeMap::from([(pair.quote, Decimal::from(400))]);
+    let changes = orderbook.reconcile_account(&alice, actual).unwrap();
+    assert_eq!(changes.modified_orders.len(), 2);
+    assert_eq!(
+        changes.modified_orders[&bids[1].id].status,
+        OrderStatus::CANCELLED
+    );
+    assert_eq!(
+        changes.modified_orders[&bids[2].id].status,
+        OrderStatus::CANCELLED
+    );
+    assert_eq!(orderbook.bid_books[&pair].len(), 1);
+    assert_eq!(orderbook.bid_books[&pair].peek().unwrap().id, bids[0].id);
+    assert_eq!(
+        orderbook.balances[&(alice, pair.quote)],
+        (Decimal::from(100), Decimal::from(300))
+    );
+    assert_eq!(
+        changes.pricelevels[&(pair, OrderSide::Bid, Decimal::from(8))],
+        Decimal::from(0)
+    );
+}
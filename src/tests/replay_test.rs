@@ -0,0 +1,74 @@
+use crate::tests::{account, limit_order, test_pair};
+use crate::Orderbook;
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+#[test]
+pub fn test_replay_order_stream() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut balances = BTreeMap::new();
+    for main in [&alice, &bob] {
+        for asset in [pair.base, pair.quote] {
+            balances.insert(
+                (main.clone(), asset),
+                (Decimal::from(100), Decimal::from(0)),
+            );
+        }
+    }
+    let stream = vec![
+        (
+            limit_order(
+                &alice,
+                OrderSide::Ask,
+                Decimal::from(2),
+                Decimal::from(5),
+                1,
+            ),
+            1,
+        ),
+        (
+            limit_order(
+                &alice,
+                OrderSide::Ask,
+                Decimal::from(3),
+                Decimal::from(5),
+                2,
+            ),
+            2,
+        ),
+        (
+            limit_order(&bob, OrderSide::Bid, Decimal::from(2), Decimal::from(3), 3),
+            3,
+        ),
+    ];
+    let configs = vec![TradingPairConfig::default(pair.base, pair.quote)];
+    let (orderbook, results) =
+        Orderbook::replay(configs.clone(), balances.clone(), stream.clone()).unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[2].trades.len(), 1);
+    assert_eq!(
+        orderbook.book_levels(&pair, OrderSide::Ask),
+        vec![
+            (Decimal::from(2), Decimal::from(2)),
+            (Decimal::from(3), Decimal::from(5))
+        ]
+    );
+    assert!(orderbook.book_levels(&pair, OrderSide::Bid).is_empty());
+    // Alice keeps 7 base reserved for the resting asks
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(90), Decimal::from(7))
+    );
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(94), Decimal::from(0))
+    );
+
+    // Replaying the same stream again reaches the same state
+    let (again, _) = Orderbook::replay(configs, balances, stream).unwrap();
+    assert_eq!(again.state_root(), orderbook.state_root());
+}
@@ -0,0 +1,89 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::ReservationReason;
+use orderbook_primitives::types::{OrderSide, OrderType};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_reservation_log_nets_to_reserved_balance_change() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_reservation_logging(true);
+
+    // Bob rests a bid reserving 8 and is partially filled for 2
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(2), Decimal::from(4), 1);
+    orderbook.process_order(bid, 1).unwrap();
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(1),
+        2,
+    );
+    orderbook.process_order(ask, 2).unwrap();
+    // A market buy with a budget of 10 spends 2 and is refunded the rest
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(3),
+        Decimal::from(1),
+        3,
+    );
+    orderbook.process_order(ask, 3).unwrap();
+    let mut market = limit_order(&bob, OrderSide::Bid, Decimal::from(0), Decimal::from(0), 4);
+    market.order_type = OrderType::MARKET;
+    market.quote_order_qty = Decimal::from(10);
+    orderbook.process_order(market, 4).unwrap();
+
+    let bob_quote: Vec<_> = orderbook
+        .reservation_log()
+        .iter()
+        .filter(|event| event.account == bob && event.asset == pair.quote)
+        .collect();
+    let net: Decimal = bob_quote.iter().map(|event| event.delta).sum();
+    assert_eq!(net, orderbook.balances[&(bob.clone(), pair.quote)].1);
+    assert_eq!(net, Decimal::from(6));
+    let reasons: Vec<_> = bob_quote.iter().map(|event| event.reason).collect();
+    assert_eq!(
+        reasons,
+        vec![
+            ReservationReason::OrderEntry,
+            ReservationReason::TradeSettle,
+            ReservationReason::OrderEntry,
+            ReservationReason::TradeSettle,
+            ReservationReason::MarketRefund,
+        ]
+    );
+
+    orderbook.clear_reservation_log();
+    assert!(orderbook.reservation_log().is_empty());
+}
+
+#[test]
+pub fn test_reservation_log_covers_underprice_refund() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_reservation_logging(true);
+
+    // Bob's bid at 3 reserves 6 and half of it trades at 2, the difference is unreserved
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(1),
+        1,
+    );
+    orderbook.process_order(ask, 1).unwrap();
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(3), Decimal::from(2), 2);
+    orderbook.process_order(bid, 2).unwrap();
+
+    let net: Decimal = orderbook
+        .reservation_log()
+        .iter()
+        .filter(|event| event.account == bob && event.asset == pair.quote)
+        .map(|event| event.delta)
+        .sum();
+    assert_eq!(net, orderbook.balances[&(bob.clone(), pair.quote)].1);
+    assert_eq!(net, Decimal::from(3));
+}
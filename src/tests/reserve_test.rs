@@ -0,0 +1,203 @@
+use crate::error::Error;
+use crate::tests::{account, funded_orderbook, funded_orderbook_with, limit_order, test_pair};
+use crate::Orderbook;
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::{Order, OrderSide};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_reserve_rejects_insufficient_balance() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    // Needs 2000 quote with only 1000 free
+    let order = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(1000),
+        1,
+    );
+    let err = orderbook.process_order(order, 1).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InsufficientBalance)
+    ));
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(1000), Decimal::from(0))
+    );
+
+    // Accounts without any balance lack funds as well
+    let order = limit_order(
+        &account(2),
+        OrderSide::Ask,
+        Decimal::from(2),
+        Decimal::from(1),
+        2,
+    );
+    let err = orderbook.process_order(order, 2).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InsufficientBalance)
+    ));
+}
+
+#[test]
+pub fn test_reserve_detects_corrupted_balance() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    orderbook.balances.insert(
+        (alice.clone(), pair.quote),
+        (Decimal::from(1000), Decimal::from(-1)),
+    );
+    let order = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(1),
+        1,
+    );
+    let err = orderbook.process_order(order, 1).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::BalanceCorruption)
+    ));
+}
+
+#[test]
+pub fn test_held_balance_can_not_be_reserved() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    orderbook.hold(&alice, pair.quote, Decimal::from(600));
+    // Needs 500 quote with only 400 available
+    let order = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(250),
+        1,
+    );
+    let err = orderbook.process_order(order.clone(), 1).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::InsufficientBalance)
+    ));
+
+    let fitting = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(2),
+        Decimal::from(200),
+        2,
+    );
+    orderbook.process_order(fitting, 2).unwrap();
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(600), Decimal::from(400))
+    );
+
+    // Released funds can be reserved again
+    orderbook.release(&alice, pair.quote, Decimal::from(600));
+    assert_eq!(orderbook.held_balance(&alice, pair.quote), Decimal::from(0));
+    orderbook.process_order(order, 3).unwrap();
+}
+
+#[test]
+pub fn test_closed_order_leaves_no_reserved_residual() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut config = TradingPairConfig::default(pair.base, pair.quote);
+    config.price_tick_size = Decimal::new(1, 8);
+    config.qty_step_size = Decimal::new(1, 2);
+    let mut orderbook = funded_orderbook_with(config, &[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::ZERO, Decimal::ZERO);
+    }
+    let pot = orderbook.fees_collector.pot.clone();
+    let price = Decimal::new(100000001, 8);
+    let half = Decimal::new(155, 2);
+    // The bid reserves its volume rounded once, the two fills spend their volumes rounded each
+    let reserved = Order::rounding_off(price * half * Decimal::TWO);
+    let spent = Order::rounding_off(price * half) * Decimal::TWO;
+    let residual = reserved - spent;
+    assert!(residual > Decimal::ZERO);
+
+    // Fills a resting bid of alice in two halves
+    let fill_bid = |orderbook: &mut Orderbook, ts: i64| {
+        let bid = limit_order(&alice, OrderSide::Bid, price, half * Decimal::TWO, ts);
+        orderbook.process_order(bid, ts as u64).unwrap();
+        for fill in 1..=2 {
+            let ask = limit_order(&bob, OrderSide::Ask, price, half, ts + fill);
+            orderbook.process_order(ask, (ts + fill) as u64).unwrap();
+        }
+    };
+
+    fill_bid(&mut orderbook, 1);
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (Decimal::from(1000) - spent, Decimal::ZERO)
+    );
+    assert!(orderbook.order_reservations.is_empty());
+
+    // Swept to the dust account, the fee pot by default
+    orderbook.pair_settings_mut(pair).sweep_reserve_residuals = true;
+    fill_bid(&mut orderbook, 4);
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.quote)],
+        (
+            Decimal::from(1000) - spent * Decimal::TWO - residual,
+            Decimal::ZERO
+        )
+    );
+    assert_eq!(orderbook.balances[&(pot, pair.quote)].0, residual);
+}
+
+#[test]
+pub fn test_partially_filled_bid_reserves_its_resting_part_once() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::ZERO, Decimal::ZERO);
+    }
+    orderbook.set_reservation_logging(true);
+    let ask = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+    orderbook.process_order(ask, 1).unwrap();
+
+    // Reserves 3 * 12, fills 1 at 10 and refunds the 2 it saved, the rest rests at 12
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(12), Decimal::from(3), 2);
+    let result = orderbook.process_order(bid, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(966), Decimal::from(24))
+    );
+    let net: Decimal = orderbook
+        .reservation_log()
+        .iter()
+        .filter(|event| event.account == bob && event.asset == pair.quote)
+        .map(|event| event.delta)
+        .sum();
+    assert_eq!(net, Decimal::from(24));
+
+    // As a maker at its own price the resting part spends exactly its reservation
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(12),
+        Decimal::from(2),
+        3,
+    );
+    orderbook.process_order(ask, 3).unwrap();
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(966), Decimal::ZERO)
+    );
+    assert_eq!(
+        orderbook.balances[&(bob, pair.base)],
+        (Decimal::from(1003), Decimal::ZERO)
+    );
+}
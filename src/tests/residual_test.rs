@@ -0,0 +1,64 @@
+use crate::tests::{account, funded_orderbook_with, limit_order, test_pair};
+use crate::{Orderbook, ResidualPolicy};
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+fn settle_sub_min_residual(policy: ResidualPolicy) -> Orderbook {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut config = TradingPairConfig::default(pair.base, pair.quote);
+    config.min_volume = Decimal::from(5);
+    let mut orderbook = funded_orderbook_with(config, &[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).residual_policy = policy;
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::from(0), Decimal::from(0));
+    }
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(10),
+        1,
+    );
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(7), 2);
+    orderbook.process_order(maker, 1).unwrap();
+    // Maker is left with a residual volume of 3 which is below min volume
+    let result = orderbook.process_order(taker, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    orderbook
+}
+
+#[test]
+pub fn test_sub_min_residual_is_returned_by_default() {
+    let orderbook = settle_sub_min_residual(ResidualPolicy::ReturnResidual);
+    let pair = test_pair();
+    assert_eq!(
+        orderbook.balances.get(&(account(1), pair.base)).unwrap(),
+        &(Decimal::from(993), Decimal::from(0))
+    );
+    let pot_base = orderbook
+        .balances
+        .get(&(orderbook.fees_collector.pot.clone(), pair.base))
+        .map(|(free, _)| *free)
+        .unwrap_or_default();
+    assert_eq!(pot_base, Decimal::from(0));
+}
+
+#[test]
+pub fn test_sub_min_residual_is_donated_to_pot() {
+    let orderbook = settle_sub_min_residual(ResidualPolicy::DonateResidualToPot);
+    let pair = test_pair();
+    assert_eq!(
+        orderbook.balances.get(&(account(1), pair.base)).unwrap(),
+        &(Decimal::from(990), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook
+            .balances
+            .get(&(orderbook.fees_collector.pot.clone(), pair.base))
+            .unwrap()
+            .0,
+        Decimal::from(3)
+    );
+}
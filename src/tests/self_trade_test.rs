@@ -0,0 +1,50 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_cancel_on_self_trade_skips_to_next_maker() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).cancel_on_self_trade = true;
+    orderbook.update_fee_structure(&alice, Decimal::from(0), Decimal::from(0));
+    let own_ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(10),
+        Decimal::from(2),
+        1,
+    );
+    let other_ask = limit_order(&bob, OrderSide::Ask, Decimal::from(11), Decimal::from(2), 2);
+    orderbook.process_order(own_ask.clone(), 1).unwrap();
+    orderbook.process_order(other_ask, 2).unwrap();
+
+    let taker = limit_order(
+        &alice,
+        OrderSide::Bid,
+        Decimal::from(11),
+        Decimal::from(2),
+        3,
+    );
+    let result = orderbook.process_order(taker, 3).unwrap();
+
+    // The own maker is cancelled and the taker fills against the next maker
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].maker.main_account, bob);
+    assert_eq!(result.trades[0].price, Decimal::from(11));
+    assert_eq!(
+        result.modified_orders[&own_ask.id].status,
+        OrderStatus::CANCELLED
+    );
+    assert!(orderbook
+        .pricelevels
+        .get(&(pair, OrderSide::Ask, Decimal::from(10)))
+        .is_none_or(|qty| qty.is_zero()));
+    // The cancelled ask released its reservation and alice received the bought base
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::from(1002), Decimal::from(0))
+    );
+    assert!(orderbook.ask_books[&pair].is_empty());
+}
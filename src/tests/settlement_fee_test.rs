@@ -0,0 +1,97 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_settlement_fee_is_charged_once_per_filled_order() {
+    let (alice, carol, dave) = (account(1), account(3), account(4));
+    let mut orderbook = funded_orderbook(&[alice.clone(), carol.clone(), dave.clone()]);
+    let pair = test_pair();
+    let fee_asset = AssetId::Asset(2);
+    let settings = orderbook.pair_settings_mut(pair);
+    settings.settlement_fee = Decimal::from(1);
+    settings.settlement_fee_asset = Some(fee_asset);
+    for main in [&alice, &carol] {
+        orderbook.balances.insert(
+            (main.clone(), fee_asset),
+            (Decimal::from(10), Decimal::from(0)),
+        );
+    }
+
+    for (ts, price) in [(1, 10), (2, 11), (3, 12)] {
+        let maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        let result = orderbook.process_order(maker, ts as u64).unwrap();
+        assert!(result.trades.is_empty());
+    }
+    // Makers which did not fill are not charged, their fee is reserved
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), fee_asset)],
+        (Decimal::from(7), Decimal::from(3))
+    );
+
+    let taker = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(12),
+        Decimal::from(3),
+        4,
+    );
+    let result = orderbook.process_order(taker, 4).unwrap();
+    assert_eq!(result.trades.len(), 3);
+    // The taker and each filled maker pay the fee once
+    let pot = orderbook.fees_collector.pot.clone();
+    assert_eq!(
+        orderbook.balances[&(carol.clone(), fee_asset)],
+        (Decimal::from(9), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, fee_asset)],
+        (Decimal::from(7), Decimal::from(0))
+    );
+    assert_eq!(
+        orderbook.balances[&(pot.clone(), fee_asset)].0,
+        Decimal::from(4)
+    );
+
+    // An order without fills is not charged, its fee is released when it is cancelled
+    let resting = limit_order(
+        &carol,
+        OrderSide::Bid,
+        Decimal::from(5),
+        Decimal::from(1),
+        5,
+    );
+    orderbook.process_order(resting.clone(), 5).unwrap();
+    assert_eq!(
+        orderbook.balances[&(carol.clone(), fee_asset)],
+        (Decimal::from(8), Decimal::from(1))
+    );
+    orderbook
+        .cancel_order(pair, OrderSide::Bid, &resting.id, 5, 6)
+        .unwrap();
+    assert_eq!(
+        orderbook.balances[&(carol, fee_asset)],
+        (Decimal::from(9), Decimal::from(0))
+    );
+
+    // An account that cannot pay the fee is rejected before reserving anything
+    let rejected = limit_order(
+        &dave,
+        OrderSide::Bid,
+        Decimal::from(12),
+        Decimal::from(1),
+        7,
+    );
+    assert!(orderbook.process_order(rejected, 7).is_err());
+    assert_eq!(
+        orderbook.balances.get(&(dave, pair.quote)).unwrap(),
+        &(Decimal::from(1000), Decimal::from(0))
+    );
+}
@@ -0,0 +1,116 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use crate::SettlementInstruction;
+use orderbook_primitives::types::OrderSide;
+use polkadex_primitives::AssetId;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+#[test]
+pub fn test_settlement_instructions_reproduce_final_balances() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&alice, Decimal::new(1, 3), Decimal::new(2, 3));
+    orderbook.update_fee_structure(&bob, Decimal::new(1, 3), Decimal::new(2, 3));
+    for (ts, price) in [(1, 10), (2, 11)] {
+        let ask = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::ONE,
+            ts,
+        );
+        orderbook.process_order(ask, ts as u64).unwrap();
+    }
+    let total = |balance: &(Decimal, Decimal)| balance.0 + balance.1;
+    let before: BTreeMap<_, _> = orderbook
+        .balances
+        .iter()
+        .map(|(key, balance)| (key.clone(), total(balance)))
+        .collect();
+
+    // Bob takes both asks and rests the remainder of his bid
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(12), Decimal::from(3), 3);
+    let result = orderbook.process_order(bid, 3).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    let instructions = orderbook.settlement_instructions(&result);
+
+    let mut settled = before.clone();
+    for instruction in &instructions {
+        let key = (instruction.account.clone(), instruction.asset);
+        assert!(result.balances.contains_key(&key));
+        let balance = settled.entry(key).or_default();
+        *balance += instruction.amount;
+    }
+    for (key, balance) in &result.balances {
+        assert_eq!(
+            settled.get(key).copied().unwrap_or_default(),
+            total(balance)
+        );
+    }
+    // One instruction per account and asset, the pot only receives fees
+    let pot = orderbook.fees_collector.pot.clone();
+    assert_eq!(instructions.len(), 6);
+    assert!(instructions
+        .iter()
+        .filter(|instruction| instruction.account == pot)
+        .all(|instruction| instruction.amount > Decimal::ZERO));
+    // Bob pays his fee in the received base, so his quote only pays for the trades
+    assert!(instructions
+        .iter()
+        .any(|instruction| instruction.account == bob
+            && instruction.asset == pair.quote
+            && instruction.amount == Decimal::from(-21)));
+}
+
+#[test]
+pub fn test_settlement_instructions_include_settlement_fees() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    let pair = test_pair();
+    let fee_asset = AssetId::Asset(2);
+    let settings = orderbook.pair_settings_mut(pair);
+    settings.settlement_fee = Decimal::from(1);
+    settings.settlement_fee_asset = Some(fee_asset);
+    for main in [&alice, &bob] {
+        orderbook.balances.insert(
+            (main.clone(), fee_asset),
+            (Decimal::from(10), Decimal::from(0)),
+        );
+    }
+    let ask = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+    orderbook.process_order(ask, 1).unwrap();
+    let total = |balance: &(Decimal, Decimal)| balance.0 + balance.1;
+    let totals = |orderbook: &crate::Orderbook| -> BTreeMap<_, _> {
+        orderbook
+            .balances
+            .iter()
+            .map(|(key, balance)| (key.clone(), total(balance)))
+            .filter(|(_, total)| !total.is_zero())
+            .collect()
+    };
+    let before = totals(&orderbook);
+
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, 2);
+    let result = orderbook.process_order(bid, 2).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    let instructions = orderbook.settlement_instructions(&result);
+
+    // Applied to the state before the order, the instructions give the state of the engine
+    let mut settled = before;
+    for instruction in &instructions {
+        let key = (instruction.account.clone(), instruction.asset);
+        let balance = settled.entry(key).or_default();
+        *balance += instruction.amount;
+    }
+    settled.retain(|_, total| !total.is_zero());
+    assert_eq!(settled, totals(&orderbook));
+    // Both sides pay the flat settlement fee
+    for main in [&alice, &bob] {
+        assert!(instructions.contains(&SettlementInstruction {
+            account: main.clone(),
+            asset: fee_asset,
+            amount: Decimal::from(-1),
+        }));
+    }
+}
@@ -0,0 +1,41 @@
+use crate::tests::{account, funded_orderbook, limit_order};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_large_trades_get_size_discount() {
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.update_fee_structure(&bob, Decimal::new(1, 3), Decimal::new(1, 2));
+    // Trades of 10 or more pay half the fee
+    orderbook.set_size_discounts(vec![(Decimal::from(10), Decimal::new(5, 1))]);
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::from(100),
+        1,
+    );
+    orderbook.process_order(maker, 1).unwrap();
+
+    let small = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(5), 2);
+    let result = orderbook.process_order(small.clone(), 2).unwrap();
+    let fee = |id| {
+        result
+            .fees
+            .iter()
+            .find(|receipt| receipt.order_id == id)
+            .map(|receipt| receipt.amt)
+    };
+    assert_eq!(fee(small.id), Some(Decimal::new(5, 2)));
+
+    let large = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(20), 3);
+    let result = orderbook.process_order(large.clone(), 3).unwrap();
+    let receipt = result
+        .fees
+        .iter()
+        .find(|receipt| receipt.order_id == large.id)
+        .unwrap();
+    // 20 * 0.01 * 0.5
+    assert_eq!(receipt.amt, Decimal::new(1, 1));
+}
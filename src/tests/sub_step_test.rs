@@ -0,0 +1,45 @@
+use crate::tests::{account, funded_orderbook_with, limit_order, test_pair};
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_sub_step_residual_is_closed_and_unreserved() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut config = TradingPairConfig::default(pair.base, pair.quote);
+    config.qty_step_size = Decimal::from(1);
+    config.min_volume = Decimal::new(1, 1);
+    let mut orderbook = funded_orderbook_with(config, &[alice.clone(), bob.clone()]);
+    for main in [&alice, &bob] {
+        orderbook.update_fee_structure(main, Decimal::from(0), Decimal::from(0));
+    }
+    let maker = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(1),
+        Decimal::new(25, 1),
+        1,
+    );
+    orderbook.process_order(maker.clone(), 1).unwrap();
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(2), 2);
+    orderbook.process_order(taker, 2).unwrap();
+    // The maker is left with 0.5, less than one qty step
+    assert_eq!(
+        orderbook.balances[&(alice.clone(), pair.base)],
+        (Decimal::new(9975, 1), Decimal::new(5, 1))
+    );
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(1), Decimal::from(1), 3);
+    let result = orderbook.process_order(taker, 3).unwrap();
+    assert!(result.trades.is_empty());
+    assert_eq!(
+        result.modified_orders[&maker.id].status,
+        OrderStatus::CLOSED
+    );
+    assert_eq!(
+        orderbook.balances[&(alice, pair.base)],
+        (Decimal::from(998), Decimal::from(0))
+    );
+    assert!(orderbook.ask_books[&pair].is_empty());
+}
@@ -0,0 +1,45 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_limit_order_sweep_is_capped() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).max_sweep_levels = Some(2);
+    for (ts, price) in (10..15).enumerate() {
+        let ts = ts as i64 + 1;
+        let maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        orderbook.process_order(maker, ts as u64).unwrap();
+    }
+
+    // Would sweep all five levels without the cap
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(14), Decimal::from(5), 6);
+    let result = orderbook.process_order(taker.clone(), 6).unwrap();
+    let prices: Vec<Decimal> = result.trades.iter().map(|trade| trade.price).collect();
+    assert_eq!(prices, vec![Decimal::from(10), Decimal::from(11)]);
+    let taker = &result.modified_orders[&taker.id];
+    assert_eq!(taker.status, OrderStatus::CANCELLED);
+    assert_eq!(taker.filled_quantity, Decimal::from(2));
+    // The remainder would cross the untouched levels, so it is cancelled instead of resting
+    assert!(orderbook.bid_books[&pair].is_empty());
+    assert_eq!(orderbook.ask_books[&pair].len(), 3);
+    assert_eq!(
+        orderbook.balances[&(bob.clone(), pair.quote)],
+        (Decimal::from(979), Decimal::from(0))
+    );
+
+    // A remainder that no longer crosses the book rests
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(13), Decimal::from(3), 7);
+    let result = orderbook.process_order(taker.clone(), 7).unwrap();
+    assert_eq!(result.trades.len(), 2);
+    assert_eq!(result.modified_orders[&taker.id].status, OrderStatus::OPEN);
+    assert_eq!(orderbook.bid_books[&pair].len(), 1);
+}
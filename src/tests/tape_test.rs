@@ -0,0 +1,101 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_recent_trades_returns_latest_first() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.set_trade_tape_size(3);
+    assert!(orderbook.recent_trades(&pair, 2).is_empty());
+
+    for price in 1..=5 {
+        let ts = price * 2;
+        let maker = limit_order(
+            &alice,
+            OrderSide::Ask,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts,
+        );
+        let taker = limit_order(
+            &bob,
+            OrderSide::Bid,
+            Decimal::from(price),
+            Decimal::from(1),
+            ts + 1,
+        );
+        orderbook.process_order(maker, ts as u64).unwrap();
+        orderbook.process_order(taker, ts as u64 + 1).unwrap();
+    }
+
+    let prices = |n| -> Vec<Decimal> {
+        orderbook
+            .recent_trades(&pair, n)
+            .iter()
+            .map(|trade| trade.price)
+            .collect()
+    };
+    assert_eq!(prices(2), vec![Decimal::from(5), Decimal::from(4)]);
+    // Only the latest 3 trades are kept
+    assert_eq!(
+        prices(10),
+        vec![Decimal::from(5), Decimal::from(4), Decimal::from(3)]
+    );
+}
+
+#[test]
+pub fn test_fill_estimate_favours_the_front_of_the_queue() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    assert_eq!(
+        orderbook.fill_estimate(&pair, OrderSide::Bid, Decimal::from(10)),
+        Decimal::ZERO
+    );
+    // 2 traded at 11
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(11),
+        Decimal::from(2),
+        1,
+    );
+    orderbook.process_order(ask, 1).unwrap();
+    let bid = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::from(2), 2);
+    orderbook.process_order(bid, 2).unwrap();
+    let bids = [(10, 1, 3), (9, 5, 4), (8, 10, 5)];
+    for (price, qty, ts) in bids {
+        let bid = limit_order(
+            &bob,
+            OrderSide::Bid,
+            Decimal::from(price),
+            Decimal::from(qty),
+            ts,
+        );
+        orderbook.process_order(bid, ts as u64).unwrap();
+    }
+    let ask = limit_order(
+        &alice,
+        OrderSide::Ask,
+        Decimal::from(12),
+        Decimal::from(1),
+        6,
+    );
+    orderbook.process_order(ask, 6).unwrap();
+
+    // Joining the best bid waits behind 1, far from the touch behind 16
+    let touch = orderbook.fill_estimate(&pair, OrderSide::Bid, Decimal::from(10));
+    let deep = orderbook.fill_estimate(&pair, OrderSide::Bid, Decimal::from(7));
+    assert_eq!(touch, Decimal::from(2) / Decimal::from(3));
+    assert_eq!(deep, Decimal::from(2) / Decimal::from(18));
+    assert!(touch > deep);
+    // Improving the best bid leaves no queue ahead and crossing fills right away
+    let improving = orderbook.fill_estimate(&pair, OrderSide::Bid, Decimal::new(105, 1));
+    assert_eq!(improving, Decimal::ONE);
+    assert_eq!(
+        orderbook.fill_estimate(&pair, OrderSide::Bid, Decimal::from(12)),
+        Decimal::ONE
+    );
+}
@@ -20,7 +20,9 @@ pub fn test_trade_price() {
     taker_order.qty = Decimal::from_f32(20.0).unwrap();
 
     let mut orderbook = Orderbook::new();
-    orderbook.add_trading_pair(TradingPairConfig::default(pair.base, pair.quote));
+    orderbook
+        .add_trading_pair(TradingPairConfig::default(pair.base, pair.quote))
+        .unwrap();
     // Add Maker balances
     orderbook.balances.insert(
         (maker_order.main_account.clone(), AssetId::Asset(1)),
@@ -0,0 +1,63 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::{OrderSide, OrderStatus};
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_will_match_skips_expired_makers() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).order_ttl = Some(100);
+    // The best ask expires at 101, the worse one at 150
+    let expired = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+    let live = limit_order(&alice, OrderSide::Ask, Decimal::from(11), Decimal::ONE, 50);
+    orderbook.process_order_at(expired.clone(), 1, 1).unwrap();
+    orderbook.process_order_at(live.clone(), 2, 50).unwrap();
+
+    let between = limit_order(
+        &bob,
+        OrderSide::Bid,
+        Decimal::new(105, 1),
+        Decimal::ONE,
+        120,
+    );
+    assert!(orderbook.will_match_at(&between, 100));
+    assert!(!orderbook.will_match_at(&between, 120));
+    let at_live = limit_order(&bob, OrderSide::Bid, Decimal::from(11), Decimal::ONE, 121);
+    assert!(orderbook.will_match_at(&at_live, 120));
+    // Past the ttl of both makers nothing matches
+    assert!(!orderbook.will_match_at(&at_live, 151));
+    // The timestamp of the taker is set by the client and does not expire makers
+    let future = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, 200);
+    assert!(orderbook.will_match(&future));
+
+    // Processing cancels the expired maker instead of trading against it
+    let result = orderbook.process_order_at(between.clone(), 3, 120).unwrap();
+    assert!(result.trades.is_empty());
+    assert_eq!(
+        result.modified_orders[&expired.id].status,
+        OrderStatus::CANCELLED
+    );
+    assert_eq!(
+        orderbook.best_price(&pair, OrderSide::Ask),
+        Some(Decimal::from(11))
+    );
+}
+
+#[test]
+pub fn test_makers_expire_from_the_time_they_rested() {
+    let pair = test_pair();
+    let (alice, bob) = (account(1), account(2));
+    let mut orderbook = funded_orderbook(&[alice.clone(), bob.clone()]);
+    orderbook.pair_settings_mut(pair).order_ttl = Some(100);
+    // The client timestamp of the maker is far older than the time it rested at
+    let maker = limit_order(&alice, OrderSide::Ask, Decimal::from(10), Decimal::ONE, 1);
+    orderbook.process_order_at(maker.clone(), 1, 1_000).unwrap();
+
+    let taker = limit_order(&bob, OrderSide::Bid, Decimal::from(10), Decimal::ONE, 2);
+    assert!(orderbook.will_match_at(&taker, 1_100));
+    assert!(!orderbook.will_match_at(&taker, 1_101));
+    let result = orderbook.process_order_at(taker, 2, 1_050).unwrap();
+    assert_eq!(result.trades.len(), 1);
+    assert_eq!(result.trades[0].maker.id, maker.id);
+}
@@ -0,0 +1,43 @@
+use crate::tests::{account, funded_orderbook, limit_order, test_pair};
+use orderbook_primitives::types::OrderSide;
+use rust_decimal::Decimal;
+
+#[test]
+pub fn test_twap_mid_weights_mids_by_time() {
+    let pair = test_pair();
+    let alice = account(1);
+    let mut orderbook = funded_orderbook(&[alice.clone()]);
+    assert_eq!(orderbook.twap_mid(&pair, 30), None);
+    let order = |side, price: i64, ts: i64| {
+        limit_order(&alice, side, Decimal::from(price), Decimal::from(1), ts)
+    };
+    orderbook
+        .process_order(order(OrderSide::Bid, 9, 1), 1)
+        .unwrap();
+    orderbook
+        .process_order(order(OrderSide::Ask, 11, 2), 2)
+        .unwrap();
+    orderbook.record_bbo(0);
+
+    orderbook
+        .cancel_order(pair, OrderSide::Ask, &order(OrderSide::Ask, 11, 2).id, 3, 3)
+        .unwrap();
+    orderbook
+        .process_order(order(OrderSide::Ask, 15, 4), 4)
+        .unwrap();
+    orderbook.record_bbo(10);
+
+    orderbook
+        .process_order(order(OrderSide::Ask, 13, 5), 5)
+        .unwrap();
+    orderbook.record_bbo(30);
+
+    // Mid 10 for 10ms and 12 for 20ms
+    assert_eq!(
+        orderbook.twap_mid(&pair, 30),
+        Some(Decimal::from(340) / Decimal::from(30))
+    );
+    assert_eq!(orderbook.twap_mid(&pair, 20), Some(Decimal::from(12)));
+    // An empty window returns the latest mid
+    assert_eq!(orderbook.twap_mid(&pair, 0), Some(Decimal::from(11)));
+}
@@ -0,0 +1,50 @@
+use crate::Orderbook;
+use orderbook_primitives::types::TradingPair;
+use rust_decimal::Decimal;
+
+/// Maximum number of mid price samples kept per pair
+pub const MAX_MID_SAMPLES: usize = 4096;
+
+impl Orderbook {
+    /// Samples the mid price of every pair at `now`, in milliseconds. Pairs without a mid price
+    /// are not sampled. Only the latest `MAX_MID_SAMPLES` samples of a pair are kept.
+    pub fn record_bbo(&mut self, now: u64) {
+        let mids: Vec<(TradingPair, Decimal)> = self
+            .trading_pairs
+            .keys()
+            .filter_map(|pair| Some((*pair, self.mid_price(pair)?)))
+            .collect();
+        for (pair, mid) in mids {
+            let samples = self.mid_samples.entry(pair).or_default();
+            samples.push_back((now, mid));
+            if samples.len() > MAX_MID_SAMPLES {
+                samples.pop_front();
+            }
+        }
+    }
+
+    /// Returns the time weighted average of the sampled mid prices of the pair over the
+    /// `window` milliseconds before the latest sample. Every sample holds until the next one.
+    /// Returns the latest mid if the window holds no time, `None` if the pair was not sampled.
+    pub fn twap_mid(&self, pair: &TradingPair, window: u64) -> Option<Decimal> {
+        let samples = self.mid_samples.get(pair)?;
+        let &(end, last_mid) = samples.back()?;
+        let start = end.saturating_sub(window);
+        let mut weighted = Decimal::ZERO;
+        let mut total = 0u64;
+        for (current, next) in samples.iter().zip(samples.iter().skip(1)) {
+            let (from, mid) = *current;
+            let until = next.0;
+            if until <= start {
+                continue;
+            }
+            let held = until.saturating_sub(from.max(start));
+            weighted = weighted.saturating_add(mid.saturating_mul(Decimal::from(held)));
+            total = total.saturating_add(held);
+        }
+        if total == 0 {
+            return Some(last_mid);
+        }
+        weighted.checked_div(Decimal::from(total))
+    }
+}
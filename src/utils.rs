@@ -1,7 +1,13 @@
-use orderbook_primitives::types::{Order, OrderSide, OrderStatus, OrderType, Trade, TradingPair};
-use polkadex_primitives::AssetId;
+use crate::error::Error;
+use crate::settings::{PairSettings, PriceMode};
+use orderbook_primitives::ocex::TradingPairConfig;
+use orderbook_primitives::types::{
+    Order, OrderId, OrderSide, OrderStatus, OrderType, Trade, TradingPair,
+};
+use polkadex_primitives::{AccountId, AssetId};
 use rust_decimal::prelude::Zero;
 use rust_decimal::Decimal;
+use std::collections::{BTreeMap, BinaryHeap};
 
 /// Calculate the amount of assets that will be received and given away when a trade settles
 /// # Arguments
@@ -33,18 +39,25 @@ pub fn calculate_assets_flows_from_trade(
     }
 }
 
+/// Returns true if the volume is below the min volume of the market. The bound is inclusive,
+/// a volume of exactly `min_volume` is valid: such orders are accepted, keep resting and
+/// keep their price level. Only volumes strictly below it are closed or dropped.
+pub fn is_below_min_volume(volume: Decimal, config: &TradingPairConfig) -> bool {
+    volume < config.min_volume()
+}
+
 /// Checks if there is enough unreserved balance for closing limit orders in trades
 ///
 /// # Parameters
 /// * `order`:  a reference to an Order object
-/// * `min_volume`: minimum volume allowed for the trading pair
+/// * `config`: config of the trading pair, for its minimum volume
 ///
 /// # Returns
 /// * `Decimal`: Returns un reserve locked balance that needs
 /// to be unlocked for closing limit orders in trades
 pub fn check_unreserved_balance_for_close_limit_orders_in_trades(
     order: &Order,
-    min_volume: Decimal,
+    config: &TradingPairConfig,
 ) -> Decimal {
     let amount = if order.side == OrderSide::Ask {
         order.qty.saturating_sub(order.filled_quantity)
@@ -56,48 +69,296 @@ pub fn check_unreserved_balance_for_close_limit_orders_in_trades(
     };
 
     if (order.order_type == OrderType::LIMIT && amount != Decimal::zero())
-        && (order.status == OrderStatus::CLOSED || order.available_volume(None) < min_volume)
+        && (order.status == OrderStatus::CLOSED
+            || is_below_min_volume(order.available_volume(None), config))
     {
         return amount;
     }
     Decimal::zero()
 }
 
+/// Returns the asset and amount reserved for the unfilled part of a resting limit order.
+pub fn resting_reservation(order: &Order) -> (AssetId, Decimal) {
+    let (asset, amount) = match order.side {
+        OrderSide::Ask => (
+            order.pair.base,
+            order.qty.saturating_sub(order.filled_quantity),
+        ),
+        OrderSide::Bid => (order.pair.quote, order.available_volume(None)),
+    };
+    (asset, Order::rounding_off(amount))
+}
+
+/// Returns true if an order resting since `rested_since` rested longer than `ttl`
+/// milliseconds at `now`.
+pub fn is_expired(rested_since: u64, ttl: u64, now: u64) -> bool {
+    rested_since.saturating_add(ttl) < now
+}
+
+/// Returns the time the order started resting, as recorded by the engine clock. Orders
+/// loaded into the book rest since their timestamp.
+pub fn rested_since(order: &Order, rested_at: &BTreeMap<OrderId, u64>) -> u64 {
+    rested_at
+        .get(&order.id)
+        .copied()
+        .unwrap_or_else(|| u64::try_from(order.timestamp).unwrap_or_default())
+}
+
+/// Returns the protection price of a market order, the worst price it accepts. Market orders
+/// carry it in their price, a zero price means the order is unprotected.
+pub fn protection_price(order: &Order) -> Option<Decimal> {
+    (order.order_type == OrderType::MARKET && order.price > Decimal::zero()).then_some(order.price)
+}
+
 // check if orders can be matched
-// if taker is market order, it can be matched with any price will always return true.
+// if taker is market order, it can be matched with any price up to its protection price.
 // if taker is limit order, it can be matched with maker if maker price is better than taker price
 pub fn will_orders_match(taker: &Order, maker: &Order) -> bool {
-    if taker.order_type == OrderType::MARKET {
-        return true;
-    }
+    let limit = match taker.order_type {
+        OrderType::LIMIT => taker.price,
+        OrderType::MARKET => match protection_price(taker) {
+            Some(protection) => protection,
+            None => return true,
+        },
+    };
     match taker.side {
-        OrderSide::Ask => taker.price.le(&maker.price),
-        OrderSide::Bid => maker.price.le(&taker.price),
+        OrderSide::Ask => limit.le(&maker.price),
+        OrderSide::Bid => maker.price.le(&limit),
+    }
+}
+
+/// Validates that the steps and volume limits of a trading pair config are positive and
+/// mutually consistent: the min volume must not exceed the max volume, and the qty bounds of
+/// the pair's settings must allow at least one qty step.
+pub fn validate_pair_config(
+    config: &TradingPairConfig,
+    settings: &PairSettings,
+) -> Result<(), Error> {
+    let positive = [
+        ("qty_step_size", config.qty_step_size),
+        ("price_tick_size", config.price_tick_size),
+        ("min_volume", config.min_volume),
+        ("max_volume", config.max_volume),
+    ];
+    for (name, value) in positive {
+        if value <= Decimal::zero() {
+            return Err(Error::InvalidPairConfig(format!(
+                "{name} must be positive, got {value}"
+            )));
+        }
+    }
+    if config.min_volume > config.max_volume {
+        return Err(Error::InvalidPairConfig(format!(
+            "min_volume {} exceeds max_volume {}",
+            config.min_volume, config.max_volume
+        )));
+    }
+    for (name, bound) in [("min_qty", settings.min_qty), ("max_qty", settings.max_qty)] {
+        if let Some(bound) = bound.filter(|bound| *bound < config.qty_step_size) {
+            return Err(Error::InvalidPairConfig(format!(
+                "qty_step_size {} exceeds {name} {bound}",
+                config.qty_step_size
+            )));
+        }
+    }
+    if let (Some(min_qty), Some(max_qty)) = (settings.min_qty, settings.max_qty) {
+        if min_qty > max_qty {
+            return Err(Error::InvalidPairConfig(format!(
+                "min_qty {min_qty} exceeds max_qty {max_qty}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Pops the best maker from the book. Among the makers resting at the best price the one with
+/// the highest priority boost is preferred, time priority breaks ties between equal boosts.
+pub fn pop_best_maker<F: Fn(&AccountId) -> u32>(
+    book: &mut BinaryHeap<Order>,
+    priority_boost: F,
+) -> Option<Order> {
+    let best = book.pop()?;
+    let price = best.price;
+    let mut candidates = vec![best];
+    while book.peek().is_some_and(|order| order.price == price) {
+        candidates.extend(book.pop());
+    }
+    // Candidates are in time priority, so the first max boost wins
+    let mut best_index = 0;
+    let mut best_boost = priority_boost(&candidates[0].main_account);
+    for (index, candidate) in candidates.iter().enumerate().skip(1) {
+        let boost = priority_boost(&candidate.main_account);
+        if boost > best_boost {
+            best_index = index;
+            best_boost = boost;
+        }
+    }
+    let best = candidates.swap_remove(best_index);
+    for candidate in candidates {
+        book.push(candidate);
+    }
+    Some(best)
+}
+
+/// Matches the taker against the makers of the book in priority order, makers are taken out of
+/// the book with `pop_best`, given the taker as matched so far. Makers that keep enough volume
+/// are pushed back into the book. Returns the generated trades.
+pub fn match_against_book<P>(
+    taker: &mut Order,
+    book: &mut BinaryHeap<Order>,
+    config: &TradingPairConfig,
+    settings: &PairSettings,
+    mut pop_best: P,
+) -> Vec<Trade>
+where
+    P: FnMut(&mut BinaryHeap<Order>, &Order) -> Option<Order>,
+{
+    let price_mode = settings.price_mode;
+    let mut trades = Vec::new();
+    // Consume until the cache is empty
+    while !book.is_empty() {
+        // Get the first(best) order from the book, stop if none is left to match
+        let Some(mut other) = pop_best(book, taker) else {
+            break;
+        };
+        //if takers volume is less than the min volume for the market,
+        // close the taker order and push the other order back into the book
+
+        if is_below_min_volume(taker.available_volume(Some(other.price)), config) {
+            taker.status = OrderStatus::CLOSED;
+            book.push(other);
+            break;
+        }
+
+        if !will_orders_match(taker, &other) {
+            // other is added back into the book
+            book.push(other);
+            break;
+        }
+
+        let price = trade_price(taker, &other, price_mode, config.price_tick_size);
+        if let Some(mut trade) = execute(taker, &mut other, price, config.qty_step_size) {
+            if is_below_min_volume(trade.maker.available_volume(Some(other.price)), config) {
+                // We will be dropping the maker order below if this condition is true
+                //why is maker not being removed from the heap ?
+                trade.maker.status = OrderStatus::CLOSED
+            }
+
+            // Check if other has enough volume to save it back to queue otherwise close it
+            if !is_below_min_volume(other.available_volume(None), config) {
+                book.push(other.clone());
+            } else {
+                other.status = OrderStatus::CLOSED
+            }
+            trades.push(trade);
+        } else {
+            // Other is not changed here so no need to update state change
+            book.push(other);
+            break;
+        }
     }
+    trades
 }
 
-// match two orders and return trade
-pub fn execute(taker: &mut Order, maker: &mut Order, qty_step_size: Decimal) -> Option<Trade> {
-    let price = maker.price;
+// Returns true if filling the taker against the maker at the price would leave the maker with
+// a residual below the min volume of the market
+pub(crate) fn leaves_sub_min_residual(
+    taker: &Order,
+    maker: &Order,
+    price: Decimal,
+    config: &TradingPairConfig,
+) -> bool {
+    let (mut taker, mut maker) = (taker.clone(), maker.clone());
+    if execute(&mut taker, &mut maker, price, config.qty_step_size).is_none() {
+        return false;
+    }
+    let remaining = maker.qty.saturating_sub(maker.filled_quantity);
+    remaining > Decimal::zero() && is_below_min_volume(maker.available_volume(None), config)
+}
 
+/// Rounds the price to a multiple of the tick size, up or down. Prices are left as they are
+/// if the tick size is not positive.
+pub fn round_to_tick(price: Decimal, tick_size: Decimal, round_up: bool) -> Decimal {
+    if tick_size <= Decimal::zero() {
+        return price;
+    }
+    let ticks = price.checked_div(tick_size).unwrap_or(Decimal::zero());
+    let ticks = if round_up {
+        ticks.ceil()
+    } else {
+        ticks.floor()
+    };
+    ticks.saturating_mul(tick_size)
+}
+
+/// Returns the price at which the taker trades with the maker. Computed prices are rounded to
+/// the tick towards the maker price, so the trade price always lies between both limit prices
+/// and the maker never trades worse than its own price.
+pub fn trade_price(
+    taker: &Order,
+    maker: &Order,
+    price_mode: PriceMode,
+    tick_size: Decimal,
+) -> Decimal {
+    match price_mode {
+        PriceMode::MakerPrice => maker.price,
+        PriceMode::Midpoint if taker.order_type == OrderType::MARKET => maker.price,
+        PriceMode::Midpoint => {
+            let midpoint = taker
+                .price
+                .saturating_add(maker.price)
+                .checked_div(Decimal::TWO)
+                .unwrap_or(maker.price);
+            // Asks are rounded down and bids up to their maker price
+            round_to_tick(midpoint, tick_size, maker.side == OrderSide::Bid)
+        }
+    }
+}
+
+// match two orders at the given price and return trade
+pub fn execute(
+    taker: &mut Order,
+    maker: &mut Order,
+    price: Decimal,
+    qty_step_size: Decimal,
+) -> Option<Trade> {
     let mut quantity_available = match (taker.side, taker.order_type) {
         (OrderSide::Bid, OrderType::MARKET) => {
             // If Market order is defined in base quantity
             if !taker.qty.is_zero() {
-                taker.qty.saturating_sub(taker.filled_quantity)
+                let unfilled = taker.qty.saturating_sub(taker.filled_quantity);
+                if taker.quote_order_qty.is_zero() {
+                    unfilled
+                } else {
+                    // Capped by a quote budget it buys no more whole steps than the budget
+                    // left affords at the price
+                    let budget = taker.quote_order_qty.saturating_sub(
+                        taker.avg_filled_price.saturating_mul(taker.filled_quantity),
+                    );
+                    let steps = budget
+                        .checked_div(price.saturating_mul(qty_step_size))
+                        .unwrap_or_else(Decimal::zero)
+                        .floor();
+                    let available_qty = unfilled.min(steps.saturating_mul(qty_step_size));
+                    if available_qty.is_zero() {
+                        return None;
+                    }
+                    available_qty
+                }
             } else {
                 // Get quote required and divide it by current price to get needed_base
                 let mut available_qty = Order::rounding_off(
                     taker
-                        .available_volume(Some(maker.price))
+                        .available_volume(Some(price))
                         .checked_div(price)
                         .unwrap_or_else(Decimal::zero),
                 );
-                // Convert it into a multiple of qty_step_size
+                // Round it down to a multiple of qty_step_size
                 available_qty = Order::rounding_off(
                     available_qty
                         .checked_div(qty_step_size)
                         .unwrap_or_else(Decimal::zero)
+                        .floor()
                         .saturating_mul(qty_step_size),
                 );
                 // If available_quantity is zero don't execute the trade0